@@ -0,0 +1,225 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path};
+
+use crate::error::TextractorError;
+use crate::generated;
+use crate::language;
+use crate::limits;
+use crate::scan::{self, FileInfo, ScanOptions, SkipReason, SkippedFile};
+
+/// Most files read from one archive; a zip bomb can hold millions of tiny entries
+const MAX_ENTRIES: usize = 10_000;
+/// Most bytes extracted from one archive, over all its files
+const MAX_TOTAL_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Clone, Copy)]
+enum Format {
+  Zip,
+  Tar,
+  TarGz,
+}
+
+fn format(path: &Path) -> Option<Format> {
+  let name = path.file_name()?.to_string_lossy().to_lowercase();
+  if name.ends_with(".zip") {
+    Some(Format::Zip)
+  } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+    Some(Format::TarGz)
+  } else if name.ends_with(".tar") {
+    Some(Format::Tar)
+  } else {
+    None
+  }
+}
+
+/// Whether `path` is an archive whose contents a load reads instead of it
+pub fn is_archive(path: &Path) -> bool {
+  format(path).is_some()
+}
+
+/// Reads the entries of one archive through the scan filters
+struct Extraction<'a> {
+  /// The archive's path; entries are shown under it
+  archive: String,
+  options: &'a ScanOptions,
+  files: usize,
+  total_size: u64,
+  on_file: &'a mut dyn FnMut(FileInfo),
+  on_skip: &'a mut dyn FnMut(SkippedFile),
+}
+
+impl Extraction<'_> {
+  /// Report that a guard stopped the extraction
+  fn stop(&mut self, why: String) {
+    log::warn!("Stopped reading archive {}: {}", self.archive, why);
+    (self.on_skip)(SkippedFile {
+      path: self.archive.clone(),
+      reason: SkipReason::TooLarge,
+      message: format!("{} {why}; the rest of it was not read", self.archive),
+    });
+  }
+
+  fn skip(&mut self, error: TextractorError) {
+    (self.on_skip)(SkippedFile {
+      path: error.path().unwrap_or(&self.archive).to_string(),
+      reason: SkipReason::of(&error),
+      message: error.to_string(),
+    });
+  }
+
+  /// Read one file entry; `false` once the extraction has to stop
+  fn entry(&mut self, name: &Path, size: u64, modified_ms: Option<u64>, reader: &mut dyn Read) -> bool {
+    // Entry names cannot escape the archive, as nothing is written to disk,
+    // but `..` and `/` would make confusing paths
+    if name.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+      return true;
+    }
+    let relative: std::path::PathBuf = name.components().filter(|c| matches!(c, Component::Normal(_))).collect();
+    let relative_str = relative.to_string_lossy().replace('\\', "/");
+    let shown = format!("{}/{}", self.archive, relative_str);
+
+    if relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')) {
+      (self.on_skip)(SkippedFile {
+        path: shown,
+        reason: SkipReason::Hidden,
+        message: "hidden file or directory".to_string(),
+      });
+      return true;
+    }
+    if self.options.in_excluded_dir(&relative) || !self.options.accepts_file(&relative) {
+      return true;
+    }
+    if !scan::is_text_file(&relative) {
+      self.skip(TextractorError::BinaryFile { path: shown });
+      return true;
+    }
+    let max_file_size = limits::max_file_size() as u64;
+    if size > max_file_size {
+      self.skip(TextractorError::FileTooLarge {
+        path: shown,
+        size,
+        limit: max_file_size,
+      });
+      return true;
+    }
+
+    self.files += 1;
+    if self.files > MAX_ENTRIES {
+      self.stop(format!("holds more than {MAX_ENTRIES} files"));
+      return false;
+    }
+    // Declared sizes can lie, so the read itself is capped too
+    let mut bytes = Vec::new();
+    if let Err(e) = reader.take(max_file_size + 1).read_to_end(&mut bytes) {
+      self.skip(TextractorError::IoError {
+        path: shown,
+        message: e.to_string(),
+      });
+      return true;
+    }
+    self.total_size += bytes.len() as u64;
+    if self.total_size > MAX_TOTAL_SIZE {
+      self.stop(format!("expands to more than {} MB", MAX_TOTAL_SIZE / (1024 * 1024)));
+      return false;
+    }
+    if bytes.len() as u64 > max_file_size {
+      self.skip(TextractorError::FileTooLarge {
+        path: shown,
+        size: bytes.len() as u64,
+        limit: max_file_size,
+      });
+      return true;
+    }
+    let Ok(content) = String::from_utf8(bytes) else {
+      self.skip(TextractorError::EncodingError { path: shown });
+      return true;
+    };
+
+    let name = relative.file_name().map_or_else(|| relative_str.clone(), |n| n.to_string_lossy().to_string());
+    (self.on_file)(FileInfo {
+      language: language::detect(&name, &content),
+      generated: generated::is_generated(&name, &content),
+      name,
+      path: shown,
+      size: content.len() as u64,
+      content,
+      is_text: true,
+      modified_ms,
+      root: None,
+      relative_path: None,
+      binary: None,
+    });
+    true
+  }
+
+  fn zip(&mut self, file: File) -> Result<(), String> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+    for index in 0..zip.len() {
+      let mut entry = zip.by_index(index).map_err(|e| e.to_string())?;
+      if !entry.is_file() || entry.is_symlink() {
+        continue;
+      }
+      let Some(name) = entry.enclosed_name() else {
+        continue;
+      };
+      let size = entry.size();
+      if !self.entry(&name, size, None, &mut entry) {
+        break;
+      }
+    }
+    Ok(())
+  }
+
+  fn tar(&mut self, reader: impl Read) -> Result<(), String> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries().map_err(|e| e.to_string())? {
+      let mut entry = entry.map_err(|e| e.to_string())?;
+      if !entry.header().entry_type().is_file() {
+        continue;
+      }
+      let name = entry.path().map_err(|e| e.to_string())?.into_owned();
+      let size = entry.size();
+      let modified_ms = entry.header().mtime().ok().map(|seconds| seconds * 1000);
+      if !self.entry(&name, size, modified_ms, &mut entry) {
+        break;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Read the text files inside the archive at `path` as if its contents had
+/// been dropped, through the same filters and size limit, without writing
+/// anything to disk. Entries are shown as `archive.zip/inner/path`. Reading
+/// stops with a skipped entry for the archive once it holds too many files
+/// or expands too far.
+pub fn read_archive(
+  path: &Path,
+  options: &ScanOptions,
+  on_file: &mut dyn FnMut(FileInfo),
+  on_skip: &mut dyn FnMut(SkippedFile),
+) {
+  let Some(format) = format(path) else {
+    return;
+  };
+  let mut extraction = Extraction {
+    archive: path.to_string_lossy().to_string(),
+    options,
+    files: 0,
+    total_size: 0,
+    on_file,
+    on_skip,
+  };
+
+  let result = File::open(path).map_err(|e| e.to_string()).and_then(|file| match format {
+    Format::Zip => extraction.zip(file),
+    Format::Tar => extraction.tar(BufReader::new(file)),
+    Format::TarGz => extraction.tar(flate2::read::GzDecoder::new(BufReader::new(file))),
+  });
+  if let Err(message) = result {
+    log::warn!("Failed to read archive {}: {}", extraction.archive, message);
+    let path = extraction.archive.clone();
+    extraction.skip(TextractorError::IoError { path, message });
+  }
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+/// What is known about a file that is not text
+#[derive(Clone, serde::Serialize)]
+pub struct BinaryInfo {
+  /// MIME type from the file's magic bytes
+  pub mime: Option<String>,
+  /// Pixel width and height, for images
+  pub width: Option<usize>,
+  pub height: Option<usize>,
+  /// One line for exports: `logo.png, 512×512 PNG, 34 KB`
+  pub description: String,
+}
+
+/// `bytes` in B, KB, MB or GB, with 1 KB = 1024 bytes
+fn format_size(bytes: u64) -> String {
+  const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+  if bytes < 1024 {
+    return format!("{bytes} B");
+  }
+  let mut size = bytes as f64 / 1024.0;
+  let mut unit = 0;
+  while size >= 1024.0 && unit + 1 < UNITS.len() {
+    size /= 1024.0;
+    unit += 1;
+  }
+  if size < 10.0 {
+    format!("{size:.1} {}", UNITS[unit])
+  } else {
+    format!("{size:.0} {}", UNITS[unit])
+  }
+}
+
+/// Type, and for images dimensions, of the non-text file at `path` of `size`
+/// bytes, read from its header
+pub fn inspect(path: &Path, size: u64) -> BinaryInfo {
+  let kind = infer::get_from_path(path).ok().flatten();
+  let dimensions = kind
+    .filter(|kind| kind.matcher_type() == infer::MatcherType::Image)
+    .and_then(|_| imagesize::size(path).ok());
+
+  let name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().to_string());
+  let mut parts = vec![name];
+  let format = kind.map_or_else(|| "binary".to_string(), |kind| kind.extension().to_uppercase());
+  parts.push(match &dimensions {
+    Some(size) => format!("{}×{} {format}", size.width, size.height),
+    None => format,
+  });
+  parts.push(format_size(size));
+
+  BinaryInfo {
+    mime: kind.map(|kind| kind.mime_type().to_string()),
+    width: dimensions.as_ref().map(|size| size.width),
+    height: dimensions.as_ref().map(|size| size.height),
+    description: parts.join(", "),
+  }
+}
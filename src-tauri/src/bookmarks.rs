@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::ranges::{self, LineRange};
+use crate::scan::{self, FileInfo};
+use crate::session::{new_id, write_json};
+
+/// A named file region kept across sessions
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Bookmark {
+  pub id: String,
+  pub name: String,
+  pub path: String,
+  /// Line ranges in `ranges::parse` syntax, e.g. `100-250,400-450`
+  pub ranges: String,
+  pub created_ms: u64,
+}
+
+/// Tauri-managed bookmarks, persisted to `path` on every change
+pub struct BookmarkStore {
+  path: Option<PathBuf>,
+  bookmarks: Mutex<Vec<Bookmark>>,
+}
+
+impl BookmarkStore {
+  pub fn load(path: Option<PathBuf>) -> Self {
+    let bookmarks = path
+      .as_ref()
+      .and_then(|p| fs::read_to_string(p).ok())
+      .and_then(|raw| match serde_json::from_str(&raw) {
+        Ok(bookmarks) => Some(bookmarks),
+        Err(e) => {
+          log::warn!("Ignoring invalid bookmarks file: {}", e);
+          None
+        }
+      })
+      .unwrap_or_default();
+
+    Self {
+      path,
+      bookmarks: Mutex::new(bookmarks),
+    }
+  }
+
+  pub fn list(&self) -> Vec<Bookmark> {
+    self.bookmarks.lock().unwrap_or_else(|e| e.into_inner()).clone()
+  }
+
+  pub fn create(&self, name: String, path: String, ranges: String) -> Result<Bookmark, String> {
+    ranges::parse(&ranges)?;
+    let created_ms = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map_or(0, |d| d.as_millis() as u64);
+
+    let mut bookmarks = self.bookmarks.lock().unwrap_or_else(|e| e.into_inner());
+    let mut id = new_id(created_ms);
+    while bookmarks.iter().any(|b| b.id == id) {
+      id = new_id(created_ms);
+    }
+    let bookmark = Bookmark {
+      id,
+      name,
+      path,
+      ranges,
+      created_ms,
+    };
+    bookmarks.push(bookmark.clone());
+    self.persist(&bookmarks)?;
+    Ok(bookmark)
+  }
+
+  /// Remove a bookmark; returns whether it existed
+  pub fn delete(&self, id: &str) -> Result<bool, String> {
+    let mut bookmarks = self.bookmarks.lock().unwrap_or_else(|e| e.into_inner());
+    let before = bookmarks.len();
+    bookmarks.retain(|b| b.id != id);
+    if bookmarks.len() == before {
+      return Ok(false);
+    }
+    self.persist(&bookmarks)?;
+    Ok(true)
+  }
+
+  fn persist(&self, bookmarks: &[Bookmark]) -> Result<(), String> {
+    match &self.path {
+      Some(path) => write_json(path, &bookmarks),
+      None => Ok(()),
+    }
+  }
+}
+
+/// Read every bookmarked file from disk, keeping only its bookmarked regions.
+/// Bookmarks on the same file are combined into one entry. Returns the files
+/// and the paths that could no longer be read.
+pub fn read_regions(bookmarks: &[Bookmark]) -> (Vec<FileInfo>, Vec<String>) {
+  let mut by_path: BTreeMap<&str, Vec<LineRange>> = BTreeMap::new();
+  for bookmark in bookmarks {
+    if let Ok(line_ranges) = ranges::parse(&bookmark.ranges) {
+      by_path.entry(&bookmark.path).or_default().extend(line_ranges);
+    }
+  }
+
+  let mut files = Vec::new();
+  let mut missing = Vec::new();
+  for (path, line_ranges) in by_path {
+    let merged = ranges::merge(line_ranges);
+    match scan::read_single_file(Path::new(path)).filter(|info| info.is_text) {
+      Some(mut info) => {
+        info.content = ranges::extract_with(&info.content, &merged, |text, _| text.to_string());
+        files.push(info);
+      }
+      None => missing.push(path.to_string()),
+    }
+  }
+  (files, missing)
+}
@@ -0,0 +1,60 @@
+use std::sync::Mutex;
+
+/// Tauri-managed token budget for the current session
+#[derive(Default)]
+pub struct TokenBudget {
+  limit: Mutex<Option<usize>>,
+}
+
+impl TokenBudget {
+  pub fn set(&self, limit: Option<usize>) {
+    *self.limit.lock().unwrap_or_else(|e| e.into_inner()) = limit.filter(|&n| n > 0);
+  }
+
+  pub fn get(&self) -> Option<usize> {
+    *self.limit.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+/// Emitted as `budget-exceeded` when a running token total first crosses the budget
+#[derive(Clone, serde::Serialize)]
+pub struct BudgetExceeded {
+  /// `processing` or `bundle`
+  pub source: &'static str,
+  pub budget: usize,
+  pub total_tokens: usize,
+  /// The file whose tokens pushed the total over the budget
+  pub file_id: Option<String>,
+  pub path: String,
+}
+
+/// Running token total for one pass over the files
+pub struct BudgetTracker {
+  limit: usize,
+  total: usize,
+  tripped: bool,
+}
+
+impl BudgetTracker {
+  pub fn new(limit: usize) -> Self {
+    Self {
+      limit,
+      total: 0,
+      tripped: false,
+    }
+  }
+
+  pub fn total(&self) -> usize {
+    self.total
+  }
+
+  /// Add `tokens`; true only for the addition that first exceeds the budget
+  pub fn add(&mut self, tokens: usize) -> bool {
+    self.total += tokens;
+    if !self.tripped && self.total > self.limit {
+      self.tripped = true;
+      return true;
+    }
+    false
+  }
+}
@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tiktoken_rs::CoreBPE;
+
+use crate::session::new_id;
+
+/// A file as recorded in a saved bundle; only its hash and token count are kept
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct BundleFile {
+  pub path: String,
+  pub hash: String,
+  pub tokens: usize,
+}
+
+/// A saved snapshot of an exported file set
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Bundle {
+  pub id: String,
+  pub name: String,
+  pub created_ms: u64,
+  pub files: Vec<BundleFile>,
+}
+
+/// Bundle listing entry, without the per-file records
+#[derive(serde::Serialize)]
+pub struct BundleSummary {
+  pub id: String,
+  pub name: String,
+  pub created_ms: u64,
+  pub file_count: usize,
+  pub total_tokens: usize,
+}
+
+/// A file to record in a new bundle
+#[derive(serde::Deserialize)]
+pub struct BundleFileInput {
+  pub path: String,
+  pub content: String,
+}
+
+/// Token change for a single path between two bundles
+#[derive(serde::Serialize)]
+pub struct FileDelta {
+  pub path: String,
+  pub old_tokens: Option<usize>,
+  pub new_tokens: Option<usize>,
+  pub token_delta: i64,
+}
+
+#[derive(serde::Serialize)]
+pub struct BundleDiff {
+  pub added: Vec<FileDelta>,
+  pub removed: Vec<FileDelta>,
+  pub changed: Vec<FileDelta>,
+  pub unchanged_count: usize,
+  pub old_total_tokens: usize,
+  pub new_total_tokens: usize,
+  pub token_delta: i64,
+}
+
+impl Bundle {
+  pub fn new(name: String, files: Vec<BundleFileInput>, encoder: &CoreBPE) -> Self {
+    let created_ms = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map_or(0, |d| d.as_millis() as u64);
+
+    Self {
+      id: new_id(created_ms),
+      name,
+      created_ms,
+      files: files
+        .into_iter()
+        .map(|file| BundleFile {
+          hash: blake3::hash(file.content.as_bytes()).to_hex().to_string(),
+          tokens: encoder.encode_ordinary(&file.content).len(),
+          path: file.path,
+        })
+        .collect(),
+    }
+  }
+
+  pub fn summary(&self) -> BundleSummary {
+    BundleSummary {
+      id: self.id.clone(),
+      name: self.name.clone(),
+      created_ms: self.created_ms,
+      file_count: self.files.len(),
+      total_tokens: self.total_tokens(),
+    }
+  }
+
+  fn total_tokens(&self) -> usize {
+    self.files.iter().map(|f| f.tokens).sum()
+  }
+}
+
+fn bundle_path(dir: &Path, id: &str) -> Result<PathBuf, String> {
+  if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+    return Err(format!("invalid bundle id: {id}"));
+  }
+  Ok(dir.join(format!("{id}.json")))
+}
+
+/// Save a new bundle, drawing a fresh id while a saved bundle has its id
+pub fn save_new(dir: &Path, bundle: &mut Bundle) -> Result<(), String> {
+  while bundle_path(dir, &bundle.id)?.exists() {
+    bundle.id = new_id(bundle.created_ms);
+  }
+  save(dir, bundle)
+}
+
+fn save(dir: &Path, bundle: &Bundle) -> Result<(), String> {
+  fs::create_dir_all(dir).map_err(|e| format!("failed to create bundle directory: {e}"))?;
+  let raw = serde_json::to_string(bundle).map_err(|e| format!("failed to serialize bundle: {e}"))?;
+  fs::write(bundle_path(dir, &bundle.id)?, raw).map_err(|e| format!("failed to save bundle: {e}"))
+}
+
+pub fn load(dir: &Path, id: &str) -> Result<Bundle, String> {
+  let raw = fs::read_to_string(bundle_path(dir, id)?).map_err(|e| format!("bundle {id} not found: {e}"))?;
+  serde_json::from_str(&raw).map_err(|e| format!("bundle {id} is corrupt: {e}"))
+}
+
+/// All saved bundles, oldest first
+pub fn list(dir: &Path) -> Vec<BundleSummary> {
+  let Ok(entries) = fs::read_dir(dir) else {
+    return Vec::new();
+  };
+
+  let mut summaries: Vec<BundleSummary> = entries
+    .filter_map(|e| e.ok())
+    .filter_map(|entry| {
+      let id = entry.path().file_stem()?.to_string_lossy().to_string();
+      load(dir, &id).ok().map(|bundle| bundle.summary())
+    })
+    .collect();
+  summaries.sort_by_key(|s| s.created_ms);
+  summaries
+}
+
+/// Compare two bundles path by path
+pub fn diff(old: &Bundle, new: &Bundle) -> BundleDiff {
+  let old_files: BTreeMap<&str, &BundleFile> = old.files.iter().map(|f| (f.path.as_str(), f)).collect();
+  let new_files: BTreeMap<&str, &BundleFile> = new.files.iter().map(|f| (f.path.as_str(), f)).collect();
+
+  let delta = |path: &str, old: Option<&BundleFile>, new: Option<&BundleFile>| {
+    let old_tokens = old.map(|f| f.tokens);
+    let new_tokens = new.map(|f| f.tokens);
+    FileDelta {
+      path: path.to_string(),
+      old_tokens,
+      new_tokens,
+      token_delta: new_tokens.unwrap_or(0) as i64 - old_tokens.unwrap_or(0) as i64,
+    }
+  };
+
+  let mut added = Vec::new();
+  let mut changed = Vec::new();
+  let mut unchanged_count = 0;
+  for (path, new_file) in &new_files {
+    match old_files.get(path) {
+      None => added.push(delta(path, None, Some(new_file))),
+      Some(old_file) if old_file.hash != new_file.hash => changed.push(delta(path, Some(old_file), Some(new_file))),
+      Some(_) => unchanged_count += 1,
+    }
+  }
+  let removed = old_files
+    .iter()
+    .filter(|(path, _)| !new_files.contains_key(*path))
+    .map(|(path, old_file)| delta(path, Some(old_file), None))
+    .collect();
+
+  let old_total_tokens = old.total_tokens();
+  let new_total_tokens = new.total_tokens();
+  BundleDiff {
+    added,
+    removed,
+    changed,
+    unchanged_count,
+    old_total_tokens,
+    new_total_tokens,
+    token_delta: new_total_tokens as i64 - old_total_tokens as i64,
+  }
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Kept open after writing: on Linux the copied text is served by the process
+/// that owns the clipboard, so dropping the handle would lose it
+static WRITER: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// Paths currently on the system clipboard: the file list left by a file
+/// manager "Copy", or else plain text with one path (or `file://` URI) per line.
+/// Lines that do not name an existing file or directory are ignored.
+pub fn paths() -> Result<Vec<String>, String> {
+  let mut clipboard = arboard::Clipboard::new().map_err(|e| format!("clipboard unavailable: {e}"))?;
+
+  if let Ok(files) = clipboard.get().file_list() {
+    if !files.is_empty() {
+      return Ok(files.into_iter().map(|p| p.to_string_lossy().into_owned()).collect());
+    }
+  }
+
+  let text = clipboard
+    .get_text()
+    .map_err(|e| format!("clipboard has no files or text: {e}"))?;
+  Ok(parse_text(&text))
+}
+
+/// Text currently on the system clipboard
+pub fn text() -> Result<String, String> {
+  let mut clipboard = arboard::Clipboard::new().map_err(|e| format!("clipboard unavailable: {e}"))?;
+  clipboard.get_text().map_err(|e| format!("clipboard has no text: {e}"))
+}
+
+/// Replace the system clipboard contents with `text`
+pub fn set_text(text: String) -> Result<(), String> {
+  let mut writer = WRITER.lock().unwrap_or_else(|e| e.into_inner());
+  let clipboard = match writer.as_mut() {
+    Some(clipboard) => clipboard,
+    None => writer.insert(arboard::Clipboard::new().map_err(|e| format!("clipboard unavailable: {e}"))?),
+  };
+  clipboard
+    .set_text(text)
+    .map_err(|e| format!("failed to write to the clipboard: {e}"))
+}
+
+fn parse_text(text: &str) -> Vec<String> {
+  text
+    .lines()
+    .map(|line| line.trim().trim_matches(|c| c == '"' || c == '\''))
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .map(|line| match line.strip_prefix("file://") {
+      Some(uri) => decode_file_uri(uri),
+      None => line.to_string(),
+    })
+    .filter(|path| Path::new(path).exists())
+    .collect()
+}
+
+/// `file://` URIs as written by GTK file managers: optional host, percent-encoded path
+fn decode_file_uri(uri: &str) -> String {
+  let path = uri.strip_prefix("localhost").unwrap_or(uri);
+  // `file:///C:/dir` on Windows
+  let path = match path.as_bytes() {
+    [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &path[1..],
+    _ => path,
+  };
+
+  let bytes = path.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+    match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+      (b'%', Some(byte)) => {
+        decoded.push(byte);
+        i += 3;
+      }
+      (byte, _) => {
+        decoded.push(byte);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,547 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::sync::Arc;
+
+use tiktoken_rs::CoreBPE;
+
+use crate::language;
+use crate::order::{self, FileOrder};
+use crate::output::fence_for;
+use crate::redact::RedactionCounts;
+use crate::split::{self, ExportSplit};
+use crate::store::StoredFile;
+use crate::template::{PromptTemplate, TemplateValues};
+
+/// Layout of the combined document
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CombinedFormat {
+  /// `/* --- path --- */` comment headers before each file
+  #[default]
+  Plain,
+  /// Repomix-style `<file path="...">` elements inside a `<files>` root
+  Xml,
+  /// A heading per file followed by its content in a fenced code block
+  Markdown,
+}
+
+/// Where the export summary goes
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SummaryPlacement {
+  Start,
+  End,
+}
+
+/// How `build_combined_output` assembles the document
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct CombinedOptions {
+  pub format: CombinedFormat,
+  /// Start the document with a tree of the included files
+  pub directory_tree: bool,
+  /// Write the document to this path instead of returning it
+  pub output_path: Option<String>,
+  /// Order of the files in the document
+  pub order: FileOrder,
+  /// Name of a prompt template from the settings to lay the document out with
+  pub template: Option<String>,
+  /// Add a JSON block saying how the export was produced
+  pub summary: Option<SummaryPlacement>,
+  /// Build one document per directory instead, each with its own tree and
+  /// token count; written next to `output_path` when given
+  pub split: Option<ExportSplit>,
+}
+
+impl Default for CombinedOptions {
+  fn default() -> Self {
+    Self {
+      format: CombinedFormat::Plain,
+      directory_tree: true,
+      output_path: None,
+      order: FileOrder::Given,
+      template: None,
+      summary: None,
+      split: None,
+    }
+  }
+}
+
+#[derive(serde::Serialize)]
+pub struct CombinedOutput {
+  /// The document, unless it was written to `output_path`
+  pub content: Option<String>,
+  pub written_to: Option<String>,
+  pub files: usize,
+  /// Sum of the token counts shown in the file headers
+  pub tokens: usize,
+  pub bytes: usize,
+  /// Requested ids with no processed file in the store
+  pub missing: Vec<String>,
+  /// The document of each group, with `split`; the fields above then sum
+  /// them up and `content` is unset
+  pub parts: Vec<CombinedPart>,
+}
+
+/// One document of a split export
+#[derive(serde::Serialize)]
+pub struct CombinedPart {
+  /// Directory the files share, or `.` for those in the root
+  pub group: String,
+  #[serde(flatten)]
+  pub output: CombinedOutput,
+}
+
+/// Result of `write_output`
+#[derive(serde::Serialize)]
+pub struct WrittenOutput {
+  pub path: String,
+  pub bytes: usize,
+  /// Requested ids with no processed file in the store
+  pub missing: Vec<String>,
+  /// Paths written in place of `path`, with `split`
+  pub parts: Vec<String>,
+}
+
+/// Display name of the language of `file`, found by `language::detect` from
+/// its name, extension, shebang or content
+pub(crate) fn language(file: &StoredFile) -> &'static str {
+  language::detect(&file.name, &file.content).map_or("Text", |key| language_name(&key))
+}
+
+/// Display name of the language `language::detect` names `key`
+pub(crate) fn language_name(key: &str) -> &'static str {
+  match key {
+    "rs" => "Rust",
+    "ts" | "tsx" | "mts" | "cts" => "TypeScript",
+    "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+    "py" | "pyi" => "Python",
+    "go" => "Go",
+    "java" => "Java",
+    "kt" | "kts" => "Kotlin",
+    "swift" => "Swift",
+    "c" | "h" => "C",
+    "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "C++",
+    "cs" => "C#",
+    "rb" => "Ruby",
+    "php" => "PHP",
+    "scala" => "Scala",
+    "dart" => "Dart",
+    "lua" => "Lua",
+    "sh" | "bash" | "zsh" => "Shell",
+    "ps1" => "PowerShell",
+    "sql" => "SQL",
+    "html" | "htm" => "HTML",
+    "css" => "CSS",
+    "scss" | "sass" => "Sass",
+    "vue" => "Vue",
+    "svelte" => "Svelte",
+    "astro" => "Astro",
+    "json" => "JSON",
+    "yaml" | "yml" => "YAML",
+    "toml" => "TOML",
+    "xml" => "XML",
+    "md" | "markdown" => "Markdown",
+    _ => "Text",
+  }
+}
+
+/// Info string for a Markdown code fence holding a file of `language`
+fn fence_tag(language: &str) -> String {
+  match language {
+    "C++" => "cpp".to_string(),
+    "C#" => "csharp".to_string(),
+    other => other.to_lowercase(),
+  }
+}
+
+#[derive(Default)]
+struct TreeDir {
+  dirs: BTreeMap<String, TreeDir>,
+  files: BTreeSet<String>,
+}
+
+impl TreeDir {
+  fn render(&self, prefix: &str, out: &mut String) {
+    let count = self.dirs.len() + self.files.len();
+    let entries = self
+      .dirs
+      .iter()
+      .map(|(name, dir)| (name, Some(dir)))
+      .chain(self.files.iter().map(|name| (name, None)));
+    for (index, (name, dir)) in entries.enumerate() {
+      let last = index + 1 == count;
+      out.push_str(prefix);
+      out.push_str(if last { "└── " } else { "├── " });
+      out.push_str(name);
+      if let Some(dir) = dir {
+        out.push_str("/\n");
+        dir.render(&format!("{prefix}{}", if last { "    " } else { "│   " }), out);
+      } else {
+        out.push('\n');
+      }
+    }
+  }
+}
+
+/// `tree`-style rendering of `paths`, each given as its components below the
+/// directory labelled `root`
+pub(crate) fn render_tree<'a>(root: &str, paths: impl IntoIterator<Item = &'a [&'a str]>) -> String {
+  let mut tree = TreeDir::default();
+  for components in paths {
+    let Some((file, dirs)) = components.split_last() else {
+      continue;
+    };
+    let mut dir = &mut tree;
+    for name in dirs {
+      dir = dir.dirs.entry(name.to_string()).or_default();
+    }
+    dir.files.insert(file.to_string());
+  }
+
+  let mut out = String::new();
+  out.push_str(root);
+  out.push('\n');
+  tree.render("", &mut out);
+  out
+}
+
+/// Tree of `paths`, below the directory they all share
+pub(crate) fn directory_tree(paths: &[&str]) -> String {
+  let split: Vec<Vec<&str>> = paths
+    .iter()
+    .map(|p| p.split(['/', '\\']).filter(|c| !c.is_empty()).collect())
+    .collect();
+  // Leading directories shared by every path; the file name itself is never shared
+  let shared = split
+    .iter()
+    .map(|c| c.len().saturating_sub(1))
+    .min()
+    .map_or(0, |limit| {
+      (0..limit)
+        .take_while(|&i| split.iter().all(|c| c[i] == split[0][i]))
+        .count()
+    });
+
+  let root = match shared {
+    0 => ".".to_string(),
+    n => format!("{}/", split[0][n - 1]),
+  };
+  render_tree(&root, split.iter().map(|c| &c[shared..]))
+}
+
+/// Escape text for XML element content, or for a double-quoted attribute when `attribute` is set
+fn escape_xml(text: &str, attribute: bool) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for ch in text.chars() {
+    match ch {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' if attribute => escaped.push_str("&quot;"),
+      other => escaped.push(other),
+    }
+  }
+  escaped
+}
+
+fn push_line(text: &mut String, content: &str) {
+  text.push_str(content);
+  if !content.ends_with('\n') {
+    text.push('\n');
+  }
+}
+
+/// Label of the first of `files` with the same content as the one at `index`,
+/// if that is an earlier one. Empty files never count as copies.
+fn original_of<'a>(files: &'a [Arc<StoredFile>], index: usize, firsts: &mut HashMap<&'a str, usize>) -> Option<&'a str> {
+  let file = &files[index];
+  if file.content.is_empty() {
+    return None;
+  }
+  let first = *firsts.entry(&file.hash).or_insert(index);
+  (first != index).then(|| files[first].label())
+}
+
+/// Sections of a combined document
+struct Built {
+  /// Directory tree section, empty without one
+  tree: String,
+  files: String,
+  tokens: usize,
+}
+
+/// Merge `files` into one document with a header per file giving its path,
+/// language and token count. Content repeated byte for byte is included once;
+/// later copies get a header naming the file that holds it.
+fn build(files: &[Arc<StoredFile>], options: &CombinedOptions, with_tree: bool, encoder: &CoreBPE) -> Built {
+  let mut head = String::new();
+  let mut text = String::new();
+  let mut tokens = 0;
+  let mut firsts = HashMap::new();
+
+  let tree = (with_tree && !files.is_empty()).then(|| {
+    let paths: Vec<&str> = files.iter().map(|f| f.label()).collect();
+    directory_tree(&paths)
+  });
+
+  match options.format {
+    CombinedFormat::Plain => {
+      if let Some(tree) = &tree {
+        head.push_str("Directory structure:\n");
+        head.push_str(tree);
+        head.push('\n');
+      }
+      for (index, file) in files.iter().enumerate() {
+        if index > 0 {
+          text.push('\n');
+        }
+        let path = file.label();
+        if let Some(original) = original_of(files, index, &mut firsts) {
+          text.push_str(&format!("/* --- {path} ({}, identical to {original}) --- */\n", language(file)));
+          continue;
+        }
+        let file_tokens = file.tokens(encoder);
+        tokens += file_tokens;
+        text.push_str(&format!("/* --- {path} ({}, {file_tokens} tokens) --- */\n", language(file)));
+        push_line(&mut text, &file.content);
+      }
+    }
+    CombinedFormat::Xml => {
+      if let Some(tree) = &tree {
+        head.push_str("<directory_structure>\n");
+        head.push_str(&escape_xml(tree, false));
+        head.push_str("</directory_structure>\n\n");
+      }
+      text.push_str("<files>\n");
+      for (index, file) in files.iter().enumerate() {
+        let path = file.label();
+        if let Some(original) = original_of(files, index, &mut firsts) {
+          text.push_str(&format!(
+            "<file path=\"{}\" language=\"{}\" duplicate_of=\"{}\"/>\n",
+            escape_xml(path, true),
+            language(file),
+            escape_xml(original, true),
+          ));
+          continue;
+        }
+        let file_tokens = file.tokens(encoder);
+        tokens += file_tokens;
+        text.push_str(&format!(
+          "<file path=\"{}\" language=\"{}\" tokens=\"{file_tokens}\">\n",
+          escape_xml(path, true),
+          language(file),
+        ));
+        push_line(&mut text, &escape_xml(&file.content, false));
+        text.push_str("</file>\n");
+      }
+      text.push_str("</files>\n");
+    }
+    CombinedFormat::Markdown => {
+      if let Some(tree) = &tree {
+        let fence = fence_for(tree);
+        head.push_str(&format!("## Directory structure\n\n{fence}text\n{tree}{fence}\n\n"));
+      }
+      for (index, file) in files.iter().enumerate() {
+        if index > 0 {
+          text.push('\n');
+        }
+        let path = file.label();
+        let language = language(file);
+        if let Some(original) = original_of(files, index, &mut firsts) {
+          text.push_str(&format!("## `{path}` ({language})\n\nIdentical to `{original}`.\n"));
+          continue;
+        }
+        let file_tokens = file.tokens(encoder);
+        tokens += file_tokens;
+        let fence = fence_for(&file.content);
+        text.push_str(&format!("## `{path}` ({language}, {file_tokens} tokens)\n\n{fence}{}\n", fence_tag(language)));
+        push_line(&mut text, &file.content);
+        text.push_str(&fence);
+        text.push('\n');
+      }
+    }
+  }
+
+  Built {
+    tree: head,
+    files: text,
+    tokens,
+  }
+}
+
+#[derive(Default, serde::Serialize)]
+struct LanguageTokens {
+  files: usize,
+  tokens: usize,
+}
+
+/// How an export was produced
+#[derive(serde::Serialize)]
+struct ExportSummary<'a> {
+  files: usize,
+  tokens: usize,
+  languages: BTreeMap<&'a str, LanguageTokens>,
+  /// Files per processing mode
+  modes: BTreeMap<&'a str, usize>,
+  redactions: RedactionCounts,
+  /// Files whose values were masked as secrets
+  secrets_masked: usize,
+  generated_at: String,
+  generated_at_ms: u64,
+}
+
+/// `ms` since the Unix epoch as an ISO 8601 UTC time, to the second
+fn utc_timestamp(ms: u64) -> String {
+  let secs = ms / 1000;
+  let (days, time) = (secs / 86_400, secs % 86_400);
+  // Days to a civil date, after Howard Hinnant's `civil_from_days`
+  let z = days as i64 + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z - era * 146_097;
+  let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + i64::from(month <= 2);
+  format!(
+    "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+    time / 3600,
+    time % 3600 / 60,
+    time % 60
+  )
+}
+
+/// The summary block of an export of `files` holding `tokens` tokens, laid
+/// out for `format`. Content repeated byte for byte is counted once, as in
+/// the document.
+fn summary_block(files: &[Arc<StoredFile>], tokens: usize, format: CombinedFormat, encoder: &CoreBPE) -> String {
+  let generated_at_ms = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map_or(0, |d| d.as_millis() as u64);
+  let mut summary = ExportSummary {
+    files: files.len(),
+    tokens,
+    languages: BTreeMap::new(),
+    modes: BTreeMap::new(),
+    redactions: RedactionCounts::default(),
+    secrets_masked: 0,
+    generated_at: utc_timestamp(generated_at_ms),
+    generated_at_ms,
+  };
+  let mut seen = HashSet::new();
+  for file in files {
+    let language = summary.languages.entry(language(file)).or_default();
+    language.files += 1;
+    if seen.insert(&file.hash) {
+      language.tokens += file.tokens(encoder);
+    }
+    let Some(provenance) = &file.provenance else {
+      continue;
+    };
+    *summary.modes.entry(&provenance.mode).or_default() += 1;
+    if let Some(counts) = &provenance.redactions {
+      summary.redactions.email += counts.email;
+      summary.redactions.phone += counts.phone;
+      summary.redactions.ip_address += counts.ip_address;
+    }
+    summary.secrets_masked += usize::from(provenance.secrets_masked);
+  }
+
+  let json = serde_json::to_string_pretty(&summary).unwrap_or_default();
+  match format {
+    CombinedFormat::Plain => format!("Export summary:\n{json}\n"),
+    CombinedFormat::Xml => format!("<export_summary>\n{}\n</export_summary>\n", escape_xml(&json, false)),
+    CombinedFormat::Markdown => format!("## Export summary\n\n```json\n{json}\n```\n"),
+  }
+}
+
+/// Write already assembled `content` to `path`
+pub fn write(path: &str, content: &str) -> Result<(), String> {
+  fs::write(path, content).map_err(|e| format!("failed to write combined output to {path}: {e}"))
+}
+
+/// Build the document and either return it or write it to `options.output_path`.
+/// With a `template` the sections are placed by it, and the directory tree is
+/// always available to its `{{tree}}`. With `options.split` a document is
+/// built for each group of files instead, written to `split::part_path`.
+pub fn output(
+  files: &[Arc<StoredFile>],
+  missing: Vec<String>,
+  options: &CombinedOptions,
+  template: Option<&PromptTemplate>,
+  encoder: &CoreBPE,
+) -> Result<CombinedOutput, String> {
+  let files = order::sort(files, &options.order, encoder);
+  let Some(split) = &options.split else {
+    return document(&files, missing, options, options.output_path.as_deref(), template, encoder);
+  };
+
+  let mut parts = Vec::new();
+  for (group, files) in split.groups(&files) {
+    let path = options.output_path.as_deref().map(|path| split::part_path(path, &group));
+    let output = document(&files, Vec::new(), options, path.as_deref(), template, encoder)?;
+    parts.push(CombinedPart { group, output });
+  }
+  Ok(CombinedOutput {
+    content: None,
+    written_to: None,
+    files: parts.iter().map(|part| part.output.files).sum(),
+    tokens: parts.iter().map(|part| part.output.tokens).sum(),
+    bytes: parts.iter().map(|part| part.output.bytes).sum(),
+    missing,
+    parts,
+  })
+}
+
+/// One document of `files`, already in order, written to `output_path` if given
+fn document(
+  files: &[Arc<StoredFile>],
+  missing: Vec<String>,
+  options: &CombinedOptions,
+  output_path: Option<&str>,
+  template: Option<&PromptTemplate>,
+  encoder: &CoreBPE,
+) -> Result<CombinedOutput, String> {
+  let built = build(files, options, options.directory_tree || template.is_some(), encoder);
+  let tokens = built.tokens;
+  let text = match template {
+    Some(template) => template.render(&TemplateValues {
+      tree: built.tree.trim_end(),
+      files: built.files.trim_end(),
+      file_count: files.len(),
+      total_tokens: tokens,
+    }),
+    None => built.tree + built.files.as_str(),
+  };
+  let text = match options.summary {
+    Some(placement) => {
+      let block = summary_block(files, tokens, options.format, encoder);
+      match placement {
+        SummaryPlacement::Start => format!("{block}\n{text}"),
+        SummaryPlacement::End => format!("{text}\n{block}"),
+      }
+    }
+    None => text,
+  };
+  let bytes = text.len();
+
+  let (content, written_to) = match output_path {
+    Some(path) => {
+      write(path, &text)?;
+      (None, Some(path.to_string()))
+    }
+    None => (Some(text), None),
+  };
+
+  Ok(CombinedOutput {
+    content,
+    written_to,
+    files: files.len(),
+    tokens,
+    bytes,
+    missing,
+    parts: Vec::new(),
+  })
+}
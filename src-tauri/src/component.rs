@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+use crate::html::{find_ignore_case, tag_len};
+
+/// Single-file component formats, split into blocks before processing
+pub fn is_component(extension: &str) -> bool {
+  ["vue", "svelte", "astro"].contains(&extension)
+}
+
+/// A stretch of a component and the extension it is processed as; `None`
+/// for the `<script>` and `<style>` tags themselves, which are kept as written
+pub struct Segment {
+  pub range: Range<usize>,
+  pub extension: Option<String>,
+}
+
+/// Value of attribute `name` in the opening `tag`, lowercased
+fn attribute(tag: &str, name: &str) -> Option<String> {
+  let lower = tag.to_ascii_lowercase();
+  let mut from = 0;
+  while let Some(found) = lower[from..].find(name) {
+    let at = from + found;
+    from = at + name.len();
+    let preceded = lower[..at].ends_with(|c: char| c.is_ascii_whitespace());
+    let rest = lower[from..].trim_start();
+    let Some(value) = rest.strip_prefix('=').filter(|_| preceded).map(str::trim_start) else {
+      continue;
+    };
+    let value = match value.chars().next() {
+      Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+      _ => value.split(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/').next().unwrap_or(""),
+    };
+    return Some(value.to_string());
+  }
+  None
+}
+
+/// The extension the content of a `<script>` or `<style>` element is
+/// processed as, from its `lang` and `type`; `None` for content that is
+/// neither code nor a stylesheet, such as HTML templates
+fn block_extension(name: &str, tag: &str) -> Option<String> {
+  if name == "style" {
+    return Some(match attribute(tag, "lang").as_deref() {
+      None | Some("postcss") => "css".to_string(),
+      Some(lang) => lang.to_string(),
+    });
+  }
+  if let Some(kind) = attribute(tag, "type") {
+    if kind.contains("json") {
+      return Some("json".to_string());
+    }
+    if !["javascript", "ecmascript", "module", "typescript"].iter().any(|t| kind.contains(t)) {
+      return None;
+    }
+  }
+  Some(attribute(tag, "lang").unwrap_or_else(|| "js".to_string()))
+}
+
+/// Content of the Astro frontmatter opening `code`, and the end of the `---`
+/// line closing it
+fn frontmatter(code: &str) -> Option<(Range<usize>, usize)> {
+  let content = code.strip_prefix("---")?;
+  let start = 3 + content.find('\n').filter(|&i| content[..i].trim().is_empty())? + 1;
+  let mut at = start;
+  for line in code[start..].split_inclusive('\n') {
+    if line.trim_end() == "---" {
+      return Some((start..at, at + line.len()));
+    }
+    at += line.len();
+  }
+  None
+}
+
+/// `code` split into its markup, processed as HTML, and its `<script>` and
+/// `<style>` blocks, processed as the language they are written in. Astro
+/// frontmatter is TypeScript. Elements inside HTML comments are markup.
+pub fn segments(code: &str) -> Vec<Segment> {
+  let mut segments = Vec::new();
+  let mut push = |range: Range<usize>, extension: Option<&str>| {
+    if !range.is_empty() {
+      segments.push(Segment {
+        range,
+        extension: extension.map(str::to_string),
+      });
+    }
+  };
+
+  let mut markup = 0;
+  if let Some((content, close_end)) = frontmatter(code) {
+    push(0..content.start, None);
+    push(content.clone(), Some("ts"));
+    push(content.end..close_end, None);
+    markup = close_end;
+  }
+
+  let mut at = markup;
+  while let Some(found) = code[at..].find('<') {
+    let start = at + found;
+    let rest = &code[start..];
+    if rest.starts_with("<!--") {
+      at = rest.find("-->").map_or(code.len(), |end| start + end + 3);
+      continue;
+    }
+    let name = ["script", "style"].into_iter().find(|name| {
+      rest.len() > name.len() + 1
+        && rest[1..=name.len()].eq_ignore_ascii_case(name)
+        && rest[name.len() + 1..].starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/')
+    });
+    let (Some(name), Some(tag_end)) = (name, tag_len(rest)) else {
+      at = start + 1;
+      continue;
+    };
+    let content_start = start + tag_end;
+    let Some(close) = find_ignore_case(&code[content_start..], &format!("</{name}")) else {
+      break;
+    };
+    let close_start = content_start + close;
+    let close_end = tag_len(&code[close_start..]).map_or(code.len(), |len| close_start + len);
+
+    push(markup..start, Some("html"));
+    push(start..content_start, None);
+    let extension = block_extension(name, &code[start..content_start]);
+    push(content_start..close_start, extension.as_deref());
+    push(close_start..close_end, None);
+    markup = close_end;
+    at = close_end;
+  }
+  push(markup..code.len(), Some("html"));
+  segments
+}
+
+/// `processed` text of the segment `original`, keeping a line break at
+/// either end when processing trimmed one away, so blocks stay on lines of
+/// their own
+fn fit(original: &str, processed: String) -> String {
+  let line_break = |whitespace: &str| if whitespace.contains('\n') { "\n" } else { "" };
+  if processed.trim().is_empty() {
+    return if processed.is_empty() { line_break(original).to_string() } else { processed };
+  }
+  let leading = if processed.starts_with(char::is_whitespace) {
+    ""
+  } else {
+    line_break(&original[..original.len() - original.trim_start().len()])
+  };
+  let trailing = if processed.ends_with(char::is_whitespace) {
+    ""
+  } else {
+    line_break(&original[original.trim_end().len()..])
+  };
+  format!("{leading}{processed}{trailing}")
+}
+
+/// `code` with each segment run through `process` with its extension; tags
+/// and blocks of unknown content are kept as written
+pub fn process(code: &str, mut process: impl FnMut(&str, &str) -> String) -> String {
+  let mut out = String::with_capacity(code.len());
+  for segment in segments(code) {
+    let original = &code[segment.range];
+    match segment.extension {
+      Some(extension) => out.push_str(&fit(original, process(original, &extension))),
+      None => out.push_str(original),
+    }
+  }
+  out
+}
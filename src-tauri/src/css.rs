@@ -0,0 +1,16 @@
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+
+/// `code` minified from its parsed rules: whitespace and comments dropped,
+/// shorthands merged and values shortened (`#ff0000` to `red`). `None` when
+/// it is not valid CSS, so the caller can fall back to collapsing whitespace.
+pub fn minify(code: &str) -> Option<String> {
+  let mut stylesheet = StyleSheet::parse(code, ParserOptions::default()).ok()?;
+  stylesheet.minify(MinifyOptions::default()).ok()?;
+  let printed = stylesheet
+    .to_css(PrinterOptions {
+      minify: true,
+      ..PrinterOptions::default()
+    })
+    .ok()?;
+  Some(printed.code)
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::lexer;
+
+/// Debug and logging calls removed by default, by language. A `*` stands for
+/// any identifier, so `log::*!` covers every `log` macro.
+const DEFAULT_CALLS: &[(&[&str], &[&str])] = &[
+  (
+    &["js", "mjs", "cjs", "jsx", "ts", "mts", "tsx", "vue", "svelte"],
+    &["console.log", "console.debug", "console.info", "console.trace", "console.dir", "debugger"],
+  ),
+  (&["py", "pyw"], &["print", "pprint", "breakpoint", "logging.debug", "logger.debug"]),
+  (
+    &["rs"],
+    &["println!", "eprintln!", "print!", "eprint!", "dbg!", "log::*!", "tracing::*!"],
+  ),
+  (&["go"], &["fmt.Print*", "log.Print*"]),
+  (&["java", "kt", "kts"], &["System.out.print*", "System.err.print*", "println"]),
+  (&["cs"], &["Console.Write*", "Debug.Write*"]),
+  (&["php"], &["var_dump", "print_r", "error_log"]),
+  (&["rb"], &["puts", "p", "pp"]),
+  (&["swift"], &["print", "debugPrint", "dump"]),
+];
+
+/// Statements removed without an argument list
+const BARE_STATEMENTS: &[&str] = &["debugger"];
+
+/// Regex matching any of `calls` at the start of a line
+fn calls_pattern<S: AsRef<str>>(calls: &[S]) -> Option<Regex> {
+  let mut names: Vec<&str> = calls.iter().map(|call| call.as_ref().trim()).filter(|call| !call.is_empty()).collect();
+  if names.is_empty() {
+    return None;
+  }
+  // Longer names first, so `print` does not shadow `println!`
+  names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+  let names: Vec<String> = names
+    .iter()
+    .map(|name| regex::escape(name).replace(r"\*", "[A-Za-z0-9_]*"))
+    .collect();
+  Regex::new(&format!(r"(?m)^[ \t]*(?:{})", names.join("|"))).ok()
+}
+
+fn is_identifier_byte(byte: u8) -> bool {
+  byte == b'_' || byte.is_ascii_alphanumeric()
+}
+
+static DEFAULT_PATTERNS: Lazy<HashMap<&'static str, Regex>> = Lazy::new(|| {
+  let mut map = HashMap::new();
+  for (extensions, calls) in DEFAULT_CALLS {
+    if let Some(pattern) = calls_pattern(calls) {
+      for ext in *extensions {
+        map.insert(*ext, pattern.clone());
+      }
+    }
+  }
+  map
+});
+
+/// Whether and which debug statements processing removes
+#[derive(Default, serde::Deserialize)]
+#[serde(default)]
+pub struct DebugOptions {
+  pub strip_debug_statements: bool,
+  /// Calls to remove per extension, replacing that extension's defaults
+  pub debug_calls: Option<HashMap<String, Vec<String>>>,
+}
+
+impl DebugOptions {
+  /// The calls to remove, or `None` when stripping is off
+  pub fn calls(self) -> Option<DebugCalls> {
+    self
+      .strip_debug_statements
+      .then(|| DebugCalls::new(self.debug_calls.unwrap_or_default()))
+  }
+}
+
+/// Which calls `strip` removes: the defaults, with per-extension overrides
+#[derive(Default)]
+pub struct DebugCalls {
+  overrides: HashMap<String, Option<Regex>>,
+}
+
+impl DebugCalls {
+  /// `overrides` maps an extension to the calls to remove in it instead of
+  /// the defaults; an empty list removes nothing
+  pub fn new(overrides: HashMap<String, Vec<String>>) -> Self {
+    let overrides = overrides
+      .into_iter()
+      .map(|(ext, calls)| (ext.trim_start_matches('.').to_lowercase(), calls_pattern(&calls)))
+      .collect();
+    Self { overrides }
+  }
+
+  fn pattern(&self, ext: &str) -> Option<&Regex> {
+    match self.overrides.get(ext) {
+      Some(pattern) => pattern.as_ref(),
+      None => DEFAULT_PATTERNS.get(ext),
+    }
+  }
+}
+
+/// End of the bracketed argument list opening at `code[start]`, skipping
+/// strings, or `None` when it never closes
+fn arguments_end(code: &str, start: usize) -> Option<usize> {
+  let bytes = code.as_bytes();
+  let mut depth = 0usize;
+  let mut quote = None;
+  let mut at = start;
+  while at < bytes.len() {
+    let byte = bytes[at];
+    match quote {
+      Some(_) if byte == b'\\' => at += 1,
+      Some(q) if byte == q => quote = None,
+      Some(_) => {}
+      None => match byte {
+        b'"' | b'\'' | b'`' => quote = Some(byte),
+        b'(' | b'[' | b'{' => depth += 1,
+        b')' | b']' | b'}' => {
+          depth -= 1;
+          if depth == 0 {
+            return Some(at + 1);
+          }
+        }
+        _ => {}
+      },
+    }
+    at += 1;
+  }
+  None
+}
+
+/// Whether `rest` holds nothing but an optional `;` and a line comment
+fn ends_statement(rest: &str) -> bool {
+  let rest = rest.trim_start_matches([' ', '\t']);
+  let rest = rest.strip_prefix(';').unwrap_or(rest).trim();
+  rest.is_empty() || rest.starts_with("//") || rest.starts_with('#')
+}
+
+/// Indentation of `line`
+fn indent(line: &str) -> &str {
+  &line[..line.len() - line.trim_start().len()]
+}
+
+/// Whether a statement starting right after `before` is the whole body of a
+/// brace-less `if`, `else`, `for`, `while` or `do`, or of an arrow or `when`
+/// branch, so removing it would leave the header without a body
+fn is_braceless_body(before: &str) -> bool {
+  let before = before.trim_end();
+  let ends_with_word = |word: &str| {
+    before
+      .strip_suffix(word)
+      .is_some_and(|head| !head.bytes().last().is_some_and(is_identifier_byte))
+  };
+  if ends_with_word("else") || ends_with_word("do") || before.ends_with("=>") || before.ends_with("->") {
+    return true;
+  }
+  if !before.ends_with(')') {
+    return false;
+  }
+  // The keyword before the parenthesis matching the last one
+  let mut depth = 0usize;
+  for (at, byte) in before.bytes().enumerate().rev() {
+    match byte {
+      b')' => depth += 1,
+      b'(' => {
+        depth -= 1;
+        if depth == 0 {
+          let head = before[..at].trim_end();
+          let keyword_start = head.len() - head.bytes().rev().take_while(|&b| is_identifier_byte(b)).count();
+          return ["if", "for", "foreach", "while"].contains(&&head[keyword_start..]);
+        }
+      }
+      _ => {}
+    }
+  }
+  false
+}
+
+/// What a Rust tail expression `dbg!(...)` evaluates to, given the text
+/// between its parentheses: the argument itself, a tuple of several, or `()`
+fn dbg_value(arguments: &str) -> String {
+  let arguments = arguments.trim();
+  let single = arguments.strip_suffix(',').unwrap_or(arguments).trim_end();
+  let mut depth = 0usize;
+  let mut quote = None;
+  let mut escaped = false;
+  let mut several = false;
+  for byte in single.bytes() {
+    match quote {
+      Some(_) if escaped => escaped = false,
+      Some(_) if byte == b'\\' => escaped = true,
+      Some(q) if byte == q => quote = None,
+      Some(_) => {}
+      None => match byte {
+        b'"' => quote = Some(byte),
+        b'(' | b'[' | b'{' => depth += 1,
+        b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+        b',' if depth == 0 => several = true,
+        _ => {}
+      },
+    }
+  }
+  if several || single.is_empty() {
+    format!("({arguments})")
+  } else {
+    single.to_string()
+  }
+}
+
+/// Remove the debug and logging statements of `code`. Only whole statements
+/// standing on their own lines go; a Python block left empty gets a `pass`,
+/// the body of a brace-less `if` or loop becomes `{}` and a Rust tail
+/// expression `dbg!(x)` becomes `x`.
+pub fn strip(code: &str, extension: &str, calls: &DebugCalls) -> String {
+  let ext = extension.trim_start_matches('.').to_lowercase();
+  let Some(pattern) = calls.pattern(&ext) else {
+    return code.to_string();
+  };
+  let python = matches!(ext.as_str(), "py" | "pyw");
+  // Ruby calls often leave out the parentheses: `puts "value"`
+  let paren_free = ext == "rb";
+  // Calls inside multi-line strings are text, not statements
+  let literals = lexer::syntax_for(&ext).map(|syntax| lexer::literal_spans(code, syntax)).unwrap_or_default();
+
+  let mut out = String::with_capacity(code.len());
+  let mut copied = 0;
+  for found in pattern.find_iter(code) {
+    let name_end = found.end();
+    let bytes = code.as_bytes();
+    let cut_identifier = name_end > 0
+      && is_identifier_byte(bytes[name_end - 1])
+      && bytes.get(name_end).copied().is_some_and(is_identifier_byte);
+    if found.start() < copied || cut_identifier || literals.iter().any(|span| span.contains(&found.start())) {
+      continue;
+    }
+
+    let after_name = &code[name_end..];
+    let spaces = after_name.len() - after_name.trim_start_matches([' ', '\t']).len();
+    let open = name_end + spaces;
+    let line_end = |from: usize| code[from..].find('\n').map_or(code.len(), |i| from + i + 1);
+    let statement_end = match code[open..].chars().next() {
+      Some('(' | '[' | '{') => match arguments_end(code, open) {
+        Some(end) => end,
+        None => continue,
+      },
+      _ if BARE_STATEMENTS.contains(&found.as_str().trim()) => name_end,
+      // An argument must follow, so `p = 1` stays
+      Some(c) if paren_free && spaces > 0 && (c.is_alphanumeric() || matches!(c, '_' | '"' | '\'' | ':' | '@' | '$')) => {
+        let end = code[open..].find('\n').map_or(code.len(), |i| open + i);
+        if code[open..end].trim_end().ends_with([',', '\\']) {
+          continue;
+        }
+        end
+      }
+      _ => continue,
+    };
+    let line_end = line_end(statement_end);
+    if !ends_statement(&code[statement_end..line_end]) {
+      continue;
+    }
+
+    let line = &code[found.start()..line_end];
+    out.push_str(&code[copied..found.start()]);
+    let rest = &code[statement_end..line_end];
+    let newline = if line.ends_with('\n') { "\n" } else { "" };
+    if ext == "rs" && found.as_str().trim() == "dbg!" && !rest.trim_start().starts_with(';') {
+      out.push_str(indent(line));
+      out.push_str(&dbg_value(&code[open + 1..statement_end - 1]));
+      out.push_str(newline);
+    } else if !python && !paren_free && is_braceless_body(&code[..found.start()]) {
+      out.push_str(indent(line));
+      out.push_str("{}");
+      out.push_str(newline);
+    } else if python {
+      let opens_block = out.trim_end().ends_with(':');
+      let next = code[line_end..].lines().find(|l| !l.trim().is_empty());
+      let block_continues = next.is_some_and(|next| indent(next).len() >= indent(line).len());
+      if opens_block && !block_continues {
+        out.push_str(indent(line));
+        out.push_str("pass\n");
+      }
+    }
+    copied = line_end;
+  }
+  out.push_str(&code[copied..]);
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn strip_default(code: &str, extension: &str) -> String {
+    strip(code, extension, &DebugCalls::default())
+  }
+
+  #[test]
+  fn standalone_statements_are_removed() {
+    let code = "function f(x) {\n  console.log(x);\n  return x;\n}\n";
+    assert_eq!(strip_default(code, "js"), "function f(x) {\n  return x;\n}\n");
+  }
+
+  #[test]
+  fn braceless_bodies_become_empty_blocks() {
+    let code = "if (ready(a))\n  console.log(a);\nelse\n  console.debug(b);\nrun();\n";
+    assert_eq!(strip_default(code, "ts"), "if (ready(a))\n  {}\nelse\n  {}\nrun();\n");
+
+    let code = "for (int i = 0; i < n; i++)\n    System.out.println(i);\ndone();\n";
+    assert_eq!(strip_default(code, "java"), "for (int i = 0; i < n; i++)\n    {}\ndone();\n");
+  }
+
+  #[test]
+  fn calls_after_other_calls_are_still_removed() {
+    let code = "setup(a)\nconsole.log(a)\n";
+    assert_eq!(strip_default(code, "js"), "setup(a)\n");
+  }
+
+  #[test]
+  fn rust_tail_dbg_keeps_its_value() {
+    let code = "fn f(a: i32) -> i32 {\n    dbg!(a);\n    dbg!(a + 1)\n}\n";
+    assert_eq!(strip_default(code, "rs"), "fn f(a: i32) -> i32 {\n    a + 1\n}\n");
+
+    let code = "fn g() -> (i32, &'static str) {\n    dbg!(1, \"a,b\")\n}\n";
+    assert_eq!(strip_default(code, "rs"), "fn g() -> (i32, &'static str) {\n    (1, \"a,b\")\n}\n");
+  }
+
+  #[test]
+  fn python_blocks_left_empty_get_a_pass() {
+    let code = "if x:\n    print(x)\ny = 1\n";
+    assert_eq!(strip_default(code, "py"), "if x:\n    pass\ny = 1\n");
+  }
+}
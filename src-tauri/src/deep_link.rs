@@ -0,0 +1,32 @@
+use tauri::Url;
+
+use crate::launch::LaunchRequest;
+
+/// URL scheme registered for the app, as configured under `plugins.deep-link`
+pub const SCHEME: &str = "textractor";
+
+/// What a `textractor://open?path=...&mode=...` link asks for. `path` may be
+/// repeated to open several files or folders at once.
+pub fn parse(link: &Url) -> Result<LaunchRequest, String> {
+  if link.scheme() != SCHEME {
+    return Err(format!("not a {SCHEME}:// link: {link}"));
+  }
+  // `textractor://open?...` puts the action in the host, `textractor:open?...` in the path
+  let action = link.host_str().unwrap_or_else(|| link.path()).trim_matches('/');
+  if action != "open" {
+    return Err(format!("unknown action in {link}: {action}"));
+  }
+
+  let mut request = LaunchRequest::default();
+  for (key, value) in link.query_pairs() {
+    match key.as_ref() {
+      "path" if !value.is_empty() => request.paths.push(value.into_owned()),
+      "mode" if !value.is_empty() => request.mode = Some(value.into_owned()),
+      _ => {}
+    }
+  }
+  if request.paths.is_empty() {
+    return Err(format!("no path in {link}"));
+  }
+  Ok(request)
+}
@@ -0,0 +1,147 @@
+use std::io::Read;
+use std::time::Duration;
+
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::error::TextractorError;
+use crate::generated;
+use crate::language;
+use crate::limits;
+use crate::scan::FileInfo;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How much of a response is checked for NUL bytes when its type is vague
+const SNIFF_LEN: usize = 8 * 1024;
+
+/// The raw-text address behind a page URL: GitHub file views, gists and
+/// pastebin pages serve HTML around the text they show
+fn raw_url(url: &str) -> String {
+  let url = url.trim();
+  let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+  let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+  let segments: Vec<&str> = path.split('/').collect();
+
+  match (host, segments.as_slice()) {
+    ("github.com", [owner, repo, "blob", tail @ ..]) if !tail.is_empty() => {
+      format!("https://raw.githubusercontent.com/{owner}/{repo}/{}", tail.join("/"))
+    }
+    ("gist.github.com", [user, id]) if !id.is_empty() => format!("https://gist.githubusercontent.com/{user}/{id}/raw"),
+    ("pastebin.com", [id]) if !id.is_empty() => format!("https://pastebin.com/raw/{id}"),
+    _ => format!("{scheme}://{rest}"),
+  }
+}
+
+/// File name for a download: the last path segment, else the host
+fn file_name(url: &str) -> String {
+  let without_query = url.split(['?', '#']).next().unwrap_or(url);
+  let path = without_query.split_once("://").map_or(without_query, |(_, rest)| rest);
+  path
+    .trim_end_matches('/')
+    .rsplit('/')
+    .next()
+    .filter(|name| !name.is_empty())
+    .unwrap_or(path)
+    .to_string()
+}
+
+/// Language key for a media type, for names that do not give it away
+fn language_of(mime: &str) -> Option<&'static str> {
+  Some(match mime {
+    "application/json" => "json",
+    "application/xml" | "text/xml" => "xml",
+    "text/html" => "html",
+    "text/css" => "css",
+    "text/javascript" | "application/javascript" | "application/x-javascript" => "js",
+    "text/markdown" | "text/x-markdown" => "md",
+    "application/toml" => "toml",
+    "application/yaml" | "application/x-yaml" | "text/yaml" => "yaml",
+    "text/x-python" | "application/x-python" => "py",
+    "text/x-sh" | "application/x-sh" => "sh",
+    "text/csv" => "csv",
+    _ if mime.ends_with("+json") => "json",
+    _ if mime.ends_with("+xml") => "xml",
+    _ => return None,
+  })
+}
+
+/// Whether a media type is certainly text (`Some(true)`), certainly not
+/// (`Some(false)`), or says too little to tell
+fn is_text_type(mime: &str) -> Option<bool> {
+  let top = mime.split('/').next().unwrap_or_default();
+  if top == "text" || language_of(mime).is_some() {
+    Some(true)
+  } else if matches!(top, "image" | "audio" | "video" | "font")
+    || matches!(mime, "application/pdf" | "application/zip" | "application/gzip" | "application/wasm")
+  {
+    Some(false)
+  } else {
+    None
+  }
+}
+
+/// Download the text resource at `url` and return it as a loaded file named
+/// after its last path segment. GitHub file pages, gists and pastebin pages
+/// are fetched in their raw form. The body is decoded from the charset the
+/// server names (or its byte order mark), and refused when it is binary or
+/// over the file size limit.
+pub fn read_url(url: &str) -> Result<FileInfo, TextractorError> {
+  let fetch_url = raw_url(url);
+  let failed = |message: String| TextractorError::IoError {
+    path: url.to_string(),
+    message,
+  };
+  if !fetch_url.starts_with("http://") && !fetch_url.starts_with("https://") {
+    return Err(TextractorError::InvalidInput(format!("{url} is not an http(s) URL")));
+  }
+
+  let response = ureq::get(&fetch_url)
+    .timeout(REQUEST_TIMEOUT)
+    .call()
+    .map_err(|e| failed(e.to_string()))?;
+  let mime = response.content_type().to_lowercase();
+  let charset = response.charset().to_string();
+  if is_text_type(&mime) == Some(false) {
+    return Err(TextractorError::BinaryFile { path: url.to_string() });
+  }
+
+  let max_file_size = limits::max_file_size() as u64;
+  let mut bytes = Vec::new();
+  response
+    .into_reader()
+    .take(max_file_size + 1)
+    .read_to_end(&mut bytes)
+    .map_err(|e| failed(e.to_string()))?;
+  if bytes.len() as u64 > max_file_size {
+    return Err(TextractorError::FileTooLarge {
+      path: url.to_string(),
+      size: bytes.len() as u64,
+      limit: max_file_size,
+    });
+  }
+  if is_text_type(&mime).is_none() && bytes[..bytes.len().min(SNIFF_LEN)].contains(&0) {
+    return Err(TextractorError::BinaryFile { path: url.to_string() });
+  }
+
+  // A byte order mark wins over the declared charset, as browsers do
+  let encoding = Encoding::for_label(charset.as_bytes()).unwrap_or(UTF_8);
+  let (content, _, had_errors) = encoding.decode(&bytes);
+  if had_errors {
+    return Err(TextractorError::EncodingError { path: url.to_string() });
+  }
+  let content = content.into_owned();
+
+  let name = file_name(&fetch_url);
+  Ok(FileInfo {
+    language: language::detect(&name, &content).or_else(|| language_of(&mime).map(str::to_string)),
+    generated: generated::is_generated(&name, &content),
+    name,
+    path: url.to_string(),
+    size: bytes.len() as u64,
+    content,
+    is_text: true,
+    modified_ms: None,
+    root: None,
+    relative_path: None,
+    binary: None,
+  })
+}
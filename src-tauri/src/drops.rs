@@ -0,0 +1,67 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long after the last drop the queued paths are loaded, so several
+/// selections dropped in quick succession become one load
+const DROP_SETTLE: Duration = Duration::from_millis(300);
+
+/// Paths dropped onto the window, waiting to be loaded together
+#[derive(Default)]
+pub struct DropQueue {
+  state: Mutex<QueueState>,
+}
+
+#[derive(Default)]
+struct QueueState {
+  paths: Vec<String>,
+  last_drop: Option<Instant>,
+  /// Whether a task is draining the queue
+  draining: bool,
+}
+
+impl DropQueue {
+  /// Queue `paths`. Returns whether the caller should start a task running
+  /// `next` until it returns `None`, because none is running.
+  pub fn push(&self, paths: Vec<String>) -> bool {
+    let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+    state.paths.extend(paths);
+    state.last_drop = Some(Instant::now());
+    !std::mem::replace(&mut state.draining, true)
+  }
+
+  /// Wait until no drop has arrived for a moment, then take every queued
+  /// path, merged. `None` once the queue is empty, after which the next
+  /// `push` starts a new task.
+  pub fn next(&self) -> Option<Vec<String>> {
+    loop {
+      let wait = {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.paths.is_empty() {
+          state.draining = false;
+          return None;
+        }
+        let since = state.last_drop.map_or(DROP_SETTLE, |last| last.elapsed());
+        if since >= DROP_SETTLE {
+          return Some(merge(std::mem::take(&mut state.paths)));
+        }
+        DROP_SETTLE - since
+      };
+      std::thread::sleep(wait);
+    }
+  }
+}
+
+/// `paths` without repeats and without paths inside another of them, which
+/// loading that one covers
+fn merge(paths: Vec<String>) -> Vec<String> {
+  let mut merged: Vec<String> = Vec::with_capacity(paths.len());
+  for path in paths {
+    if merged.iter().any(|kept| Path::new(&path).starts_with(kept)) {
+      continue;
+    }
+    merged.retain(|kept| !Path::new(kept).starts_with(&path));
+    merged.push(path);
+  }
+  merged
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Error returned by commands. Serialized as `{ code, message, path }` so the
+/// frontend can branch on the machine-readable `code` instead of matching
+/// message text.
+#[derive(Clone, Debug)]
+pub enum TextractorError {
+  IoError { path: String, message: String },
+  FileTooLarge { path: String, size: u64, limit: u64 },
+  /// Content is not valid UTF-8
+  EncodingError { path: String },
+  /// Not a text file
+  BinaryFile { path: String },
+  /// Processing one file failed; the rest of the batch is unaffected
+  ProcessingFailed { path: String, message: String },
+  /// Processing one file took longer than the per-file timeout
+  Timeout { path: String, timeout_ms: u64 },
+  TokenizerUnavailable(String),
+  /// No loaded file with this id
+  UnknownFile(String),
+  InvalidInput(String),
+  Cancelled,
+  /// A background task panicked or could not be run
+  TaskFailed(String),
+}
+
+impl TextractorError {
+  pub fn code(&self) -> &'static str {
+    match self {
+      TextractorError::IoError { .. } => "io_error",
+      TextractorError::FileTooLarge { .. } => "file_too_large",
+      TextractorError::EncodingError { .. } => "encoding_error",
+      TextractorError::BinaryFile { .. } => "binary_file",
+      TextractorError::ProcessingFailed { .. } => "processing_failed",
+      TextractorError::Timeout { .. } => "timeout",
+      TextractorError::TokenizerUnavailable(_) => "tokenizer_unavailable",
+      TextractorError::UnknownFile(_) => "unknown_file",
+      TextractorError::InvalidInput(_) => "invalid_input",
+      TextractorError::Cancelled => "cancelled",
+      TextractorError::TaskFailed(_) => "task_failed",
+    }
+  }
+
+  /// The file the error is about, if any
+  pub fn path(&self) -> Option<&str> {
+    match self {
+      TextractorError::IoError { path, .. }
+      | TextractorError::FileTooLarge { path, .. }
+      | TextractorError::EncodingError { path }
+      | TextractorError::BinaryFile { path }
+      | TextractorError::ProcessingFailed { path, .. }
+      | TextractorError::Timeout { path, .. } => Some(path),
+      _ => None,
+    }
+  }
+
+  /// Failure reading `path`, telling invalid UTF-8 apart from other I/O errors
+  pub fn io(path: &Path, error: &io::Error) -> Self {
+    let path = path.to_string_lossy().to_string();
+    match error.kind() {
+      io::ErrorKind::InvalidData => TextractorError::EncodingError { path },
+      _ => TextractorError::IoError {
+        path,
+        message: error.to_string(),
+      },
+    }
+  }
+}
+
+impl fmt::Display for TextractorError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TextractorError::IoError { path, message } => write!(f, "failed to read {path}: {message}"),
+      TextractorError::FileTooLarge { path, size, limit } => {
+        write!(f, "{path} is {size} bytes, over the {limit} byte limit")
+      }
+      TextractorError::EncodingError { path } => write!(f, "{path} is not valid UTF-8"),
+      TextractorError::BinaryFile { path } => write!(f, "{path} is not a text file"),
+      TextractorError::ProcessingFailed { path, message } => write!(f, "failed to process {path}: {message}"),
+      TextractorError::Timeout { path, timeout_ms } => write!(f, "processing {path} took over {timeout_ms} ms"),
+      TextractorError::TokenizerUnavailable(message) => write!(f, "tokenizer unavailable: {message}"),
+      TextractorError::UnknownFile(id) => write!(f, "no loaded file with id {id}"),
+      TextractorError::InvalidInput(message) => f.write_str(message),
+      TextractorError::Cancelled => f.write_str("cancelled"),
+      TextractorError::TaskFailed(message) => write!(f, "background task failed: {message}"),
+    }
+  }
+}
+
+impl std::error::Error for TextractorError {}
+
+impl serde::Serialize for TextractorError {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+
+    let mut state = serializer.serialize_struct("TextractorError", 3)?;
+    state.serialize_field("code", self.code())?;
+    state.serialize_field("message", &self.to_string())?;
+    state.serialize_field("path", &self.path())?;
+    state.end()
+  }
+}
+
+/// Failures of `async_runtime::spawn_blocking` tasks
+impl From<tauri::Error> for TextractorError {
+  fn from(error: tauri::Error) -> Self {
+    match error {
+      tauri::Error::JoinError(e) if e.is_cancelled() => TextractorError::Cancelled,
+      e => TextractorError::TaskFailed(e.to_string()),
+    }
+  }
+}
@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A processed file to write into the mirrored tree
+#[derive(serde::Deserialize)]
+pub struct ExportFile {
+  pub path: String,
+  pub content: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ExportSummary {
+  pub written: usize,
+  pub bytes: usize,
+  pub skipped: Vec<String>,
+}
+
+/// Deepest directory containing every absolute path
+fn common_root(paths: &[&Path]) -> Option<PathBuf> {
+  let mut parents = paths.iter().filter(|p| p.is_absolute()).filter_map(|p| p.parent());
+  let mut root = parents.next()?.to_path_buf();
+  for parent in parents {
+    while !parent.starts_with(&root) {
+      if !root.pop() {
+        return None;
+      }
+    }
+  }
+  Some(root)
+}
+
+/// Path of `path` below the output directory. Absolute paths are taken relative
+/// to `root`; anything that would escape the output directory is rejected.
+fn relative_target(path: &Path, root: Option<&Path>) -> Option<PathBuf> {
+  let relative = match root {
+    Some(root) if path.is_absolute() => path.strip_prefix(root).ok()?,
+    _ => path,
+  };
+
+  let mut target = PathBuf::new();
+  for component in relative.components() {
+    match component {
+      Component::Normal(part) => target.push(part),
+      Component::CurDir => {}
+      _ => return None,
+    }
+  }
+  (!target.as_os_str().is_empty()).then_some(target)
+}
+
+/// Write every file below `out_dir`, recreating the directory structure the
+/// files share. Files whose path cannot be placed safely are reported as skipped.
+pub fn write_tree(out_dir: &Path, files: &[ExportFile]) -> Result<ExportSummary, String> {
+  let paths: Vec<&Path> = files.iter().map(|f| Path::new(&f.path)).collect();
+  let root = common_root(&paths);
+
+  let mut summary = ExportSummary {
+    written: 0,
+    bytes: 0,
+    skipped: Vec::new(),
+  };
+
+  for (file, path) in files.iter().zip(&paths) {
+    let Some(relative) = relative_target(path, root.as_deref()) else {
+      summary.skipped.push(file.path.clone());
+      continue;
+    };
+    let target = out_dir.join(relative);
+    if let Some(parent) = target.parent() {
+      fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    fs::write(&target, &file.content).map_err(|e| format!("failed to write {}: {e}", target.display()))?;
+    summary.written += 1;
+    summary.bytes += file.content.len();
+  }
+
+  log::info!("Exported {} files to {}", summary.written, out_dir.display());
+  Ok(summary)
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use tiktoken_rs::CoreBPE;
+
+use crate::store::StoredFile;
+
+/// How `select_files_for_budget` chooses files
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FitStrategy {
+  /// Take the biggest files that still fit, so the most code makes it in
+  LargestFirst,
+  /// Take files closest to the project root first, then alphabetically
+  PathPriority,
+  /// Keep every file but truncate each to its share of the budget
+  ProportionalTruncation,
+}
+
+#[derive(serde::Serialize)]
+pub struct FittedFile {
+  pub id: String,
+  pub tokens: usize,
+  /// Token count the file must be cut down to, for proportional truncation
+  pub truncate_to: Option<usize>,
+}
+
+#[derive(serde::Serialize)]
+pub struct BudgetSelection {
+  pub selected: Vec<FittedFile>,
+  pub skipped: Vec<String>,
+  /// Requested ids with no processed file in the store
+  pub missing: Vec<String>,
+  pub used_tokens: usize,
+  pub leftover_tokens: usize,
+}
+
+fn depth(path: &str) -> usize {
+  path.matches(['/', '\\']).count()
+}
+
+pub fn select(files: &[Arc<StoredFile>], max_tokens: usize, strategy: FitStrategy, encoder: &CoreBPE) -> BudgetSelection {
+  let sized: Vec<(&StoredFile, usize)> = files.iter().map(|f| (f.as_ref(), f.tokens(encoder))).collect();
+  let mut selected = Vec::new();
+  let mut skipped = Vec::new();
+  let mut used_tokens = 0;
+
+  match strategy {
+    FitStrategy::ProportionalTruncation => {
+      let total: usize = sized.iter().map(|(_, tokens)| tokens).sum();
+      for (file, tokens) in &sized {
+        let share = if total <= max_tokens {
+          *tokens
+        } else {
+          (*tokens as u128 * max_tokens as u128 / total as u128) as usize
+        };
+        if share == 0 {
+          skipped.push(file.id.clone());
+          continue;
+        }
+        used_tokens += share;
+        selected.push(FittedFile {
+          id: file.id.clone(),
+          tokens: share,
+          truncate_to: (share < *tokens).then_some(share),
+        });
+      }
+    }
+    FitStrategy::LargestFirst | FitStrategy::PathPriority => {
+      let mut order: Vec<usize> = (0..sized.len()).collect();
+      match strategy {
+        FitStrategy::LargestFirst => order.sort_by_key(|&i| std::cmp::Reverse(sized[i].1)),
+        _ => order.sort_by(|&a, &b| {
+          let (a, b) = (sized[a].0.label(), sized[b].0.label());
+          depth(a).cmp(&depth(b)).then_with(|| a.cmp(b))
+        }),
+      }
+      for i in order {
+        let (file, tokens) = sized[i];
+        if used_tokens + tokens > max_tokens {
+          skipped.push(file.id.clone());
+          continue;
+        }
+        used_tokens += tokens;
+        selected.push(FittedFile {
+          id: file.id.clone(),
+          tokens,
+          truncate_to: None,
+        });
+      }
+    }
+  }
+
+  BudgetSelection {
+    selected,
+    skipped,
+    missing: Vec::new(),
+    used_tokens,
+    leftover_tokens: max_tokens.saturating_sub(used_tokens),
+  }
+}
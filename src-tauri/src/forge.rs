@@ -0,0 +1,389 @@
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::TextractorError;
+use crate::generated;
+use crate::language;
+use crate::limits;
+use crate::scan::{self, FileInfo, ScanOptions, ScanWarning, SkipReason, SkippedFile};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest wait for a rate limit to reset before giving up
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+const MAX_ATTEMPTS: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Host {
+  GitHub,
+  GitLab,
+}
+
+/// A repository on GitHub or a GitLab instance, parsed from its web URL
+pub struct RemoteRepo {
+  host: Host,
+  /// `https://gitlab.example.com`
+  origin: String,
+  /// `owner/repo`, or `group/subgroup/project` on GitLab
+  project: String,
+  /// Branch, tag or commit named in the URL (`/tree/<ref>`)
+  reference: Option<String>,
+}
+
+impl RemoteRepo {
+  /// Parse `https://github.com/owner/repo`, `github.com/owner/repo/tree/main`
+  /// or `https://gitlab.com/group/project/-/tree/main`. Hosts other than
+  /// github.com are taken to be GitLab instances.
+  pub fn parse(url: &str) -> Result<Self, String> {
+    let trimmed = url.trim().trim_end_matches('/');
+    let (scheme, rest) = match trimmed.split_once("://") {
+      Some((scheme, rest)) => (scheme, rest),
+      None => ("https", trimmed),
+    };
+    let (host, path) = rest.split_once('/').ok_or_else(|| format!("{url} does not name a repository"))?;
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let origin = format!("{scheme}://{host}");
+
+    let (kind, project, reference) = if host.eq_ignore_ascii_case("github.com") || host.eq_ignore_ascii_case("www.github.com") {
+      if segments.len() < 2 {
+        return Err(format!("{url} does not name a GitHub repository"));
+      }
+      let reference = match segments.get(2) {
+        Some(&"tree") | Some(&"blob") if segments.len() > 3 => Some(segments[3..].join("/")),
+        _ => None,
+      };
+      (Host::GitHub, segments[..2].join("/"), reference)
+    } else {
+      let end = segments.iter().position(|&s| s == "-").unwrap_or(segments.len());
+      if end < 2 {
+        return Err(format!("{url} does not name a GitLab project"));
+      }
+      let reference = match segments.get(end + 1) {
+        Some(&"tree") | Some(&"blob") if segments.len() > end + 2 => Some(segments[end + 2..].join("/")),
+        _ => None,
+      };
+      (Host::GitLab, segments[..end].join("/"), reference)
+    };
+
+    Ok(Self {
+      host: kind,
+      origin,
+      project: project.trim_end_matches(".git").to_string(),
+      reference,
+    })
+  }
+
+  pub fn is_github(&self) -> bool {
+    self.host == Host::GitHub
+  }
+
+  /// Web URL of the repository, which loaded paths are shown under
+  fn web_url(&self) -> String {
+    format!("{}/{}", self.origin, self.project)
+  }
+
+  fn api_url(&self, path: &str) -> String {
+    match self.host {
+      Host::GitHub => format!("https://api.github.com/repos/{}{path}", self.project),
+      Host::GitLab => format!("{}/api/v4/projects/{}{path}", self.origin, encode(&self.project)),
+    }
+  }
+}
+
+/// Percent-encode everything but unreserved characters
+fn encode(text: &str) -> String {
+  let mut encoded = String::with_capacity(text.len());
+  for byte in text.bytes() {
+    if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+      encoded.push(byte as char);
+    } else {
+      encoded.push_str(&format!("%{byte:02X}"));
+    }
+  }
+  encoded
+}
+
+/// Percent-encode each segment of a `/`-separated path
+fn encode_path(path: &str) -> String {
+  path.split('/').map(encode).collect::<Vec<_>>().join("/")
+}
+
+/// Seconds until the rate limit on `response` resets, when it is exhausted
+fn rate_limit_wait(response: &ureq::Response) -> Option<Duration> {
+  if let Some(seconds) = response.header("retry-after").and_then(|s| s.trim().parse::<u64>().ok()) {
+    return Some(Duration::from_secs(seconds));
+  }
+  let remaining = response
+    .header("x-ratelimit-remaining")
+    .or_else(|| response.header("ratelimit-remaining"))?;
+  if remaining.trim() != "0" {
+    return None;
+  }
+  let reset = response
+    .header("x-ratelimit-reset")
+    .or_else(|| response.header("ratelimit-reset"))
+    .and_then(|s| s.trim().parse::<u64>().ok())?;
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+  Some(Duration::from_secs(reset.saturating_sub(now) + 1))
+}
+
+/// A file in a repository listing
+struct TreeEntry {
+  path: String,
+  /// Size in bytes, when the API reports it
+  size: Option<u64>,
+}
+
+/// Authenticated client for one repository's API
+struct Client<'a> {
+  repo: &'a RemoteRepo,
+  token: Option<&'a str>,
+}
+
+impl Client<'_> {
+  /// GET `url`, waiting out short rate limits and retrying
+  fn get(&self, url: &str) -> Result<ureq::Response, String> {
+    let mut request = ureq::get(url).timeout(REQUEST_TIMEOUT);
+    request = match (self.repo.host, self.token) {
+      (Host::GitHub, Some(token)) => request.set("Authorization", &format!("Bearer {token}")),
+      (Host::GitLab, Some(token)) => request.set("PRIVATE-TOKEN", token),
+      (_, None) => request,
+    };
+    if self.repo.host == Host::GitHub {
+      request = request.set("Accept", "application/vnd.github+json");
+    }
+
+    let mut attempt = 1;
+    loop {
+      match request.clone().call() {
+        Ok(response) => return Ok(response),
+        Err(ureq::Error::Status(status @ (403 | 429), response)) => {
+          let Some(wait) = rate_limit_wait(&response) else {
+            return Err(format!("{url} returned {status}: {}", response.status_text()));
+          };
+          if wait > MAX_RATE_LIMIT_WAIT || attempt == MAX_ATTEMPTS {
+            let hint = if self.token.is_none() {
+              "; an access token raises the limit"
+            } else {
+              ""
+            };
+            return Err(format!("API rate limit exceeded, resets in {} seconds{hint}", wait.as_secs()));
+          }
+          log::warn!("Rate limited by {}, retrying in {} seconds", self.repo.origin, wait.as_secs());
+          std::thread::sleep(wait);
+        }
+        Err(ureq::Error::Status(404, _)) => {
+          return Err(format!("{url} was not found; private repositories need an access token"));
+        }
+        Err(ureq::Error::Status(status, response)) => {
+          return Err(format!("{url} returned {status}: {}", response.status_text()));
+        }
+        Err(e) if attempt < MAX_ATTEMPTS => log::warn!("Request to {} failed, retrying: {}", url, e),
+        Err(e) => return Err(format!("request to {url} failed: {e}")),
+      }
+      attempt += 1;
+    }
+  }
+
+  fn get_json(&self, url: &str) -> Result<serde_json::Value, String> {
+    self
+      .get(url)?
+      .into_json()
+      .map_err(|e| format!("{url} did not return JSON: {e}"))
+  }
+
+  fn default_branch(&self) -> Result<String, String> {
+    self.get_json(&self.repo.api_url(""))?["default_branch"]
+      .as_str()
+      .map(str::to_string)
+      .ok_or_else(|| "the repository has no default branch".to_string())
+  }
+
+  /// Every file at `reference`, and whether the listing was cut short
+  fn tree(&self, reference: &str) -> Result<(Vec<TreeEntry>, bool), String> {
+    // Symlinks are blobs too, holding their target
+    let is_file = |entry: &serde_json::Value| entry["type"] == "blob" && entry["mode"] != "120000";
+    match self.repo.host {
+      Host::GitHub => {
+        let url = self.repo.api_url(&format!("/git/trees/{}?recursive=1", encode(reference)));
+        let response = self.get_json(&url)?;
+        let files = response["tree"]
+          .as_array()
+          .ok_or("tree response has no entries")?
+          .iter()
+          .filter(|entry| is_file(entry))
+          .filter_map(|entry| {
+            Some(TreeEntry {
+              path: entry["path"].as_str()?.to_string(),
+              size: entry["size"].as_u64(),
+            })
+          })
+          .collect();
+        Ok((files, response["truncated"].as_bool().unwrap_or(false)))
+      }
+      Host::GitLab => {
+        let mut files = Vec::new();
+        let mut page = "1".to_string();
+        loop {
+          let url = self.repo.api_url(&format!(
+            "/repository/tree?recursive=true&per_page=100&ref={}&page={page}",
+            encode(reference)
+          ));
+          let response = self.get(&url)?;
+          let next = response.header("x-next-page").unwrap_or_default().trim().to_string();
+          let entries: Vec<serde_json::Value> =
+            response.into_json().map_err(|e| format!("{url} did not return JSON: {e}"))?;
+          files.extend(
+            entries
+              .iter()
+              .filter(|entry| is_file(entry))
+              .filter_map(|entry| {
+                Some(TreeEntry {
+                  path: entry["path"].as_str()?.to_string(),
+                  size: None,
+                })
+              }),
+          );
+          if next.is_empty() {
+            return Ok((files, false));
+          }
+          page = next;
+        }
+      }
+    }
+  }
+
+  /// Content of the file at `path`, cut off one byte past `limit`. GitHub
+  /// serves it outside the API, so downloads do not use up the rate limit.
+  fn content(&self, reference: &str, path: &str, limit: u64) -> Result<Vec<u8>, String> {
+    let url = match self.repo.host {
+      Host::GitHub => format!(
+        "https://raw.githubusercontent.com/{}/{}/{}",
+        self.repo.project,
+        encode_path(reference),
+        encode_path(path)
+      ),
+      Host::GitLab => self.repo.api_url(&format!("/repository/files/{}/raw?ref={}", encode(path), encode(reference))),
+    };
+    let mut bytes = Vec::new();
+    self
+      .get(&url)?
+      .into_reader()
+      .take(limit + 1)
+      .read_to_end(&mut bytes)
+      .map_err(|e| format!("failed to download {path}: {e}"))?;
+    Ok(bytes)
+  }
+}
+
+/// Download the files of `repo` at `reference` (or the ref in its URL, or
+/// its default branch) through the host's REST API, applying the same
+/// filters, hidden-file rule and size limit as a local scan. Each file is
+/// handed to `on_file` as it arrives and each left out to `on_skip`; paths
+/// are shown under the repository's web URL. Returns the number of files
+/// read, and a warning when the host listed only part of the tree.
+pub fn fetch(
+  repo: &RemoteRepo,
+  reference: Option<&str>,
+  token: Option<&str>,
+  options: &ScanOptions,
+  mut on_file: impl FnMut(FileInfo),
+  mut on_skip: impl FnMut(SkippedFile),
+) -> Result<(usize, Option<ScanWarning>), String> {
+  let client = Client { repo, token };
+  let reference = match reference.or(repo.reference.as_deref()) {
+    Some(reference) => reference.to_string(),
+    None => client.default_branch()?,
+  };
+  let (entries, truncated) = client.tree(&reference)?;
+  let web_url = repo.web_url();
+  let max_file_size = limits::max_file_size() as u64;
+
+  let mut count = 0;
+  for TreeEntry { path, size } in entries {
+    let relative = Path::new(&path);
+    let shown = format!("{web_url}/{path}");
+    let skip = |error: TextractorError| SkippedFile {
+      path: shown.clone(),
+      reason: SkipReason::of(&error),
+      message: error.to_string(),
+    };
+
+    if relative.components().any(|c| c.as_os_str().to_string_lossy().starts_with('.')) {
+      on_skip(SkippedFile {
+        path: shown,
+        reason: SkipReason::Hidden,
+        message: "hidden file or directory".to_string(),
+      });
+      continue;
+    }
+    if options.in_excluded_dir(relative) || !options.accepts_file(relative) {
+      continue;
+    }
+    if !scan::is_text_file(relative) {
+      on_skip(skip(TextractorError::BinaryFile { path: shown.clone() }));
+      continue;
+    }
+    let too_large = |size: u64| {
+      skip(TextractorError::FileTooLarge {
+        path: shown.clone(),
+        size,
+        limit: max_file_size,
+      })
+    };
+    if let Some(size) = size.filter(|&size| size > max_file_size) {
+      on_skip(too_large(size));
+      continue;
+    }
+
+    let bytes = match client.content(&reference, &path, max_file_size) {
+      Ok(bytes) => bytes,
+      Err(message) => {
+        on_skip(skip(TextractorError::IoError {
+          path: shown.clone(),
+          message,
+        }));
+        continue;
+      }
+    };
+    if bytes.len() as u64 > max_file_size {
+      on_skip(too_large(bytes.len() as u64));
+      continue;
+    }
+    let Ok(content) = String::from_utf8(bytes) else {
+      on_skip(skip(TextractorError::EncodingError { path: shown.clone() }));
+      continue;
+    };
+
+    let name = relative.file_name().map_or_else(|| path.clone(), |n| n.to_string_lossy().to_string());
+    let generated = generated::is_generated(&name, &content);
+    if generated && !options.include_generated {
+      on_skip(SkippedFile {
+        path: shown,
+        reason: SkipReason::Generated,
+        message: "generated or minified file".to_string(),
+      });
+      continue;
+    }
+    count += 1;
+    on_file(FileInfo {
+      language: language::detect(&name, &content),
+      generated,
+      name,
+      path: shown,
+      size: content.len() as u64,
+      content,
+      is_text: true,
+      modified_ms: None,
+      root: None,
+      relative_path: None,
+      binary: None,
+    });
+  }
+
+  let warning = truncated.then(|| ScanWarning {
+    path: web_url,
+    message: format!("The host listed only part of the repository; {count} files were read"),
+  });
+  Ok((count, warning))
+}
@@ -0,0 +1,97 @@
+/// File name endings of build output and code generators
+const GENERATED_SUFFIXES: &[&str] = &[
+  ".min.js",
+  ".min.mjs",
+  ".min.css",
+  "-min.js",
+  ".bundle.js",
+  ".chunk.js",
+  ".pb.go",
+  ".pb.gw.go",
+  ".pb.cc",
+  ".pb.h",
+  "_pb2.py",
+  "_pb2.pyi",
+  "_pb2_grpc.py",
+  "_pb.js",
+  "_pb.d.ts",
+  "_grpc_pb.js",
+  ".g.dart",
+  ".freezed.dart",
+  ".designer.cs",
+  ".g.cs",
+  ".generated.cs",
+  ".generated.ts",
+  ".generated.js",
+];
+
+/// Name starts of generated Go code (`zz_generated.deepcopy.go`)
+const GENERATED_PREFIXES: &[&str] = &["zz_generated"];
+
+/// Phrases generators put at the top of their output
+const GENERATED_MARKERS: &[&str] = &[
+  "@generated",
+  "code generated",
+  "do not edit",
+  "auto-generated",
+  "autogenerated",
+  "automatically generated",
+];
+
+/// How far into a file the generator markers are looked for
+const HEADER_LEN: usize = 1024;
+/// Files shorter than this are never judged minified by their line lengths
+const MIN_MINIFIED_LEN: usize = 1024;
+/// A single line this long means the file is not meant to be read
+const MAX_LINE_LEN: usize = 5000;
+/// Nor does an average line this long
+const MAX_AVERAGE_LINE_LEN: usize = 300;
+
+/// Extensions of prose and data where long lines are normal
+fn allows_long_lines(name: &str) -> bool {
+  let ext = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase()).unwrap_or_default();
+  matches!(ext.as_str(), "md" | "mdx" | "markdown" | "txt" | "rst" | "adoc" | "csv" | "tsv" | "log")
+}
+
+/// Leading part of `content`, cut back to a character boundary
+fn header(content: &str) -> &str {
+  let mut end = HEADER_LEN.min(content.len());
+  while !content.is_char_boundary(end) {
+    end -= 1;
+  }
+  &content[..end]
+}
+
+/// Whether a file looks generated or minified, by its name (`*.min.js`,
+/// `*.pb.go`), a generator marker near its top (`// Code generated ... DO NOT
+/// EDIT`, `@generated`), a source map comment, or lines too long to read
+pub fn is_generated(name: &str, content: &str) -> bool {
+  let lower_name = name.to_lowercase();
+  if GENERATED_SUFFIXES.iter().any(|suffix| lower_name.ends_with(suffix))
+    || GENERATED_PREFIXES.iter().any(|prefix| lower_name.starts_with(prefix))
+  {
+    return true;
+  }
+
+  let head = header(content).to_lowercase();
+  if GENERATED_MARKERS.iter().any(|marker| head.contains(marker)) {
+    return true;
+  }
+  // Bundlers end their output with a pointer to its source map
+  let tail = content.trim_end().rsplit('\n').next().unwrap_or_default();
+  if tail.starts_with("//# sourceMappingURL=") || tail.starts_with("/*# sourceMappingURL=") {
+    return true;
+  }
+
+  if content.len() < MIN_MINIFIED_LEN || allows_long_lines(name) {
+    return false;
+  }
+  let mut lines = 0;
+  for line in content.lines() {
+    if line.len() > MAX_LINE_LEN {
+      return true;
+    }
+    lines += 1;
+  }
+  content.len() / lines.max(1) > MAX_AVERAGE_LINE_LEN
+}
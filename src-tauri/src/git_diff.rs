@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use git2::{Delta, DiffFindOptions, DiffOptions, Patch, Repository};
+
+/// How a file differs from the base
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+  Added,
+  Modified,
+  Deleted,
+  Renamed,
+  Copied,
+  TypeChanged,
+}
+
+/// A file that differs from the base
+#[derive(serde::Serialize)]
+pub struct ChangedFile {
+  /// Path relative to the repository root; the old path for deletions
+  pub path: String,
+  /// Where a renamed or copied file came from
+  pub old_path: Option<String>,
+  pub kind: ChangeKind,
+  /// Unified diff of the file, when hunks were asked for
+  pub patch: Option<String>,
+}
+
+/// The files changed in a repository
+pub struct Changes {
+  /// The repository's working directory
+  pub workdir: PathBuf,
+  pub files: Vec<ChangedFile>,
+}
+
+fn kind(delta: Delta) -> Option<ChangeKind> {
+  Some(match delta {
+    Delta::Added | Delta::Untracked => ChangeKind::Added,
+    Delta::Modified => ChangeKind::Modified,
+    Delta::Deleted => ChangeKind::Deleted,
+    Delta::Renamed => ChangeKind::Renamed,
+    Delta::Copied => ChangeKind::Copied,
+    Delta::Typechange => ChangeKind::TypeChanged,
+    _ => return None,
+  })
+}
+
+fn path_string(path: Option<&Path>) -> Option<String> {
+  path.map(|p| p.to_string_lossy().replace('\\', "/"))
+}
+
+/// The files that differ between where the current branch forked from
+/// `base_ref` and the working directory: committed, staged and unstaged
+/// changes, and new untracked files, as a pull request would show them.
+/// `root` may be anywhere inside the repository.
+pub fn changed_files(root: &Path, base_ref: &str, include_hunks: bool) -> Result<Changes, String> {
+  let repo = Repository::discover(root).map_err(|e| format!("{} is not in a git repository: {}", root.display(), e.message()))?;
+  let workdir = repo.workdir().ok_or("bare repositories have no working files")?.to_path_buf();
+
+  let base = repo
+    .revparse_single(base_ref)
+    .and_then(|object| object.peel_to_commit())
+    .map_err(|e| format!("unknown base {base_ref}: {}", e.message()))?;
+  // Changes made on the base since the branch forked are not the branch's
+  let fork_point = repo
+    .head()
+    .and_then(|head| head.peel_to_commit())
+    .and_then(|head| repo.merge_base(base.id(), head.id()))
+    .and_then(|id| repo.find_commit(id))
+    .unwrap_or(base);
+  let base_tree = fork_point.tree().map_err(|e| e.message().to_string())?;
+
+  let mut options = DiffOptions::new();
+  options
+    .include_untracked(true)
+    .recurse_untracked_dirs(true)
+    .show_untracked_content(true);
+  let mut diff = repo
+    .diff_tree_to_workdir_with_index(Some(&base_tree), Some(&mut options))
+    .map_err(|e| format!("failed to diff against {base_ref}: {}", e.message()))?;
+  diff
+    .find_similar(Some(DiffFindOptions::new().renames(true).copies(true)))
+    .map_err(|e| e.message().to_string())?;
+
+  let mut files = Vec::new();
+  for (index, delta) in diff.deltas().enumerate() {
+    let Some(kind) = kind(delta.status()) else {
+      continue;
+    };
+    let new_path = path_string(delta.new_file().path());
+    let old_path = path_string(delta.old_file().path());
+    let (path, old_path) = match kind {
+      ChangeKind::Deleted => (old_path, None),
+      ChangeKind::Renamed | ChangeKind::Copied => (new_path, old_path),
+      _ => (new_path, None),
+    };
+    let Some(path) = path else {
+      continue;
+    };
+    let patch = if include_hunks {
+      Patch::from_diff(&diff, index)
+        .ok()
+        .flatten()
+        .and_then(|mut patch| patch.to_buf().ok())
+        .map(|buf| String::from_utf8_lossy(&buf).into_owned())
+    } else {
+      None
+    };
+    files.push(ChangedFile {
+      path,
+      old_path,
+      kind,
+      patch,
+    });
+  }
+
+  Ok(Changes { workdir, files })
+}
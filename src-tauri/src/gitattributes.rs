@@ -0,0 +1,134 @@
+use globset::{GlobBuilder, GlobMatcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A `.gitattributes` line that sets or unsets `linguist-generated` or
+/// `linguist-vendored`
+struct Rule {
+  matcher: GlobMatcher,
+  excluded: bool,
+}
+
+/// The `linguist-generated` / `linguist-vendored` markers that apply below a
+/// scan root, i.e. the files GitHub collapses in diffs and leaves out of
+/// language statistics
+pub struct LinguistRules {
+  base: PathBuf,
+  rules: Vec<Rule>,
+}
+
+/// Whether an attribute line marks matching paths as excluded (`Some(true)`),
+/// explicitly not excluded (`Some(false)`), or says nothing about it
+fn linguist_flag(attributes: &[&str]) -> Option<bool> {
+  let mut flag = None;
+  for attribute in attributes {
+    let (name, value) = match attribute.split_once('=') {
+      Some((name, value)) => (name, value),
+      None => (*attribute, "true"),
+    };
+    let (name, set) = match name.strip_prefix(['-', '!']) {
+      Some(name) => (name, false),
+      None => (name, value != "false"),
+    };
+    if name == "linguist-generated" || name == "linguist-vendored" {
+      flag = Some(flag.unwrap_or(false) || set);
+    }
+  }
+  flag
+}
+
+impl LinguistRules {
+  /// Rules from the `.gitattributes` files in `root` and its ancestors, up to
+  /// the enclosing repository root
+  pub fn for_root(root: &Path) -> Self {
+    let mut dirs = Vec::new();
+    for dir in root.ancestors() {
+      dirs.push(dir.to_path_buf());
+      if dir.join(".git").exists() {
+        break;
+      }
+    }
+    // Without a repository, only the dropped directory's own file counts
+    if !dirs.last().is_some_and(|d| d.join(".git").exists()) {
+      dirs.truncate(1);
+    }
+
+    let mut rules = Self {
+      base: dirs.last().cloned().unwrap_or_else(|| root.to_path_buf()),
+      rules: Vec::new(),
+    };
+    // Outer files first so that deeper, more specific files override them
+    for dir in dirs.iter().rev() {
+      rules.add_dir(dir);
+    }
+    rules
+  }
+
+  /// Load `dir/.gitattributes`, if any, on top of the existing rules
+  pub fn add_dir(&mut self, dir: &Path) {
+    let Ok(raw) = fs::read_to_string(dir.join(".gitattributes")) else {
+      return;
+    };
+    let Some(prefix) = self.relative(dir) else {
+      return;
+    };
+
+    for line in raw.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let mut parts = line.split_whitespace();
+      let Some(pattern) = parts.next() else {
+        continue;
+      };
+      let Some(excluded) = linguist_flag(&parts.collect::<Vec<_>>()) else {
+        continue;
+      };
+
+      // As in .gitignore, a pattern without a slash matches at any depth
+      let pattern = pattern.trim_end_matches('/');
+      let glob = match pattern.strip_prefix('/') {
+        Some(anchored) => format!("{prefix}{anchored}"),
+        None if pattern.contains('/') => format!("{prefix}{pattern}"),
+        None => format!("{prefix}**/{pattern}"),
+      };
+      match GlobBuilder::new(&glob).literal_separator(true).build() {
+        Ok(glob) => self.rules.push(Rule {
+          matcher: glob.compile_matcher(),
+          excluded,
+        }),
+        Err(e) => log::warn!("Ignoring invalid .gitattributes pattern {}: {}", pattern, e),
+      }
+    }
+  }
+
+  /// `path` relative to the rule base with `/` separators and a trailing `/`
+  /// for non-empty results, or `None` if it lies outside the base
+  fn relative(&self, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(&self.base).ok()?;
+    let mut out = String::new();
+    for component in relative.components() {
+      out.push_str(&component.as_os_str().to_string_lossy());
+      out.push('/');
+    }
+    Some(out)
+  }
+
+  /// Whether the last matching rule marks `path` as generated or vendored.
+  /// Directories are checked too, so a marked directory is skipped whole.
+  pub fn is_excluded(&self, path: &Path) -> bool {
+    if self.rules.is_empty() {
+      return false;
+    }
+    let Some(relative) = self.relative(path) else {
+      return false;
+    };
+    let relative = relative.trim_end_matches('/');
+    self
+      .rules
+      .iter()
+      .rev()
+      .find(|rule| rule.matcher.is_match(relative))
+      .is_some_and(|rule| rule.excluded)
+  }
+}
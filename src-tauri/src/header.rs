@@ -0,0 +1,104 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Leading lines kept verbatim when comments are removed or code is minified
+#[derive(Clone, Copy, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct HeaderOptions {
+  /// Keep a `#!/usr/bin/env ...` interpreter line
+  pub preserve_shebang: bool,
+  /// Keep a leading comment block carrying an SPDX identifier, copyright or
+  /// licence notice
+  pub preserve_license_header: bool,
+}
+
+static LICENSE_NOTICE: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"(?i)SPDX-License-Identifier|copyright|\blicen[cs]e[ds]?\b|©|\(c\)\s*\d{4}").expect("valid regex")
+});
+
+/// Block comment (and docstring) delimiters a header may use
+const BLOCKS: &[(&str, &str)] = &[
+  ("/*", "*/"),
+  ("<!--", "-->"),
+  ("{-", "-}"),
+  ("(*", "*)"),
+  ("\"\"\"", "\"\"\""),
+  ("'''", "'''"),
+];
+
+/// Line comment markers a header may use
+const LINE_MARKERS: &[&str] = &["//", "#", "--", ";", "%", "'"];
+
+/// Offset just past the line that `at` is on
+fn line_end(text: &str, at: usize) -> usize {
+  text[at..].find('\n').map_or(text.len(), |i| at + i + 1)
+}
+
+/// Length of the `#!` line opening `code`; Rust's `#![attr]` is not one
+fn shebang_len(code: &str) -> usize {
+  if code.starts_with("#!") && !code.starts_with("#![") {
+    line_end(code, 0)
+  } else {
+    0
+  }
+}
+
+/// Length of the comment block opening `text`, through the end of its last line
+fn comment_block_len(text: &str) -> Option<usize> {
+  if let Some((open, close)) = BLOCKS.iter().find(|(open, _)| text.starts_with(open)) {
+    let end = text[open.len()..].find(close)? + open.len() + close.len();
+    return Some(line_end(text, end));
+  }
+
+  let marker = LINE_MARKERS.iter().find(|marker| text.starts_with(*marker))?;
+  let len = text
+    .split_inclusive('\n')
+    .take_while(|line| line.trim_start().starts_with(marker))
+    .map(str::len)
+    .sum();
+  Some(len)
+}
+
+/// Run `process` over `code`, keeping the shebang and licence header that
+/// `options` asks for verbatim in front of the processed rest
+pub fn apply(code: &str, options: HeaderOptions, process: impl Fn(&str) -> String) -> String {
+  let shebang = shebang_len(code);
+  let keep_shebang = options.preserve_shebang && shebang > 0;
+
+  let blank = code[shebang..].len() - code[shebang..].trim_start().len();
+  let license_start = shebang + blank;
+  let license_end = options
+    .preserve_license_header
+    .then(|| comment_block_len(&code[license_start..]))
+    .flatten()
+    .filter(|&len| LICENSE_NOTICE.is_match(&code[license_start..license_start + len]))
+    .map(|len| license_start + len);
+
+  if !keep_shebang && license_end.is_none() {
+    return process(code);
+  }
+
+  let mut out = String::with_capacity(code.len());
+  let body_start = match license_end {
+    Some(end) => {
+      if keep_shebang {
+        out.push_str(&code[..license_start]);
+      } else {
+        out.push_str(&process(&code[..shebang]));
+      }
+      out.push_str(&code[license_start..end]);
+      end
+    }
+    None => {
+      out.push_str(&code[..shebang]);
+      shebang
+    }
+  };
+
+  let body = process(&code[body_start..]);
+  if !out.is_empty() && !out.ends_with('\n') && !body.is_empty() {
+    out.push('\n');
+  }
+  out.push_str(&body);
+  out
+}
@@ -0,0 +1,142 @@
+use crate::{css, js_minify};
+
+/// Elements whose content is not collapsed like text: kept as written, or
+/// minified as the script or stylesheet it is
+const RAW_ELEMENTS: &[&str] = &["pre", "textarea", "script", "style"];
+
+/// Byte offset of `needle` in `haystack`, ignoring ASCII case
+pub(crate) fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+  haystack
+    .as_bytes()
+    .windows(needle.len())
+    .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Length of the tag at the start of `rest`, up to its `>` outside quotes
+pub(crate) fn tag_len(rest: &str) -> Option<usize> {
+  let mut quote = None;
+  for (i, c) in rest.char_indices().skip(1) {
+    match (quote, c) {
+      (Some(q), c) if c == q => quote = None,
+      (Some(_), _) => {}
+      (None, '"' | '\'') => quote = Some(c),
+      (None, '>') => return Some(i + 1),
+      _ => {}
+    }
+  }
+  None
+}
+
+/// `tag` with whitespace runs outside attribute values collapsed to a space,
+/// and none before the closing `>` or `/>`
+fn collapse_tag(tag: &str) -> String {
+  let mut out = String::with_capacity(tag.len());
+  let mut quote = None;
+  let mut space = false;
+  for c in tag.chars() {
+    match quote {
+      Some(q) => {
+        out.push(c);
+        if c == q {
+          quote = None;
+        }
+      }
+      None if c.is_whitespace() => space = true,
+      None => {
+        if std::mem::take(&mut space) {
+          out.push(' ');
+        }
+        if c == '"' || c == '\'' {
+          quote = Some(c);
+        }
+        out.push(c);
+      }
+    }
+  }
+  for (spaced, closing) in [(" />", "/>"), (" >", ">")] {
+    if out.ends_with(spaced) {
+      out.truncate(out.len() - spaced.len());
+      out.push_str(closing);
+    }
+  }
+  out
+}
+
+/// `text` with whitespace runs collapsed to a space; runs at either end that
+/// span a line break are formatting and dropped
+fn collapse_text(text: &str) -> String {
+  let trimmed = text.trim();
+  if trimmed.is_empty() {
+    return if text.is_empty() || text.contains('\n') { String::new() } else { " ".to_string() };
+  }
+  let edge = |whitespace: &str| if whitespace.is_empty() || whitespace.contains('\n') { "" } else { " " };
+  let leading = &text[..text.len() - text.trim_start().len()];
+  let trailing = &text[text.trim_end().len()..];
+  format!("{}{}{}", edge(leading), trimmed.split_whitespace().collect::<Vec<_>>().join(" "), edge(trailing))
+}
+
+fn tag_name(tag: &str) -> String {
+  tag[1..]
+    .chars()
+    .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+    .collect::<String>()
+    .to_ascii_lowercase()
+}
+
+/// Content of a raw element: scripts and stylesheets minified when they
+/// parse, everything else as written
+fn raw_content(name: &str, open_tag: &str, content: &str, minify_embedded: bool) -> String {
+  let minified = match name {
+    _ if !minify_embedded || content.trim().is_empty() => None,
+    "style" => css::minify(content),
+    "script" => {
+      let lower = open_tag.to_ascii_lowercase();
+      let is_js = !lower.contains("type=") || ["javascript", "module", "ecmascript"].iter().any(|t| lower.contains(t));
+      is_js.then(|| js_minify::minify(content)).flatten()
+    }
+    _ => None,
+  };
+  minified.unwrap_or_else(|| content.to_string())
+}
+
+/// Collapse the whitespace of an HTML or XML document. Attribute values,
+/// comments, CDATA sections and, in HTML, `<pre>` and `<textarea>` keep
+/// theirs; inline `<script>` and `<style>` blocks are minified as JavaScript
+/// and CSS when `minify_embedded` and kept as written otherwise.
+pub fn minify(code: &str, html: bool, minify_embedded: bool) -> String {
+  let mut out = String::with_capacity(code.len());
+  let mut rest = code;
+
+  while !rest.is_empty() {
+    let verbatim_end = [("<!--", "-->"), ("<![CDATA[", "]]>")]
+      .iter()
+      .find(|(open, _)| rest.starts_with(open))
+      .map(|(open, close)| rest[open.len()..].find(close).map_or(rest.len(), |i| open.len() + i + close.len()));
+    if let Some(end) = verbatim_end {
+      out.push_str(&rest[..end]);
+      rest = &rest[end..];
+      continue;
+    }
+
+    let starts_tag = rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?'));
+    let Some(len) = starts_tag.then(|| tag_len(rest)).flatten() else {
+      // Text runs to the next tag; a `<` that starts none is text too
+      let end = rest.char_indices().skip(1).find(|&(_, c)| c == '<').map_or(rest.len(), |(i, _)| i);
+      out.push_str(&collapse_text(&rest[..end]));
+      rest = &rest[end..];
+      continue;
+    };
+
+    let tag = &rest[..len];
+    out.push_str(&collapse_tag(tag));
+    rest = &rest[len..];
+
+    let name = tag_name(tag);
+    if html && !tag.ends_with("/>") && RAW_ELEMENTS.contains(&name.as_str()) {
+      let end = find_ignore_case(rest, &format!("</{name}")).unwrap_or(rest.len());
+      out.push_str(&raw_content(&name, tag, &rest[..end], minify_embedded));
+      rest = &rest[end..];
+    }
+  }
+  out
+}
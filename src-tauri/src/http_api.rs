@@ -0,0 +1,237 @@
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tauri::Manager;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::combined::{self, CombinedOptions};
+use crate::settings::SettingsState;
+use crate::store::FileStore;
+use crate::tokens::{self, TokenModel, TOKENIZER};
+
+/// Port the API listens on unless another is asked for
+pub const DEFAULT_PORT: u16 = 7395;
+/// Largest request body read, in bytes
+const MAX_BODY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Persisted while the API is enabled, so it comes back on the next launch
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct HttpApiSettings {
+  pub port: u16,
+  /// Clients send it as `Authorization: Bearer <token>`
+  pub token: String,
+}
+
+/// Where the running API listens and the token it expects
+#[derive(Clone, serde::Serialize)]
+pub struct HttpApiInfo {
+  pub url: String,
+  pub token: String,
+}
+
+/// A fresh random token, as hex
+pub fn new_token() -> Result<String, String> {
+  let mut bytes = [0u8; 32];
+  getrandom::getrandom(&mut bytes).map_err(|e| format!("failed to generate a token: {e}"))?;
+  Ok(bytes.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+struct Running {
+  server: Arc<Server>,
+  info: HttpApiInfo,
+}
+
+/// Tauri-managed handle on the embedded HTTP server, if it is running
+#[derive(Default)]
+pub struct HttpApiState {
+  running: Mutex<Option<Running>>,
+}
+
+impl HttpApiState {
+  /// Listen on `127.0.0.1:<port>`, replacing a server already running. Each
+  /// request is answered on a thread of its own.
+  pub fn start(&self, app_handle: tauri::AppHandle, settings: &HttpApiSettings) -> Result<HttpApiInfo, String> {
+    self.stop();
+    let address = format!("127.0.0.1:{}", settings.port);
+    let server = Arc::new(Server::http(&address).map_err(|e| format!("failed to listen on {address}: {e}"))?);
+    let info = HttpApiInfo {
+      url: format!("http://{address}"),
+      token: settings.token.clone(),
+    };
+
+    // Comparing hashes takes the same time however much of a guess is right
+    let token_hash = blake3::hash(settings.token.as_bytes());
+    let accepting = Arc::clone(&server);
+    std::thread::spawn(move || {
+      // Ends once `stop` unblocks the server
+      for request in accepting.incoming_requests() {
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || handle(&app_handle, &token_hash, request));
+      }
+    });
+
+    log::info!("HTTP API listening on {}", info.url);
+    *self.running.lock().unwrap_or_else(|e| e.into_inner()) = Some(Running {
+      server,
+      info: info.clone(),
+    });
+    Ok(info)
+  }
+
+  pub fn stop(&self) {
+    if let Some(running) = self.running.lock().unwrap_or_else(|e| e.into_inner()).take() {
+      running.server.unblock();
+      log::info!("HTTP API stopped");
+    }
+  }
+
+  pub fn info(&self) -> Option<HttpApiInfo> {
+    self.running.lock().unwrap_or_else(|e| e.into_inner()).as_ref().map(|running| running.info.clone())
+  }
+}
+
+/// Body of `POST /process`; the fields of `process_code`
+#[derive(serde::Deserialize)]
+struct ProcessRequest {
+  code: String,
+  mode: String,
+  #[serde(default)]
+  extension: String,
+  #[serde(default)]
+  options: Option<crate::CodeOptions>,
+}
+
+/// Body of `POST /count-tokens`; the fields of `count_tokens`
+#[derive(serde::Deserialize)]
+struct CountRequest {
+  text: String,
+  model: Option<String>,
+  special_tokens: Option<bool>,
+}
+
+/// Body of `POST /export`; the fields of `build_combined_output`
+#[derive(serde::Deserialize)]
+struct ExportRequest {
+  file_ids: Vec<String>,
+  options: Option<CombinedOptions>,
+}
+
+/// A failed request: HTTP status and message
+type Failure = (u16, String);
+
+fn parse<'a, T: serde::Deserialize<'a>>(body: &'a str) -> Result<T, Failure> {
+  serde_json::from_str(body).map_err(|e| (400, format!("invalid request body: {e}")))
+}
+
+/// `POST /process`, which needs nothing from the app
+fn process(body: &str) -> Result<Value, Failure> {
+  let request: ProcessRequest = parse(body)?;
+  let content = crate::process_text(request.code, &request.mode, &request.extension, request.options.unwrap_or_default());
+  Ok(json!({ "content": content }))
+}
+
+fn route(app_handle: &tauri::AppHandle, path: &str, body: &str) -> Result<Value, Failure> {
+  match path {
+    "/process" => process(body),
+    "/count-tokens" => {
+      let request: CountRequest = parse(body)?;
+      let settings = app_handle.state::<SettingsState>().get();
+      let model = TokenModel::parse(request.model.as_deref(), &settings);
+      let count = tokens::count(&model, &request.text, &settings, request.special_tokens.unwrap_or(false))
+        .map_err(|e| (500, e))?;
+      Ok(json!({ "tokens": count }))
+    }
+    "/export" => {
+      let request: ExportRequest = parse(body)?;
+      let encoder = TOKENIZER.as_ref().map_err(|e| (500, e.clone()))?;
+      let options = request.options.unwrap_or_default();
+      let template = app_handle
+        .state::<SettingsState>()
+        .get()
+        .template(options.template.as_deref())
+        .map_err(|e| (400, e))?;
+      let (files, missing) = app_handle.state::<FileStore>().get_many(&request.file_ids);
+      let output = combined::output(&files, missing, &options, template.as_ref(), encoder).map_err(|e| (500, e))?;
+      serde_json::to_value(output).map_err(|e| (500, e.to_string()))
+    }
+    _ => Err((404, format!("no endpoint {path}"))),
+  }
+}
+
+/// Whether the `Authorization` header value carries the bearer token hashed
+/// to `token_hash`
+fn is_authorized(authorization: Option<&str>, token_hash: &blake3::Hash) -> bool {
+  authorization
+    .and_then(|value| value.strip_prefix("Bearer "))
+    .is_some_and(|token| blake3::hash(token.trim().as_bytes()) == *token_hash)
+}
+
+fn respond(app_handle: &tauri::AppHandle, token_hash: &blake3::Hash, request: &mut Request) -> Result<Value, Failure> {
+  let authorization = request
+    .headers()
+    .iter()
+    .find(|header| header.field.equiv("Authorization"))
+    .map(|header| header.value.as_str());
+  if !is_authorized(authorization, token_hash) {
+    return Err((401, "missing or wrong bearer token".to_string()));
+  }
+  if *request.method() != Method::Post {
+    return Err((405, "endpoints take POST requests".to_string()));
+  }
+
+  let mut body = String::new();
+  request
+    .as_reader()
+    .take(MAX_BODY_SIZE + 1)
+    .read_to_string(&mut body)
+    .map_err(|e| (400, format!("failed to read request body: {e}")))?;
+  if body.len() as u64 > MAX_BODY_SIZE {
+    return Err((413, format!("request body over {} MB", MAX_BODY_SIZE / (1024 * 1024))));
+  }
+
+  let path = request.url().split('?').next().unwrap_or_default().to_string();
+  route(app_handle, &path, &body)
+}
+
+fn handle(app_handle: &tauri::AppHandle, token_hash: &blake3::Hash, mut request: Request) {
+  let (status, body) = match respond(app_handle, token_hash, &mut request) {
+    Ok(body) => (200, body),
+    Err((status, message)) => (status, json!({ "error": message })),
+  };
+  let content_type = Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+  let response = Response::from_string(body.to_string())
+    .with_status_code(status)
+    .with_header(content_type);
+  if let Err(e) = request.respond(response) {
+    log::warn!("Failed to answer HTTP API request: {}", e);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_the_bearer_token_is_authorized() {
+    let token = new_token().expect("random token");
+    let hash = blake3::hash(token.as_bytes());
+    assert!(is_authorized(Some(&format!("Bearer {token}")), &hash));
+    assert!(!is_authorized(Some(&format!("Basic {token}")), &hash));
+    assert!(!is_authorized(Some("Bearer wrong"), &hash));
+    assert!(!is_authorized(None, &hash));
+  }
+
+  #[test]
+  fn process_endpoint_strips_comments() {
+    let body = json!({ "code": "let a = 1; // note\n", "mode": "remove-comments", "extension": "rs" }).to_string();
+    let response = process(&body).expect("processed");
+    assert_eq!(response["content"].as_str().map(str::trim_end), Some("let a = 1;"));
+  }
+
+  #[test]
+  fn malformed_bodies_are_bad_requests() {
+    let (status, _) = process("{\"code\": 1}").expect_err("rejected");
+    assert_eq!(status, 400);
+  }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndentKind {
+  /// No indented lines
+  None,
+  Tabs,
+  Spaces,
+  /// Both tab- and space-indented lines
+  Mixed,
+}
+
+/// How a file is indented
+#[derive(serde::Serialize)]
+pub struct Indentation {
+  pub kind: IndentKind,
+  /// Spaces per level, when indented with spaces
+  pub width: Option<usize>,
+  /// Lines whose indentation disagrees with the dominant style, including
+  /// lines that mix tabs and spaces in their own indentation
+  pub inconsistent_lines: usize,
+}
+
+impl Indentation {
+  /// Every indented line uses the same character and, for spaces, a whole
+  /// number of levels
+  pub fn is_consistent(&self) -> bool {
+    self.kind != IndentKind::Mixed && self.inconsistent_lines == 0
+  }
+}
+
+fn leading_whitespace(line: &str) -> &str {
+  &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+pub fn detect(content: &str) -> Indentation {
+  let mut tab_lines = 0;
+  let mut space_lines = 0;
+  let mut mixed_lines = 0;
+  let mut space_indents = Vec::new();
+
+  for line in content.lines().filter(|l| !l.trim().is_empty()) {
+    let indent = leading_whitespace(line);
+    match (indent.contains('\t'), indent.contains(' ')) {
+      (true, true) => mixed_lines += 1,
+      (true, false) => tab_lines += 1,
+      (false, true) => {
+        space_lines += 1;
+        space_indents.push(indent.len());
+      }
+      (false, false) => space_indents.push(0),
+    }
+  }
+
+  // The most common step between consecutive indentation levels
+  let mut steps: HashMap<usize, usize> = HashMap::new();
+  for pair in space_indents.windows(2) {
+    let step = pair[0].abs_diff(pair[1]);
+    if step > 0 {
+      *steps.entry(step).or_default() += 1;
+    }
+  }
+  let width = steps
+    .into_iter()
+    .max_by_key(|&(step, count)| (count, std::cmp::Reverse(step)))
+    .map(|(step, _)| step);
+
+  let kind = match (tab_lines > 0, space_lines > 0) {
+    (false, false) if mixed_lines == 0 => IndentKind::None,
+    (true, false) => IndentKind::Tabs,
+    (false, true) => IndentKind::Spaces,
+    _ => IndentKind::Mixed,
+  };
+
+  let off_grid = match (kind, width) {
+    (IndentKind::Spaces, Some(width)) => space_indents.iter().filter(|&&n| n % width != 0).count(),
+    _ => 0,
+  };
+  let minority = match kind {
+    IndentKind::Mixed => tab_lines.min(space_lines),
+    _ => 0,
+  };
+
+  Indentation {
+    kind,
+    width: if kind == IndentKind::Spaces { width } else { None },
+    inconsistent_lines: mixed_lines + minority + off_grid,
+  }
+}
+
+/// Whether the line starting at byte `at` lies inside one of the `verbatim`
+/// ranges; a line whose string only opens after its indentation is not
+fn in_verbatim(at: usize, verbatim: &[Range<usize>]) -> bool {
+  verbatim.iter().any(|range| range.contains(&at))
+}
+
+/// Reduce space indentation to one space per level. Files whose indentation
+/// is inconsistent are returned unchanged, since re-indenting them could
+/// change their structure in indentation-sensitive languages. Lines inside
+/// the `verbatim` byte ranges, such as Python triple-quoted strings and YAML
+/// block scalars, are kept as written and left out of the detection, since
+/// their indentation is part of their value.
+pub fn compress(content: &str, verbatim: &[Range<usize>]) -> String {
+  let mut lines = Vec::new();
+  let mut at = 0;
+  for line in content.split_inclusive('\n') {
+    lines.push((line, in_verbatim(at, verbatim)));
+    at += line.len();
+  }
+
+  let outside: String = lines.iter().filter(|(_, kept)| !kept).map(|(line, _)| *line).collect();
+  let indentation = detect(&outside);
+  let width = match (indentation.kind, indentation.width) {
+    (IndentKind::Spaces, Some(width)) if width > 1 && indentation.is_consistent() => width,
+    _ => return content.to_string(),
+  };
+
+  lines
+    .into_iter()
+    .map(|(line, kept)| {
+      let indent = leading_whitespace(line).len();
+      if kept || line.trim().is_empty() {
+        line.to_string()
+      } else {
+        format!("{}{}", " ".repeat(indent / width), &line[indent..])
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{lexer, yaml};
+
+  #[test]
+  fn python_triple_quoted_strings_keep_their_indentation() {
+    let code = "def f():\n    if x:\n        s = \"\"\"\n    a\n        b\n\"\"\"\n        return s\n";
+    let spans = lexer::literal_spans(code, lexer::syntax_for("py").expect("python syntax"));
+    assert_eq!(
+      compress(code, &spans),
+      "def f():\n if x:\n  s = \"\"\"\n    a\n        b\n\"\"\"\n  return s\n"
+    );
+  }
+
+  #[test]
+  fn yaml_block_scalars_keep_their_indentation() {
+    let code = "job:\n  script: |\n    make\n      --verbose\n  name: build\n";
+    assert_eq!(
+      compress(code, &yaml::block_scalar_ranges(code)),
+      "job:\n script: |\n    make\n      --verbose\n name: build\n"
+    );
+  }
+}
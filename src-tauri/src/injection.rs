@@ -0,0 +1,100 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+const SNIPPET_CHARS: usize = 120;
+
+/// What kind of steering attempt a finding looks like
+#[derive(Clone, Copy, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+  /// "Ignore previous instructions" and similar overrides
+  InstructionOverride,
+  /// Attempts to reassign the model's role or reveal its prompt
+  RoleHijack,
+  /// Chat-template control tokens embedded in content
+  ControlTokens,
+  /// Zero-width, tag or bidi control characters that hide text from readers
+  InvisibleText,
+  /// HTML elements styled to be invisible
+  HiddenHtml,
+}
+
+#[derive(serde::Serialize)]
+pub struct Finding {
+  pub kind: FindingKind,
+  pub line: usize,
+  pub snippet: String,
+}
+
+static INSTRUCTION_OVERRIDE: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r"(?i)\b(?:ignore|disregard|forget|override)\s+(?:all\s+|any\s+|the\s+)?(?:previous|prior|above|earlier|preceding|your)\s+(?:instructions|prompts?|rules|directions|context)",
+  )
+  .expect("valid regex")
+});
+static ROLE_HIJACK: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r"(?i)\byou\s+are\s+now\s+(?:a|an|the|in)\b|\bnew\s+instructions\s*:|\b(?:reveal|print|repeat|output)\s+(?:your|the)\s+system\s+prompt|\bdo\s+not\s+(?:tell|inform|mention\s+this\s+to)\s+the\s+user",
+  )
+  .expect("valid regex")
+});
+static CONTROL_TOKENS: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"<\|(?:im_start|im_end|system|endoftext|start_header_id|end_header_id|eot_id)\|>|\[/?INST\]|<</?SYS>>")
+    .expect("valid regex")
+});
+static HIDDEN_HTML: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r#"(?i)<[a-z][^>]*\bstyle\s*=\s*["'][^"']*(?:display\s*:\s*none|visibility\s*:\s*hidden|font-size\s*:\s*0(?:px|pt|em)?\s*(?:;|["'])|opacity\s*:\s*0(?:\.0+)?\s*(?:;|["']))"#,
+  )
+  .expect("valid regex")
+});
+
+fn is_invisible(ch: char) -> bool {
+  matches!(
+    ch,
+    '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{2069}' | '\u{FEFF}' | '\u{E0000}'..='\u{E007F}'
+  )
+}
+
+fn snippet(line: &str) -> String {
+  let visible: String = line
+    .trim()
+    .chars()
+    .map(|c| if is_invisible(c) { '\u{FFFD}' } else { c })
+    .take(SNIPPET_CHARS)
+    .collect();
+  visible
+}
+
+/// Scan text for instruction-like content that may try to steer a model
+pub fn scan(content: &str) -> Vec<Finding> {
+  let mut findings = Vec::new();
+
+  for (index, line) in content.lines().enumerate() {
+    let checks = [
+      (FindingKind::InstructionOverride, INSTRUCTION_OVERRIDE.is_match(line)),
+      (FindingKind::RoleHijack, ROLE_HIJACK.is_match(line)),
+      (FindingKind::ControlTokens, CONTROL_TOKENS.is_match(line)),
+      (FindingKind::HiddenHtml, HIDDEN_HTML.is_match(line)),
+      // A BOM at the very start of a file is harmless
+      (
+        FindingKind::InvisibleText,
+        line
+          .char_indices()
+          .any(|(pos, c)| is_invisible(c) && !(index == 0 && pos == 0 && c == '\u{FEFF}')),
+      ),
+    ];
+
+    for (kind, hit) in checks {
+      if hit {
+        findings.push(Finding {
+          kind,
+          line: index + 1,
+          snippet: snippet(line),
+        });
+      }
+    }
+  }
+
+  findings
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::lexer;
+
+/// Target chunk size for streamed processing; chunks stay well under the
+/// size limit of the individual processing passes
+pub const CHUNK_SIZE: usize = 128 * 1024;
+
+/// Emitted as `processing-job-chunk` for each processed piece of a job's text.
+/// Concatenating `content` in `index` order yields the full result.
+#[derive(Clone, serde::Serialize)]
+pub struct JobChunk {
+  pub job_id: u64,
+  pub index: usize,
+  pub content: String,
+  pub processed_bytes: usize,
+  pub total_bytes: usize,
+}
+
+/// Emitted as `processing-job-finished` once a job stops
+#[derive(Clone, serde::Serialize)]
+pub struct JobFinished {
+  pub job_id: u64,
+  pub chunk_count: usize,
+  pub cancelled: bool,
+}
+
+/// Emitted as `token-count-progress` after each piece of a token count job
+#[derive(Clone, serde::Serialize)]
+pub struct TokenCountProgress {
+  pub job_id: u64,
+  pub processed_bytes: usize,
+  pub total_bytes: usize,
+  /// Tokens in the text up to `processed_bytes`
+  pub tokens: usize,
+}
+
+/// Running token count jobs, kept apart from the processing jobs so their
+/// ids cannot cancel each other
+#[derive(Default)]
+pub struct TokenCountJobs(pub ProcessingJobs);
+
+/// Running streamed-processing jobs and their cancellation flags
+#[derive(Default)]
+pub struct ProcessingJobs {
+  next_id: AtomicU64,
+  running: Mutex<HashMap<u64, Arc<AtomicBool>>>,
+}
+
+impl ProcessingJobs {
+  /// Register a job; the flag is set when it should stop
+  pub fn start(&self) -> (u64, Arc<AtomicBool>) {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    self
+      .running
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .insert(id, Arc::clone(&cancelled));
+    (id, cancelled)
+  }
+
+  pub fn cancel(&self, id: u64) -> Result<(), String> {
+    let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+    let flag = running.get(&id).ok_or_else(|| format!("no running processing job with id {id}"))?;
+    flag.store(true, Ordering::Relaxed);
+    Ok(())
+  }
+
+  pub fn finish(&self, id: u64) {
+    self.running.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+  }
+}
+
+/// Cancellation flag of the current `process_files_with_progress` run
+#[derive(Default)]
+pub struct ProcessingCancel {
+  current: Mutex<Arc<AtomicBool>>,
+}
+
+impl ProcessingCancel {
+  /// A fresh flag for a new run, so a late cancel of a previous run cannot
+  /// stop this one
+  pub fn begin(&self) -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    *self.current.lock().unwrap_or_else(|e| e.into_inner()) = Arc::clone(&flag);
+    flag
+  }
+
+  pub fn cancel(&self) {
+    self.current.lock().unwrap_or_else(|e| e.into_inner()).store(true, Ordering::Relaxed);
+  }
+}
+
+/// How often paused workers look at the cancellation flag
+const PAUSE_POLL: Duration = Duration::from_millis(200);
+
+/// Pause switch of `process_files_with_progress`; workers finish the file
+/// they are on and wait before starting the next
+#[derive(Default)]
+pub struct ProcessingPause {
+  paused: Mutex<bool>,
+  resumed: Condvar,
+}
+
+impl ProcessingPause {
+  pub fn set(&self, paused: bool) {
+    *self.paused.lock().unwrap_or_else(|e| e.into_inner()) = paused;
+    if !paused {
+      self.resumed.notify_all();
+    }
+  }
+
+  /// Block while paused, unless `cancelled` is set meanwhile
+  pub fn wait(&self, cancelled: &AtomicBool) {
+    let mut paused = self.paused.lock().unwrap_or_else(|e| e.into_inner());
+    while *paused && !cancelled.load(Ordering::Relaxed) {
+      paused = self
+        .resumed
+        .wait_timeout(paused, PAUSE_POLL)
+        .unwrap_or_else(|e| e.into_inner())
+        .0;
+    }
+  }
+}
+
+/// Split `code` into pieces of roughly `target` bytes that can be processed
+/// independently. Cuts are made only after a blank line that is followed by
+/// an unindented line, and never inside one of the comments and string
+/// literals at `spans`. Text with no such point stays in one piece.
+fn split_chunks<'a>(code: &'a str, spans: &[Range<usize>], target: usize) -> Vec<&'a str> {
+  let mut chunks = Vec::new();
+  let mut start = 0;
+  let mut search_from = 0;
+
+  while code.len() - start > target {
+    let mut window_start = (start + target).max(search_from);
+    while !code.is_char_boundary(window_start) {
+      window_start += 1;
+    }
+    let rest = &code[window_start..];
+    let blank_line = [rest.find("\n\n").map(|i| i + 2), rest.find("\n\r\n").map(|i| i + 3)]
+      .into_iter()
+      .flatten()
+      .min();
+    let Some(found) = blank_line else {
+      break;
+    };
+    let cut = window_start + found;
+    search_from = cut;
+
+    let next_line_top_level = code[cut..].chars().next().is_some_and(|c| !c.is_whitespace());
+    if next_line_top_level && !splits_span(spans, cut) {
+      chunks.push(&code[start..cut]);
+      start = cut;
+    }
+  }
+  chunks.push(&code[start..]);
+  chunks
+}
+
+/// Byte ranges of the comments and string literals of `code`, which no cut
+/// may fall inside; empty for languages the lexer does not know
+fn protected_spans(code: &str, extension: &str) -> Vec<Range<usize>> {
+  lexer::syntax_for(extension).map_or_else(Vec::new, |syntax| lexer::token_spans(code, syntax))
+}
+
+/// Whether cutting at byte `at` would split one of the ordered `spans`
+fn splits_span(spans: &[Range<usize>], at: usize) -> bool {
+  let next = spans.partition_point(|span| span.end <= at);
+  spans.get(next).is_some_and(|span| span.start < at)
+}
+
+/// Cut `code[range]` at line ends outside `spans` once a piece grows past
+/// `target` bytes
+fn split_lines(code: &str, range: Range<usize>, spans: &[Range<usize>], target: usize) -> Vec<Range<usize>> {
+  let mut pieces = Vec::new();
+  let mut start = range.start;
+  let mut end = range.start;
+  for line in code[range.clone()].split_inclusive('\n') {
+    end += line.len();
+    if end - start >= target && end < range.end && !splits_span(spans, end) {
+      pieces.push(start..end);
+      start = end;
+    }
+  }
+  if start < range.end {
+    pieces.push(start..range.end);
+  }
+  pieces
+}
+
+/// Cut `code[range]`, a piece with too few line ends such as a minified
+/// bundle on one line, into pieces of about `target` bytes, anywhere outside
+/// `spans`; after whitespace or punctuation that ends a statement or item
+/// where one comes soon enough
+fn split_within_lines(code: &str, range: Range<usize>, spans: &[Range<usize>], target: usize) -> Vec<Range<usize>> {
+  let bytes = code.as_bytes();
+  let allowed = |at: usize| code.is_char_boundary(at) && !splits_span(spans, at);
+  let mut pieces = Vec::new();
+  let mut start = range.start;
+  while range.end - start > target {
+    let from = start + target;
+    let preferred = (from..range.end.min(from + target))
+      .find(|&at| matches!(bytes[at - 1], b' ' | b'\t' | b';' | b',' | b'{' | b'}') && allowed(at));
+    let Some(cut) = preferred.or_else(|| (from..range.end).find(|&at| allowed(at))) else {
+      break;
+    };
+    pieces.push(start..cut);
+    start = cut;
+  }
+  pieces.push(start..range.end);
+  pieces
+}
+
+/// `split_chunks`, with any chunk still over `limit` bytes (long generated
+/// files and dumps rarely have blank-line break points) cut further at line
+/// ends, and within lines that are too long on their own. Cuts never fall
+/// inside a comment or string literal of `extension`'s language.
+pub fn split_bounded<'a>(code: &'a str, extension: &str, target: usize, limit: usize) -> Vec<&'a str> {
+  let spans = protected_spans(code, extension);
+  let chunks = split_chunks(code, &spans, target);
+  if chunks.iter().all(|chunk| chunk.len() <= limit) {
+    return chunks;
+  }
+
+  let mut pieces = Vec::new();
+  let mut offset = 0;
+  for chunk in chunks {
+    let range = offset..offset + chunk.len();
+    offset = range.end;
+    if chunk.len() <= limit {
+      pieces.push(range);
+      continue;
+    }
+    for piece in split_lines(code, range, &spans, target) {
+      if piece.len() > limit {
+        pieces.extend(split_within_lines(code, piece, &spans, target));
+      } else {
+        pieces.push(piece);
+      }
+    }
+  }
+  pieces.into_iter().map(|range| &code[range]).collect()
+}
+
+/// Concatenates processed chunks, restoring the line break or space that
+/// passes like minify trim from chunk ends so neighbouring chunks are not
+/// glued together
+#[derive(Default)]
+pub struct ChunkJoiner {
+  /// Separator the previous chunk ended with before processing, if its
+  /// processed text lost it
+  separator: Option<char>,
+}
+
+impl ChunkJoiner {
+  /// `content`, processed from `chunk`, as it should be appended after the
+  /// previous chunks
+  pub fn next(&mut self, chunk: &str, mut content: String) -> String {
+    if let Some(separator) = self.separator {
+      if !content.is_empty() && !content.starts_with(char::is_whitespace) {
+        content.insert(0, separator);
+      }
+    }
+    if !content.is_empty() {
+      let ended = match chunk.chars().next_back() {
+        Some('\n') => Some('\n'),
+        Some(c) if c.is_whitespace() => Some(' '),
+        _ => None,
+      };
+      self.separator = ended.filter(|_| !content.ends_with(char::is_whitespace));
+    }
+    content
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn template_literals_are_not_cut() {
+    let template = format!("`\n{}`", "<p>line</p>\n".repeat(20));
+    let code = format!("{}const page = {template};\n{}", "let a = 1;\n".repeat(10), "let b = 2;\n".repeat(10));
+    let pieces = split_bounded(&code, "js", 40, 60);
+    assert!(pieces.len() > 1);
+    assert_eq!(pieces.concat(), code);
+    assert!(pieces.iter().any(|piece| piece.contains(&template)));
+  }
+
+  #[test]
+  fn blank_lines_inside_comments_are_not_cut_at() {
+    let comment = "/*\n\nfn ignored() {}\n\nend */";
+    let code = format!("fn a() {{}}\n\n{comment}\n\nfn b() {{}}\n\nfn c() {{}}\n");
+    let pieces = split_bounded(&code, "rs", 8, 1000);
+    assert!(pieces.len() > 1);
+    assert_eq!(pieces.concat(), code);
+    assert!(pieces.iter().any(|piece| piece.contains(comment)));
+  }
+
+  #[test]
+  fn comment_markers_in_strings_do_not_stop_cuts() {
+    let code = "let a = \"/*\";\n\nlet b = 1;\n\nlet c = 2;\n";
+    assert_eq!(split_bounded(code, "rs", 4, 1000), ["let a = \"/*\";\n\n", "let b = 1;\n\n", "let c = 2;\n"]);
+  }
+
+  #[test]
+  fn long_lines_are_cut_outside_strings() {
+    let code = format!("var s=\"{}\";{}", "x;".repeat(100), "f(1);".repeat(100));
+    let pieces = split_bounded(&code, "js", 50, 100);
+    assert!(pieces.len() > 1);
+    assert_eq!(pieces.concat(), code);
+    let string_end = code.find("\";").unwrap() + 1;
+    assert!(pieces[0].len() >= string_end);
+    assert!(pieces[1..].iter().all(|piece| piece.len() <= 100));
+  }
+
+  #[test]
+  fn joiner_restores_trimmed_separators() {
+    let mut joiner = ChunkJoiner::default();
+    assert_eq!(joiner.next("a;\n", "a;".to_string()), "a;");
+    assert_eq!(joiner.next("b; ", "b;".to_string()), "\nb;");
+    assert_eq!(joiner.next("c;", "c;".to_string()), " c;");
+    assert_eq!(joiner.next("d", "d".to_string()), "d");
+  }
+}
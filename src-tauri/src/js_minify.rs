@@ -0,0 +1,28 @@
+use std::panic::AssertUnwindSafe;
+
+use minify_js::{Session, TopLevelMode};
+
+/// The JavaScript and TypeScript family; TypeScript only minifies when it
+/// has no type syntax the JavaScript parser would stop at
+pub fn is_js_family(extension: &str) -> bool {
+  matches!(extension, "js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx")
+}
+
+/// `code` minified from its syntax tree, or `None` when it does not parse.
+/// It is read as a script first, which keeps top-level names readable, and
+/// as a module, whose unexported top-level names are shortened too, when
+/// `import` or `export` rule that out.
+pub fn minify(code: &str) -> Option<String> {
+  [TopLevelMode::Global, TopLevelMode::Module].into_iter().find_map(|mode| {
+    let mut output = Vec::new();
+    // The minifier panics on some inputs it cannot handle instead of failing
+    let minified = std::panic::catch_unwind(AssertUnwindSafe(|| {
+      let session = Session::new();
+      minify_js::minify(&session, mode, code.as_bytes(), &mut output).is_ok()
+    }));
+    match minified {
+      Ok(true) => String::from_utf8(output).ok(),
+      _ => None,
+    }
+  })
+}
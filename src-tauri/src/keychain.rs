@@ -0,0 +1,34 @@
+//! API keys and access tokens, kept in the OS keychain (macOS Keychain,
+//! Windows Credential Manager, the Secret Service on Linux) rather than in
+//! the settings file.
+
+/// Service the entries are filed under
+const SERVICE: &str = "com.ireddragonicy.contextractor";
+
+fn entry(name: &str) -> Result<keyring::Entry, String> {
+  keyring::Entry::new(SERVICE, name).map_err(|e| format!("failed to open the {name} keychain entry: {e}"))
+}
+
+/// The secret stored as `name`, if any
+pub fn get(name: &str) -> Option<String> {
+  match entry(name).and_then(|entry| entry.get_password().map_err(|e| e.to_string())) {
+    Ok(secret) => Some(secret),
+    Err(e) => {
+      log::debug!("No {} secret in the keychain: {}", name, e);
+      None
+    }
+  }
+}
+
+/// Store `secret` as `name`, or delete the entry with `None`
+pub fn set(name: &str, secret: Option<&str>) -> Result<(), String> {
+  let entry = entry(name)?;
+  let result = match secret {
+    Some(secret) => entry.set_password(secret),
+    None => match entry.delete_credential() {
+      Err(keyring::Error::NoEntry) => Ok(()),
+      result => result,
+    },
+  };
+  result.map_err(|e| format!("failed to store the {name} secret in the system keychain: {e}"))
+}
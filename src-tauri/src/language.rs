@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Files recognised by name, with the language key (the extension comment
+/// handling knows them by) they are written in
+const FILENAMES: &[(&str, &str)] = &[
+  ("makefile", "makefile"),
+  ("gnumakefile", "makefile"),
+  ("dockerfile", "dockerfile"),
+  ("containerfile", "dockerfile"),
+  ("cmakelists.txt", "cmake"),
+  ("jenkinsfile", "groovy"),
+  // Starlark is Python syntax
+  ("build", "py"),
+  ("build.bazel", "py"),
+  ("workspace", "py"),
+  ("workspace.bazel", "py"),
+  ("module.bazel", "py"),
+  ("tiltfile", "py"),
+  ("sconstruct", "py"),
+  ("sconscript", "py"),
+  ("rakefile", "rb"),
+  ("gemfile", "rb"),
+  ("podfile", "rb"),
+  ("vagrantfile", "rb"),
+  ("brewfile", "rb"),
+  ("fastfile", "rb"),
+  ("pkgbuild", "sh"),
+  (".bashrc", "bash"),
+  (".bash_profile", "bash"),
+  (".profile", "sh"),
+  (".zshrc", "zsh"),
+  (".zprofile", "zsh"),
+];
+
+/// Interpreters named on a shebang line, with their language key
+const INTERPRETERS: &[(&str, &str)] = &[
+  ("sh", "sh"),
+  ("dash", "sh"),
+  ("ash", "sh"),
+  ("ksh", "sh"),
+  ("bash", "bash"),
+  ("zsh", "zsh"),
+  ("fish", "fish"),
+  ("python", "py"),
+  ("pypy", "py"),
+  ("node", "js"),
+  ("deno", "ts"),
+  ("bun", "js"),
+  ("ts-node", "ts"),
+  ("ruby", "rb"),
+  ("perl", "pl"),
+  ("php", "php"),
+  ("lua", "lua"),
+  ("rscript", "r"),
+  ("pwsh", "ps1"),
+  ("make", "makefile"),
+];
+
+/// Language of a file recognised by its name alone
+pub fn from_filename(name: &str) -> Option<&'static str> {
+  let name = name.to_lowercase();
+  FILENAMES.iter().find(|(file, _)| *file == name).map(|(_, language)| *language)
+}
+
+/// Language named by a `#!` line: `#!/bin/bash`, `#!/usr/bin/env python3`
+fn from_shebang(content: &str) -> Option<&'static str> {
+  let line = content.strip_prefix("#!")?.lines().next()?;
+  let mut words = line.split_whitespace();
+  let mut program = words.next()?.rsplit('/').next()?;
+  if program == "env" {
+    // `env -S interpreter args` splits the rest itself
+    program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+  }
+  let program = program.to_lowercase();
+  // Versioned interpreters: `python3.12`, `ruby2.7`
+  let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+  INTERPRETERS
+    .iter()
+    .find(|(interpreter, _)| *interpreter == program)
+    .map(|(_, language)| *language)
+}
+
+/// Language guessed from the opening of `content`
+fn from_content(content: &str) -> Option<&'static str> {
+  let start = content.trim_start_matches('\u{feff}').trim_start();
+  let lower = start.chars().take(16).collect::<String>().to_lowercase();
+  if lower.starts_with("<?php") {
+    Some("php")
+  } else if lower.starts_with("<?xml") {
+    Some("xml")
+  } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+    Some("html")
+  } else if start.starts_with('{') && serde_json::from_str::<serde_json::Value>(content).is_ok() {
+    Some("json")
+  } else {
+    None
+  }
+}
+
+/// Lines typical of a language, by language key. Each line matching one
+/// counts towards that language.
+static SIGNATURES: Lazy<Vec<(&'static str, Regex)>> = Lazy::new(|| {
+  [
+    ("rs", r"^\s*(pub(\([a-z]+\))? )?(fn|struct|enum|impl|trait|mod) \w|^\s*use \w+::|\blet mut \w|^\s*#\[derive\("),
+    ("py", r"^\s*def \w+\(.*\):|^\s*(from [\w.]+ )?import \w[\w.]*( as \w+)?$|^\s*class \w+(\(.*\))?:$|^\s*(elif|except\b.*:|print\()"),
+    ("ts", r"^\s*(export )?(interface|type|enum) \w+\b.*[{=]|\b(const|let) \w+: [A-Za-z]|\): (string|number|boolean|void|Promise<)"),
+    ("js", r"^\s*(export )?(const|let|var) \w+ = |^\s*(export )?(async )?function\*? \w+\(|=> \{|\brequire\(['\x22]|console\.log\("),
+    ("go", r"^package \w+$|^\s*func (\(\w+ \*?\w+\) )?\w+\(|:= |^import \($"),
+    ("java", r"^\s*(public|private|protected) (static )?(final )?(class|interface|void|[A-Z]\w*(<.*>)?) \w+|^import java\.|System\.out\.print"),
+    ("cs", r"^using System(\.\w+)*;|^\s*namespace [\w.]+|^\s*(public|private|internal) (static )?(async )?(class|void|Task|string|int) \w+|Console\.Write"),
+    ("cpp", r"^#include <(iostream|vector|string|memory|map)>|\bstd::\w+|^\s*template ?<|^\s*namespace \w+ \{"),
+    ("c", r"^#include [<\x22][\w/]+\.h[>\x22]|^\s*(static )?(int|void|char|unsigned|size_t) \*?\w+\(|\bprintf\(|\bmalloc\("),
+    ("rb", r"^\s*def \w+[?!]?(\(.*\))?$|^\s*end$|^\s*require ['\x22]|\bputs\b|^\s*module [A-Z]"),
+    ("php", r"\$\w+ = |^\s*(public |private )?function \w+\(|->\w+\(|^\s*echo "),
+    ("sh", r"^\s*(if \[|fi$|then$|done$|esac$|export \w+=|echo )|\$\{?\w+\}?|^\s*\w+\(\) \{"),
+    ("sql", r"(?i)^\s*(select\b.*\bfrom\b|select$|insert into|update \w+ set|delete from|create (table|index|view)|alter table|from \w|where \w|join \w)"),
+    ("css", r"^\s*[.#]?[\w-]+( [.#]?[\w-]+)*\s*\{$|^\s*[a-z-]+: [^;\n]+;$|^\s*@media "),
+    ("yaml", r"^\s*[\w-]+:( [^{}\n]*)?$|^\s*- [\w-]+:"),
+    ("md", r"^#{1,6} \S|^\s*[-*] \[[ x]\] |^\[.+\]\(.+\)|^```"),
+  ]
+  .into_iter()
+  .map(|(language, pattern)| (language, Regex::new(&format!("(?m){pattern}")).expect("invalid signature")))
+  .collect()
+});
+
+/// Lines of a snippet `guess` looks at
+const GUESS_LINES: usize = 200;
+
+/// Language of text with no name to go by, such as a pasted snippet: a
+/// shebang, how it opens, or else the language whose typical lines it has
+/// most of. `None` for prose and anything too short to tell.
+pub fn guess(content: &str) -> Option<&'static str> {
+  if let Some(language) = from_shebang(content).or_else(|| from_content(content)) {
+    return Some(language);
+  }
+  let end = content.match_indices('\n').nth(GUESS_LINES).map_or(content.len(), |(i, _)| i);
+  let sample = &content[..end];
+  SIGNATURES
+    .iter()
+    .map(|(language, pattern)| (*language, pattern.find_iter(sample).count()))
+    .filter(|&(_, score)| score >= 2)
+    // The first listed wins a tie, so TypeScript beats JavaScript
+    .fold(None, |best: Option<(&str, usize)>, (language, score)| match best {
+      Some((_, top)) if top >= score => best,
+      _ => Some((language, score)),
+    })
+    .map(|(language, _)| language)
+}
+
+/// Language of a file: by its name (`Makefile`), its extension, a shebang,
+/// or how its content opens. `None` when nothing gives it away.
+pub fn detect(name: &str, content: &str) -> Option<String> {
+  if let Some(language) = from_filename(name) {
+    return Some(language.to_string());
+  }
+  if let Some(ext) = Path::new(name).extension().and_then(|ext| ext.to_str()) {
+    return Some(ext.to_lowercase());
+  }
+  from_shebang(content).or_else(|| from_content(content)).map(str::to_string)
+}
+
+/// Comment delimiters for a language the built-in tables miss or get wrong
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CommentDelimiters {
+  /// Line comment markers: `//`, `#`
+  pub line: Vec<String>,
+  /// Block comment open and close pairs: `["/*", "*/"]`
+  pub block: Vec<(String, String)>,
+}
+
+/// User additions to the built-in language tables, persisted with the settings
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct LanguageConfig {
+  /// Comment syntax by extension, replacing the built-in handling of it
+  pub comments: BTreeMap<String, CommentDelimiters>,
+  /// Extensions handled as another one: `{"zig": "rs", "tf": "conf"}`
+  pub aliases: BTreeMap<String, String>,
+  /// Extensions read as text besides the built-in list
+  pub text_extensions: Vec<String>,
+  /// Extensions never read as text, even when the built-in list has them
+  pub binary_extensions: Vec<String>,
+}
+
+/// Comment patterns compiled from `CommentDelimiters`
+#[derive(Clone)]
+pub struct CustomComments {
+  pub line: Option<Regex>,
+  pub block: Option<Regex>,
+}
+
+/// `LanguageConfig` in the form lookups use
+#[derive(Default)]
+struct Active {
+  comments: HashMap<String, CustomComments>,
+  aliases: HashMap<String, String>,
+  text: HashSet<String>,
+  binary: HashSet<String>,
+}
+
+// Read for every file, so swapped whole rather than locked while in use
+static ACTIVE: Lazy<RwLock<Arc<Active>>> = Lazy::new(Default::default);
+
+fn active() -> Arc<Active> {
+  Arc::clone(&ACTIVE.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+fn normalize(ext: &str) -> String {
+  ext.trim().trim_start_matches('.').to_lowercase()
+}
+
+fn compile(delimiters: &CommentDelimiters) -> CustomComments {
+  let line: Vec<String> = delimiters.line.iter().filter(|m| !m.is_empty()).map(|m| regex::escape(m)).collect();
+  let block: Vec<String> = delimiters
+    .block
+    .iter()
+    .filter(|(open, close)| !open.is_empty() && !close.is_empty())
+    .map(|(open, close)| format!(r"{}[\s\S]*?{}", regex::escape(open), regex::escape(close)))
+    .collect();
+  let build = |alternatives: Vec<String>, suffix: &str| {
+    (!alternatives.is_empty())
+      .then(|| Regex::new(&format!("(?:{}){suffix}", alternatives.join("|"))).expect("escaped delimiters"))
+  };
+  CustomComments {
+    line: build(line, r"[^\n]*"),
+    block: build(block, ""),
+  }
+}
+
+/// Make `config` the one every later scan and processing pass uses
+pub fn apply(config: &LanguageConfig) {
+  let active = Active {
+    comments: config.comments.iter().map(|(ext, delimiters)| (normalize(ext), compile(delimiters))).collect(),
+    aliases: config.aliases.iter().map(|(ext, target)| (normalize(ext), normalize(target))).collect(),
+    text: config.text_extensions.iter().map(|ext| normalize(ext)).collect(),
+    binary: config.binary_extensions.iter().map(|ext| normalize(ext)).collect(),
+  };
+  *ACTIVE.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(active);
+}
+
+/// The extension `ext` is handled as: its alias target, or itself
+pub fn resolve(ext: &str) -> String {
+  let ext = normalize(ext);
+  active().aliases.get(&ext).cloned().unwrap_or(ext)
+}
+
+/// User comment patterns for `ext`, after aliasing
+pub fn custom_comments(ext: &str) -> Option<CustomComments> {
+  active().comments.get(&normalize(ext)).cloned()
+}
+
+/// Whether the user config decides that `ext` is text (`Some(true)`) or
+/// binary (`Some(false)`); `None` leaves it to the built-in list
+pub fn text_override(ext: &str) -> Option<bool> {
+  let ext = normalize(ext);
+  let active = active();
+  if active.binary.contains(&ext) {
+    Some(false)
+  } else if active.text.contains(&ext) || active.aliases.contains_key(&ext) || active.comments.contains_key(&ext) {
+    Some(true)
+  } else {
+    None
+  }
+}
@@ -0,0 +1,111 @@
+//! The built-in comment syntax table, compiled in from `languages.toml`
+
+use once_cell::sync::Lazy;
+
+use crate::{lexer, syntax_tree};
+
+/// Block comments that nest, as written in the table
+#[derive(serde::Deserialize)]
+pub struct NestedEntry {
+  pub open: String,
+  pub close: String,
+  /// Line comment marker; a block opener after it is not a comment start
+  pub line: Option<String>,
+  /// Blocks opening with this are kept
+  pub keep: Option<String>,
+}
+
+/// How a language's string literals are delimited
+#[derive(Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StringsEntry {
+  #[default]
+  CLike,
+  Basic,
+  HeredocRuby,
+}
+
+/// One `[[language]]` of the table
+#[derive(serde::Deserialize)]
+pub struct LanguageEntry {
+  pub name: String,
+  /// Extensions, or `ext:variant` keys picked between by content
+  pub extensions: Vec<String>,
+  pub line: Option<String>,
+  pub block: Option<String>,
+  pub docstring: Option<String>,
+  pub nested: Option<NestedEntry>,
+  #[serde(default)]
+  pub preserve_strings: bool,
+  #[serde(default)]
+  pub strings: StringsEntry,
+}
+
+#[derive(serde::Deserialize)]
+struct Table {
+  language: Vec<LanguageEntry>,
+}
+
+pub static LANGUAGES: Lazy<Vec<LanguageEntry>> = Lazy::new(|| {
+  toml::from_str::<Table>(include_str!("languages.toml"))
+    .expect("valid language table")
+    .language
+});
+
+/// Table name of the language `ext` belongs to
+pub fn name_of(ext: &str) -> Option<&'static str> {
+  LANGUAGES
+    .iter()
+    .find(|language| language.extensions.iter().any(|key| key.split(':').next() == Some(ext)))
+    .map(|language| language.name.as_str())
+}
+
+/// How comments are found in a language
+#[derive(serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommentHandling {
+  /// A tree-sitter parse tree
+  SyntaxTree,
+  /// The string-aware comment lexer
+  Lexer,
+  /// Regexes, with string literals protected where the table says so
+  Patterns,
+}
+
+#[derive(serde::Serialize)]
+pub struct SupportedLanguage {
+  pub name: String,
+  pub extensions: Vec<String>,
+  /// For the first extension; parse trees fall back to the lexer or
+  /// patterns on code that does not parse
+  pub handling: CommentHandling,
+}
+
+/// The languages comment removal knows, in table order
+pub fn supported() -> Vec<SupportedLanguage> {
+  LANGUAGES
+    .iter()
+    .map(|language| {
+      let mut extensions: Vec<String> = Vec::new();
+      for key in &language.extensions {
+        let ext = key.split(':').next().unwrap_or(key).to_string();
+        if !extensions.contains(&ext) {
+          extensions.push(ext);
+        }
+      }
+      let first = extensions.first().map_or("", String::as_str);
+      let handling = if syntax_tree::supports(first) {
+        CommentHandling::SyntaxTree
+      } else if lexer::syntax_for(first).is_some() {
+        CommentHandling::Lexer
+      } else {
+        CommentHandling::Patterns
+      };
+      SupportedLanguage {
+        name: language.name.clone(),
+        extensions,
+        handling,
+      }
+    })
+    .collect()
+}
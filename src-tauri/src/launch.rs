@@ -0,0 +1,61 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Files and folders named on the command line, ready for loading: flags
+/// are dropped (`-psn_0_1234` from older macOS Finder launches too), and
+/// relative paths are taken from `cwd`, the launching shell's directory
+pub fn paths_from_args(args: impl IntoIterator<Item = String>, cwd: &Path) -> Vec<String> {
+  args
+    .into_iter()
+    .filter(|arg| !arg.starts_with('-'))
+    .map(|arg| cwd.join(arg))
+    .filter(|path| path.exists())
+    .map(|path| path.to_string_lossy().to_string())
+    .collect()
+}
+
+/// Paths the app was asked to open, by its command line, a second launch
+/// forwarded to this one, the OS opening an associated file, or a
+/// `textractor://` link
+#[derive(Clone, Default, serde::Serialize)]
+pub struct LaunchRequest {
+  pub paths: Vec<String>,
+  /// Processing mode the link asked for (`minify`, `outline`, ...)
+  pub mode: Option<String>,
+}
+
+/// Launch requests, kept until the frontend is ready for them
+#[derive(Default)]
+pub struct LaunchPaths {
+  state: Mutex<LaunchState>,
+}
+
+#[derive(Default)]
+struct LaunchState {
+  /// Whether the frontend listens for load events yet
+  ready: bool,
+  pending: LaunchRequest,
+}
+
+impl LaunchPaths {
+  /// The request to act on now; until the frontend is ready it is merged
+  /// into the one `take` returns instead, and `None` is returned
+  pub fn offer(&self, request: LaunchRequest) -> Option<LaunchRequest> {
+    let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+    if state.ready {
+      return Some(request);
+    }
+    state.pending.paths.extend(request.paths);
+    if request.mode.is_some() {
+      state.pending.mode = request.mode;
+    }
+    None
+  }
+
+  /// Mark the frontend ready and hand over what was asked for until then
+  pub fn take(&self) -> LaunchRequest {
+    let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+    state.ready = true;
+    std::mem::take(&mut state.pending)
+  }
+}
@@ -0,0 +1,1080 @@
+//! Single-pass comment removal. The text is scanned once, character by
+//! character, tracking whether the scanner is in code, a string or a comment,
+//! so comment markers inside strings (and quotes inside comments) are never
+//! misread. The same scan finds the string literals that minification keeps
+//! verbatim.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Comment and string syntax of one language
+pub struct Syntax {
+  /// Line comment markers
+  line: &'static [&'static str],
+  /// A line comment marker only counts at the start of a word, as in shell
+  /// (`$#`, `${#var}`) and YAML (`key: a#b`)
+  line_at_word_start: bool,
+  /// Block comment delimiters
+  block: &'static [(&'static str, &'static str)],
+  /// Block comments nest, as in Rust `/* /* */ */`
+  nested: bool,
+  /// String literal kinds, tried in order, so longer openers come first
+  strings: &'static [Literal],
+  /// Python literals: `r`/`b`/`f`/`u` prefixes and f-string replacement
+  /// fields, which may hold quotes of their own
+  python_literals: bool,
+  /// Statements made only of string literals (docstrings, including
+  /// implicitly concatenated ones) are removed like comments
+  docstrings: bool,
+  /// Rust literals: raw strings (`r"..."`, `r#"..."#` with any number of `#`)
+  /// and lifetimes, whose `'` does not open a character literal
+  rust_literals: bool,
+  /// Heredoc syntax, whose bodies are kept verbatim like strings
+  heredocs: Option<Heredoc>,
+  /// JavaScript regex literals (`/https?:\/\//g`), told apart from division
+  /// by the token before them
+  regex_literals: bool,
+}
+
+/// How a string literal escapes its closing delimiter
+#[derive(Clone, Copy)]
+enum Escape {
+  /// `\"`
+  Backslash,
+  /// `""`, as in C# verbatim strings
+  Doubled,
+  /// `` `" ``, as in PowerShell, where a backtick escapes any character
+  Backtick,
+  /// Raw strings have no escapes
+  None,
+}
+
+/// One kind of string literal
+pub struct Literal {
+  /// Opening delimiter, including any prefix such as C#'s `@`
+  open: &'static str,
+  close: &'static str,
+  escape: Escape,
+  /// The literal may span lines
+  multiline: bool,
+  /// The first character of `open` may be repeated to lengthen both
+  /// delimiters, as in Swift `##"..."##` and C# `""""...""""`
+  extendable: bool,
+  /// Opener of embedded expressions (`${`, `\(`, `{`), which are code and
+  /// may hold strings of their own
+  holes: Option<&'static str>,
+}
+
+const DOUBLE: Literal = Literal {
+  open: "\"",
+  close: "\"",
+  escape: Escape::Backslash,
+  multiline: false,
+  extendable: false,
+  holes: None,
+};
+
+const SINGLE: Literal = Literal {
+  open: "'",
+  close: "'",
+  ..DOUBLE
+};
+
+/// Java text blocks and other `"""` strings with escapes
+const TEXT_BLOCK: Literal = Literal {
+  open: "\"\"\"",
+  close: "\"\"\"",
+  multiline: true,
+  ..DOUBLE
+};
+
+const TEMPLATE: Literal = Literal {
+  open: "`",
+  close: "`",
+  multiline: true,
+  holes: Some("${"),
+  ..DOUBLE
+};
+
+/// Go raw strings
+const BACKTICK_RAW: Literal = Literal {
+  open: "`",
+  close: "`",
+  escape: Escape::None,
+  multiline: true,
+  ..DOUBLE
+};
+
+/// Kotlin and Scala `"""` strings, which are raw apart from `${}` templates
+const TRIPLE_RAW: Literal = Literal {
+  escape: Escape::None,
+  holes: Some("${"),
+  ..TEXT_BLOCK
+};
+
+const TEMPLATED: Literal = Literal {
+  holes: Some("${"),
+  ..DOUBLE
+};
+
+const C_STRINGS: &[Literal] = &[TEXT_BLOCK, DOUBLE, SINGLE];
+
+const CSHARP_STRINGS: &[Literal] = &[
+  // Interpolated verbatim strings, in either prefix order
+  Literal {
+    open: "$@\"",
+    escape: Escape::Doubled,
+    multiline: true,
+    holes: Some("{"),
+    ..DOUBLE
+  },
+  Literal {
+    open: "@$\"",
+    escape: Escape::Doubled,
+    multiline: true,
+    holes: Some("{"),
+    ..DOUBLE
+  },
+  Literal {
+    open: "@\"",
+    escape: Escape::Doubled,
+    multiline: true,
+    ..DOUBLE
+  },
+  // Interpolated and plain raw string literals
+  Literal {
+    open: "$\"\"\"",
+    escape: Escape::None,
+    holes: Some("{"),
+    ..TEXT_BLOCK
+  },
+  Literal {
+    escape: Escape::None,
+    extendable: true,
+    ..TEXT_BLOCK
+  },
+  Literal {
+    open: "$\"",
+    holes: Some("{"),
+    ..DOUBLE
+  },
+  DOUBLE,
+  SINGLE,
+];
+
+const KOTLIN_STRINGS: &[Literal] = &[TRIPLE_RAW, TEMPLATED, SINGLE];
+
+const SWIFT_STRINGS: &[Literal] = &[
+  // Raw strings with `#` delimiters
+  Literal {
+    open: "#\"\"\"",
+    close: "\"\"\"#",
+    escape: Escape::None,
+    multiline: true,
+    extendable: true,
+    holes: None,
+  },
+  Literal {
+    open: "#\"",
+    close: "\"#",
+    escape: Escape::None,
+    extendable: true,
+    ..DOUBLE
+  },
+  Literal {
+    holes: Some("\\("),
+    ..TEXT_BLOCK
+  },
+  Literal {
+    holes: Some("\\("),
+    ..DOUBLE
+  },
+];
+
+const QUOTES: &[Literal] = &[DOUBLE, SINGLE];
+
+/// SQL strings and quoted identifiers, which escape their quote by doubling
+/// it and take backslashes literally, and PostgreSQL `$$` bodies
+const SQL_STRINGS: &[Literal] = &[
+  Literal {
+    open: "$$",
+    close: "$$",
+    escape: Escape::None,
+    multiline: true,
+    ..DOUBLE
+  },
+  Literal {
+    escape: Escape::Doubled,
+    multiline: true,
+    ..SINGLE
+  },
+  Literal {
+    escape: Escape::Doubled,
+    multiline: true,
+    ..DOUBLE
+  },
+  Literal {
+    open: "`",
+    close: "`",
+    escape: Escape::Doubled,
+    multiline: true,
+    ..DOUBLE
+  },
+];
+
+/// PowerShell here-strings, whose `"@` or `'@` closes only at the start of a
+/// line, then expandable and verbatim strings
+const POWERSHELL_STRINGS: &[Literal] = &[
+  Literal {
+    open: "@\"",
+    close: "\n\"@",
+    escape: Escape::None,
+    multiline: true,
+    ..DOUBLE
+  },
+  Literal {
+    open: "@'",
+    close: "\n'@",
+    escape: Escape::None,
+    multiline: true,
+    ..DOUBLE
+  },
+  Literal {
+    escape: Escape::Backtick,
+    multiline: true,
+    ..DOUBLE
+  },
+  Literal {
+    escape: Escape::Doubled,
+    multiline: true,
+    ..SINGLE
+  },
+];
+
+/// Flavours of `<<WORD` heredocs
+#[derive(Clone, Copy)]
+pub(crate) enum Heredoc {
+  /// `<<EOF`, `<<-EOF`, `<< 'EOF'`; `<<<` is a here-string
+  Shell,
+  /// `<<EOF`, `<<~EOF`, `<< "EOF"`
+  Perl,
+  /// `<<<EOT`, `<<<"EOT"` and `<<<'EOT'` nowdocs; the closing identifier may
+  /// be indented and followed by code
+  Php,
+  /// `<<EOS`, `<<-EOS`, `<<~EOS`; bare identifiers must be capitalised so
+  /// `a <<b` stays an append
+  Ruby,
+}
+
+/// The line a heredoc body ends with
+pub(crate) struct Terminator {
+  word: String,
+  /// Whitespace may precede the word
+  indented: bool,
+  /// Code may follow the word on its line
+  trailing_code: bool,
+}
+
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+
+/// C-family base syntax
+const C_LIKE: Syntax = Syntax {
+  line: &["//"],
+  line_at_word_start: false,
+  block: C_BLOCK,
+  nested: false,
+  strings: QUOTES,
+  python_literals: false,
+  docstrings: false,
+  rust_literals: false,
+  heredocs: None,
+  regex_literals: false,
+};
+
+/// JavaScript and TypeScript
+const JS: Syntax = Syntax {
+  strings: &[TEMPLATE, DOUBLE, SINGLE],
+  regex_literals: true,
+  ..C_LIKE
+};
+
+/// C, C++, Java and the other languages with `"""` text blocks
+const C: Syntax = Syntax {
+  strings: C_STRINGS,
+  ..C_LIKE
+};
+
+const CSHARP: Syntax = Syntax {
+  strings: CSHARP_STRINGS,
+  ..C_LIKE
+};
+
+const KOTLIN: Syntax = Syntax {
+  strings: KOTLIN_STRINGS,
+  ..C_LIKE
+};
+
+const GO: Syntax = Syntax {
+  strings: &[BACKTICK_RAW, DOUBLE, SINGLE],
+  ..C_LIKE
+};
+
+/// Verilog, where `'` marks sized numbers (`8'hFF`) and backticks start
+/// compiler directives
+const VERILOG: Syntax = Syntax {
+  strings: &[DOUBLE],
+  ..C_LIKE
+};
+
+const SCALA: Syntax = Syntax {
+  nested: true,
+  strings: KOTLIN_STRINGS,
+  ..C_LIKE
+};
+
+const SWIFT: Syntax = Syntax {
+  nested: true,
+  strings: SWIFT_STRINGS,
+  ..C_LIKE
+};
+
+/// Rust, whose plain strings may span lines
+const RUST: Syntax = Syntax {
+  nested: true,
+  strings: &[
+    Literal {
+      multiline: true,
+      ..DOUBLE
+    },
+    SINGLE,
+  ],
+  rust_literals: true,
+  ..C_LIKE
+};
+
+const CSS: Syntax = Syntax {
+  line: &[],
+  ..C_LIKE
+};
+
+/// SCSS and Less
+const SCSS: Syntax = Syntax { ..C_LIKE };
+
+const JSONC: Syntax = Syntax {
+  strings: &[DOUBLE],
+  ..C_LIKE
+};
+
+const SQL: Syntax = Syntax {
+  line: &["--"],
+  strings: SQL_STRINGS,
+  ..C_LIKE
+};
+
+const PHP: Syntax = Syntax {
+  line: &["//", "#"],
+  heredocs: Some(Heredoc::Php),
+  ..C_LIKE
+};
+
+/// Vue, Svelte and Astro components
+const COMPONENT: Syntax = Syntax {
+  block: &[("/*", "*/"), ("<!--", "-->")],
+  ..JS
+};
+
+const PYTHON: Syntax = Syntax {
+  line: &["#"],
+  line_at_word_start: false,
+  block: &[],
+  nested: false,
+  strings: &[],
+  python_literals: true,
+  docstrings: true,
+  rust_literals: false,
+  heredocs: None,
+  regex_literals: false,
+};
+
+const CYTHON: Syntax = Syntax {
+  docstrings: false,
+  ..PYTHON
+};
+
+/// YAML and generic config files
+const HASH_WORD: Syntax = Syntax {
+  line_at_word_start: true,
+  strings: QUOTES,
+  python_literals: false,
+  docstrings: false,
+  ..PYTHON
+};
+
+const SHELL: Syntax = Syntax {
+  heredocs: Some(Heredoc::Shell),
+  ..HASH_WORD
+};
+
+const PERL: Syntax = Syntax {
+  heredocs: Some(Heredoc::Perl),
+  ..HASH_WORD
+};
+
+/// PowerShell, where `#` inside a bare word such as `a#b` is not a comment
+const POWERSHELL: Syntax = Syntax {
+  block: &[("<#", "#>")],
+  strings: POWERSHELL_STRINGS,
+  ..HASH_WORD
+};
+
+/// TOML and R
+const HASH: Syntax = Syntax {
+  strings: QUOTES,
+  python_literals: false,
+  docstrings: false,
+  ..PYTHON
+};
+
+/// HTML, XML and SVG; text content is not quoted, so quotes are not tracked
+const SGML: Syntax = Syntax {
+  line: &[],
+  line_at_word_start: false,
+  block: &[("<!--", "-->")],
+  nested: false,
+  strings: &[],
+  python_literals: false,
+  docstrings: false,
+  rust_literals: false,
+  heredocs: None,
+  regex_literals: false,
+};
+
+/// The lexer syntax for an extension, for languages of the c-like, hash-like
+/// and sgml-like families
+pub fn syntax_for(ext: &str) -> Option<&'static Syntax> {
+  let syntax = match ext {
+    "js" | "mjs" | "cjs" | "ts" | "mts" | "tsx" | "jsx" => &JS,
+    "c" | "h" | "cpp" | "hpp" | "cc" | "java" | "dart" | "groovy" => &C,
+    "cs" => &CSHARP,
+    "kt" | "kts" => &KOTLIN,
+    "go" => &GO,
+    "v" | "vh" | "sv" | "svh" => &VERILOG,
+    "rs" => &RUST,
+    "swift" => &SWIFT,
+    "scala" => &SCALA,
+    "css" => &CSS,
+    "scss" | "less" => &SCSS,
+    "jsonc" => &JSONC,
+    "sql" => &SQL,
+    "php" => &PHP,
+    "vue" | "svelte" | "astro" => &COMPONENT,
+    "py" | "pyw" => &PYTHON,
+    "pyx" => &CYTHON,
+    "sh" | "bash" | "zsh" => &SHELL,
+    "pl" | "pm" => &PERL,
+    "ps1" | "psm1" => &POWERSHELL,
+    "fish" | "yaml" | "yml" | "conf" | "makefile" | "dockerfile" | "cmake" => &HASH_WORD,
+    "toml" | "r" => &HASH,
+    "html" | "htm" | "xml" | "svg" | "xhtml" => &SGML,
+    _ => return None,
+  };
+  Some(syntax)
+}
+
+impl Literal {
+  /// Length of this literal at the start of `rest` up to and including its
+  /// closing delimiter, or `None` when `rest` does not open one. Unterminated
+  /// literals run to the end of the line, or of the text when multi-line.
+  fn len(&self, rest: &str, syntax: &Syntax) -> Option<usize> {
+    let bytes = rest.as_bytes();
+    let (mut at, close) = if self.extendable {
+      let repeated = self.open.as_bytes()[0];
+      let base = self.open.bytes().take_while(|&b| b == repeated).count();
+      let run = bytes.iter().take_while(|&&b| b == repeated).count();
+      if run < base || !rest[run - base..].starts_with(self.open) {
+        return None;
+      }
+      let extra = (repeated as char).to_string().repeat(run - base);
+      let close = if self.close.ends_with(repeated as char) {
+        format!("{}{extra}", self.close)
+      } else {
+        format!("{extra}{}", self.close)
+      };
+      (run - base + self.open.len(), Cow::Owned(close))
+    } else if rest.starts_with(self.open) {
+      (self.open.len(), Cow::Borrowed(self.close))
+    } else {
+      return None;
+    };
+    let close = close.as_bytes();
+
+    while at < bytes.len() {
+      if let Some(hole) = self.holes.filter(|hole| bytes[at..].starts_with(hole.as_bytes())) {
+        // `{{` is a literal brace where holes open with a bare `{`
+        if hole == "{" && bytes.get(at + 1) == Some(&b'{') {
+          at += 2;
+          continue;
+        }
+        if let Some(end) = hole_end(rest, at + hole.len(), self.multiline, syntax) {
+          at = end;
+          continue;
+        }
+      }
+      match self.escape {
+        Escape::Backslash if bytes[at] == b'\\' => {
+          at += 2;
+          continue;
+        }
+        Escape::Backtick if bytes[at] == b'`' => {
+          at += 2;
+          continue;
+        }
+        Escape::Doubled if bytes[at..].starts_with(close) && bytes[at + close.len()..].starts_with(close) => {
+          at += 2 * close.len();
+          continue;
+        }
+        _ => {}
+      }
+      if bytes[at..].starts_with(close) {
+        return Some(at + close.len());
+      }
+      if !self.multiline && bytes[at] == b'\n' {
+        return Some(at);
+      }
+      at += 1;
+    }
+    Some(rest.len())
+  }
+}
+
+/// Length of the first of the syntax's string literals opening at `rest`
+fn literal_len(rest: &str, syntax: &Syntax) -> Option<usize> {
+  syntax.strings.iter().find_map(|literal| literal.len(rest, syntax))
+}
+
+/// End of the embedded expression whose opener ends before `start`, or `None`
+/// when it never closes
+fn hole_end(rest: &str, start: usize, multiline: bool, syntax: &Syntax) -> Option<usize> {
+  let bytes = rest.as_bytes();
+  let mut depth = 1;
+  let mut at = start;
+  while at < bytes.len() {
+    if let Some(len) = literal_len(&rest[at..], syntax) {
+      at += len;
+      continue;
+    }
+    match bytes[at] {
+      b'(' | b'[' | b'{' => depth += 1,
+      b')' | b']' | b'}' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(at + 1);
+        }
+      }
+      b'\n' if !multiline => return None,
+      _ => {}
+    }
+    at += rest[at..].chars().next().map_or(1, char::len_utf8);
+  }
+  None
+}
+
+/// Length of the Rust raw string starting at `rest` (`r`, `br` or `cr`, any
+/// number of `#`, then `"`) up to and including the matching `"#..`, or
+/// `None` when `rest` does not start one. Unterminated strings run to the end.
+fn raw_string_len(rest: &str) -> Option<usize> {
+  let bytes = rest.as_bytes();
+  let mut at = match bytes {
+    [b'r', ..] => 1,
+    [b'b' | b'c', b'r', ..] => 2,
+    _ => return None,
+  };
+  let hashes = bytes[at..].iter().take_while(|&&b| b == b'#').count();
+  at += hashes;
+  if bytes.get(at) != Some(&b'"') {
+    return None;
+  }
+  at += 1;
+
+  let close = format!("\"{}", "#".repeat(hashes));
+  Some(rest[at..].find(&close).map_or(rest.len(), |i| at + i + close.len()))
+}
+
+/// Whether a literal prefix at `at` starts a token rather than ending an
+/// identifier, as `r` in `r"..."` but not in `bar"`
+fn starts_token(code: &str, at: usize) -> bool {
+  code[..at].chars().next_back().map_or(true, |c| !(c.is_alphanumeric() || c == '_'))
+}
+
+/// Whether the `'` starting `rest` is a lifetime or label (`'a`, `'static`)
+/// rather than a character literal (`'a'`, `'\n'`)
+fn is_lifetime(rest: &str) -> bool {
+  let Some(after) = rest.strip_prefix('\'') else {
+    return false;
+  };
+  let mut chars = after.chars();
+  match chars.next() {
+    Some(c) if c.is_alphabetic() || c == '_' => chars.next() != Some('\''),
+    _ => false,
+  }
+}
+
+/// Length of the Python string literal starting at `rest`, including an
+/// `r`/`b`/`f`/`u` prefix, or `None` when `rest` does not start one.
+/// Unterminated strings run to the end of the line, or of the text when
+/// triple-quoted.
+fn python_string_len(rest: &str) -> Option<usize> {
+  let bytes = rest.as_bytes();
+  let prefix = bytes.iter().take_while(|b| b"rRbBfFuU".contains(b)).count();
+  let quote = *bytes.get(prefix)?;
+  if prefix > 2 || !matches!(quote, b'"' | b'\'') {
+    return None;
+  }
+  let formatted = bytes[..prefix].iter().any(|b| matches!(b, b'f' | b'F'));
+  let triple = bytes[prefix..].starts_with(&[quote; 3]);
+  let delimiter = if triple { 3 } else { 1 };
+
+  let mut at = prefix + delimiter;
+  while at < bytes.len() {
+    match bytes[at] {
+      b'\\' => at += 2,
+      b'\n' if !triple => return Some(at),
+      b if b == quote && (!triple || bytes[at..].starts_with(&[quote; 3])) => return Some(at + delimiter),
+      b'{' if formatted && bytes.get(at + 1) == Some(&b'{') => at += 2,
+      b'{' if formatted => at = replacement_field_end(rest, at + 1, triple),
+      _ => at += 1,
+    }
+  }
+  Some(rest.len())
+}
+
+/// End of the f-string replacement field whose `{` ends before `start`.
+/// The field is code, so quotes in it open nested strings
+/// (`f"{d["key"]}"`) rather than closing the outer one.
+fn replacement_field_end(rest: &str, start: usize, multiline: bool) -> usize {
+  let bytes = rest.as_bytes();
+  let mut depth = 1;
+  let mut at = start;
+  while at < bytes.len() {
+    match bytes[at] {
+      b'{' | b'[' | b'(' => depth += 1,
+      b'}' | b']' | b')' => {
+        depth -= 1;
+        if depth == 0 {
+          return at + 1;
+        }
+      }
+      b'\n' if !multiline => return at,
+      b if b.is_ascii() && starts_token(rest, at) => {
+        if let Some(len) = python_string_len(&rest[at..]) {
+          at += len;
+          continue;
+        }
+      }
+      _ => {}
+    }
+    at += 1;
+  }
+  at
+}
+
+/// End of the statement made only of the Python string literals (implicitly
+/// concatenated when several) starting at `start`, which opens its line, or
+/// `None` when the line holds anything else
+fn string_statement_end(code: &str, start: usize) -> Option<usize> {
+  let line_start = code[..start].rfind('\n').map_or(0, |i| i + 1);
+  // A backslash continues the previous line into this one
+  if !code[line_start..start].trim().is_empty() || code[..line_start].ends_with("\\\n") {
+    return None;
+  }
+
+  let mut end = start;
+  while let Some(len) = python_string_len(&code[end..]) {
+    end += len;
+    end += code[end..].len() - code[end..].trim_start_matches([' ', '\t']).len();
+  }
+  let rest = &code[end..];
+  (end > start && (rest.is_empty() || rest.starts_with(['\n', '#']))).then_some(end)
+}
+
+/// Parse the heredoc operator starting at `rest`, returning its length and
+/// the terminator of the body that starts on the next line
+pub(crate) fn heredoc_opener(rest: &str, flavour: Heredoc) -> Option<(usize, Terminator)> {
+  let bytes = rest.as_bytes();
+  let operator = match flavour {
+    Heredoc::Php => "<<<",
+    _ => "<<",
+  };
+  if !rest.starts_with(operator) {
+    return None;
+  }
+  let mut at = operator.len();
+
+  let modifier = match (flavour, bytes.get(at)) {
+    (Heredoc::Shell, Some(b'-')) | (Heredoc::Perl | Heredoc::Ruby, Some(b'~')) | (Heredoc::Ruby, Some(b'-')) => true,
+    (Heredoc::Shell, Some(b'<')) => return None,
+    _ => false,
+  };
+  if modifier {
+    at += 1;
+  }
+  let spaces = bytes[at..].iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+  at += spaces;
+
+  let (word, len, quoted) = match bytes.get(at) {
+    Some(&quote @ (b'"' | b'\'')) => {
+      let close = rest[at + 1..].find(quote as char)?;
+      let word = &rest[at + 1..at + 1 + close];
+      (word, close + 2, true)
+    }
+    Some(b'\\') if matches!(flavour, Heredoc::Shell) => {
+      let word = identifier(&rest[at + 1..]);
+      (word, word.len() + 1, true)
+    }
+    _ => {
+      let word = identifier(&rest[at..]);
+      (word, word.len(), false)
+    }
+  };
+  // Only the shell and PHP put spaces before a bare word; elsewhere `<< x` is a shift
+  let spaced_bare = spaces > 0 && !quoted && !matches!(flavour, Heredoc::Shell | Heredoc::Php);
+  let lowercase_ruby = matches!(flavour, Heredoc::Ruby) && !quoted && !word.starts_with(|c: char| c.is_ascii_uppercase() || c == '_');
+  if word.is_empty() || word.contains('\n') || spaced_bare || lowercase_ruby {
+    return None;
+  }
+
+  let terminator = Terminator {
+    word: word.to_string(),
+    indented: modifier || matches!(flavour, Heredoc::Php),
+    trailing_code: matches!(flavour, Heredoc::Php),
+  };
+  Some((at + len, terminator))
+}
+
+fn identifier(text: &str) -> &str {
+  let len = text
+    .bytes()
+    .enumerate()
+    .take_while(|&(i, b)| b == b'_' || b.is_ascii_alphabetic() || (i > 0 && b.is_ascii_digit()))
+    .count();
+  &text[..len]
+}
+
+/// Length of the heredoc body starting at `body` (the line after the
+/// operator) through its terminator word, or `None` when no line ends it
+pub(crate) fn heredoc_body_len(body: &str, terminator: &Terminator) -> Option<usize> {
+  let mut line_start = 0;
+  for line in body.split_inclusive('\n') {
+    let content = line.trim_end_matches('\n');
+    let indent = if terminator.indented { content.len() - content.trim_start().len() } else { 0 };
+    if let Some(after) = content[indent..].strip_prefix(terminator.word.as_str()) {
+      let ends = if terminator.trailing_code {
+        !after.starts_with(|c: char| c == '_' || c.is_ascii_alphanumeric())
+      } else {
+        after.is_empty()
+      };
+      if ends {
+        return Some(line_start + indent + terminator.word.len());
+      }
+    }
+    line_start += line.len();
+  }
+  None
+}
+
+/// Keywords after which a `/` starts a regex literal rather than dividing
+const REGEX_KEYWORDS: &[&str] = &[
+  "return", "typeof", "instanceof", "in", "of", "new", "delete", "void", "throw", "case", "do", "else", "yield", "await",
+];
+
+/// Length of the JavaScript regex literal (with its flags) starting at
+/// `code[at..]`, or `None` when the `/` there divides or opens a comment.
+/// A `/` starts a regex after an operator, an opening bracket, a keyword
+/// like `return`, or at the start of the text.
+fn regex_literal_len(code: &str, at: usize) -> Option<usize> {
+  let rest = &code[at..];
+  if !rest.starts_with('/') || rest[1..].starts_with(['/', '*']) {
+    return None;
+  }
+
+  let before = code[..at].trim_end();
+  let expects_operand = match before.chars().next_back() {
+    None => true,
+    Some(c) if c.is_alphanumeric() || c == '_' || c == '$' => {
+      let word_start = before
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .map_or(0, |i| i + 1);
+      let word = &before[word_start..];
+      REGEX_KEYWORDS.contains(&word) && !before[..word_start].ends_with('.')
+    }
+    // Closing brackets and quotes end an operand, as in `(a) / b` and `x[1] / 2`
+    Some(c) => !matches!(c, ')' | ']' | '"' | '\'' | '`'),
+  };
+  if !expects_operand {
+    return None;
+  }
+
+  let bytes = rest.as_bytes();
+  let mut in_class = false;
+  let mut i = 1;
+  while i < bytes.len() {
+    match bytes[i] {
+      b'\\' => i += 1,
+      b'\n' => return None,
+      b'[' => in_class = true,
+      b']' => in_class = false,
+      b'/' if !in_class => {
+        let flags = bytes[i + 1..].iter().take_while(|b| b.is_ascii_alphabetic()).count();
+        return Some(i + 1 + flags);
+      }
+      _ => {}
+    }
+    i += 1;
+  }
+  None
+}
+
+/// Length of the block comment starting at `rest`, or `None` when it never ends
+fn block_len(rest: &str, open: &str, close: &str, nested: bool) -> Option<usize> {
+  if !nested {
+    return rest[open.len()..].find(close).map(|i| open.len() + i + close.len());
+  }
+  let bytes = rest.as_bytes();
+  let mut depth = 0;
+  let mut at = 0;
+  while at < bytes.len() {
+    if bytes[at..].starts_with(open.as_bytes()) {
+      depth += 1;
+      at += open.len();
+    } else if bytes[at..].starts_with(close.as_bytes()) {
+      depth -= 1;
+      at += close.len();
+      if depth == 0 {
+        return Some(at);
+      }
+    } else {
+      at += 1;
+    }
+  }
+  None
+}
+
+/// What a span of the scanned text holds
+#[derive(Clone, Copy, PartialEq)]
+enum SpanKind {
+  Comment,
+  /// A Python string-only statement, removed with the comments
+  Docstring,
+  Literal,
+}
+
+struct Span {
+  range: Range<usize>,
+  kind: SpanKind,
+}
+
+/// The comments and literals of `code`, in order. Unterminated block comments
+/// are code.
+fn scan(code: &str, syntax: &Syntax) -> Vec<Span> {
+  let mut spans = Vec::new();
+  let mut at = 0;
+  // Open brackets; Python statements only start outside them
+  let mut brackets = 0usize;
+  // Heredocs whose bodies start on the next line
+  let mut heredocs = Vec::new();
+  let push = |spans: &mut Vec<Span>, start: usize, end: usize, kind: SpanKind| {
+    spans.push(Span { range: start..end, kind });
+    end
+  };
+
+  while at < code.len() {
+    let rest = &code[at..];
+    if rest.starts_with('\n') && !heredocs.is_empty() {
+      at += 1;
+      for terminator in heredocs.drain(..) {
+        let len = heredoc_body_len(&code[at..], &terminator).unwrap_or(0);
+        at = push(&mut spans, at, at + len, SpanKind::Literal);
+      }
+      continue;
+    }
+
+    if syntax.rust_literals && starts_token(code, at) {
+      if let Some(len) = raw_string_len(rest) {
+        at = push(&mut spans, at, at + len, SpanKind::Literal);
+        continue;
+      }
+    }
+
+    if syntax.python_literals && starts_token(code, at) {
+      if let Some(len) = python_string_len(rest) {
+        let docstring = if syntax.docstrings && brackets == 0 { string_statement_end(code, at) } else { None };
+        at = match docstring {
+          Some(end) => push(&mut spans, at, end, SpanKind::Docstring),
+          None => push(&mut spans, at, at + len, SpanKind::Literal),
+        };
+        continue;
+      }
+    }
+
+    // Markers and quotes are ASCII, so anything else is plain code
+    if !rest.as_bytes()[0].is_ascii_punctuation() {
+      at += rest.chars().next().map_or(1, char::len_utf8);
+      continue;
+    }
+
+    if syntax.rust_literals && is_lifetime(rest) {
+      at += 1;
+      continue;
+    }
+
+    if let Some(len) = literal_len(rest, syntax) {
+      at = push(&mut spans, at, at + len, SpanKind::Literal);
+      continue;
+    }
+
+    if let Some(flavour) = syntax.heredocs {
+      if let Some((len, terminator)) = heredoc_opener(rest, flavour) {
+        // A body that never ends means the `<<` was a shift after all
+        let line_end = rest.find('\n').map_or(code.len(), |i| at + i + 1);
+        if heredoc_body_len(&code[line_end..], &terminator).is_some() {
+          heredocs.push(terminator);
+        }
+        at += len;
+        continue;
+      }
+    }
+
+    if syntax.regex_literals {
+      if let Some(len) = regex_literal_len(code, at) {
+        at = push(&mut spans, at, at + len, SpanKind::Literal);
+        continue;
+      }
+    }
+
+    let word_start = !syntax.line_at_word_start || code[..at].chars().next_back().map_or(true, char::is_whitespace);
+    if word_start && syntax.line.iter().any(|marker| rest.starts_with(marker)) {
+      let len = rest.find('\n').unwrap_or(rest.len());
+      at = push(&mut spans, at, at + len, SpanKind::Comment);
+      continue;
+    }
+
+    if let Some((open, close)) = syntax.block.iter().find(|(open, _)| rest.starts_with(open)) {
+      at = match block_len(rest, open, close, syntax.nested) {
+        Some(len) => push(&mut spans, at, at + len, SpanKind::Comment),
+        None => code.len(),
+      };
+      continue;
+    }
+
+    match rest.as_bytes()[0] {
+      b'(' | b'[' | b'{' => brackets += 1,
+      b')' | b']' | b'}' => brackets = brackets.saturating_sub(1),
+      _ => {}
+    }
+    at += 1;
+  }
+
+  spans
+}
+
+/// Remove the comments (and, for Python, docstrings) from `code` that
+/// `removes` accepts. Unterminated block comments are left in place.
+pub fn strip_comments(code: &str, syntax: &Syntax, removes: impl Fn(&str) -> bool) -> String {
+  let mut out = String::with_capacity(code.len());
+  // Start of the code not yet copied to `out`
+  let mut copied = 0;
+  for span in scan(code, syntax) {
+    if span.kind != SpanKind::Literal && removes(&code[span.range.clone()]) {
+      out.push_str(&code[copied..span.range.start]);
+      copied = span.range.end;
+    }
+  }
+  out.push_str(&code[copied..]);
+  out
+}
+
+/// Byte ranges of the comments in `code`, docstrings included
+pub(crate) fn comment_spans(code: &str, syntax: &Syntax) -> Vec<Range<usize>> {
+  scan(code, syntax)
+    .into_iter()
+    .filter(|span| span.kind != SpanKind::Literal)
+    .map(|span| span.range)
+    .collect()
+}
+
+/// Byte ranges of the comments and literals of `code`, in order
+pub(crate) fn token_spans(code: &str, syntax: &Syntax) -> Vec<Range<usize>> {
+  scan(code, syntax).into_iter().map(|span| span.range).collect()
+}
+
+/// Byte ranges of the string literals in `code`, heredoc bodies and regex
+/// literals included
+pub(crate) fn literal_spans(code: &str, syntax: &Syntax) -> Vec<Range<usize>> {
+  scan(code, syntax)
+    .into_iter()
+    .filter(|span| span.kind != SpanKind::Comment)
+    .map(|span| span.range)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn strip(code: &str, ext: &str) -> String {
+    strip_comments(code, syntax_for(ext).expect("known extension"), |_| true)
+  }
+
+  fn strip_rust(code: &str) -> String {
+    strip_comments(code, syntax_for("rs").expect("rust syntax"), |_| true)
+  }
+
+  #[test]
+  fn rust_string_with_slashes_is_not_a_lifetime() {
+    let code = "let url = \"http://example.com\"; // c\n";
+    assert_eq!(strip_rust(code).trim_end(), "let url = \"http://example.com\";");
+  }
+
+  #[test]
+  fn rust_lifetimes_next_to_char_literals() {
+    let code = "fn f<'a>(s: &'a str) -> char { if s.is_empty() { 'a' } else { '/' } } // c\n";
+    assert_eq!(
+      strip_rust(code).trim_end(),
+      "fn f<'a>(s: &'a str) -> char { if s.is_empty() { 'a' } else { '/' } }"
+    );
+  }
+
+  #[test]
+  fn c_like_markers_inside_strings_are_kept() {
+    let code = "const s = \"/* not */ // nor\"; /* gone */ const t = `${a} // kept`; // gone\n";
+    assert_eq!(strip(code, "js"), "const s = \"/* not */ // nor\";  const t = `${a} // kept`; \n");
+  }
+
+  #[test]
+  fn javascript_regex_literals_are_not_comments() {
+    let code = "const re = /https?:\\/\\//g; // gone\n";
+    assert_eq!(strip(code, "js"), "const re = /https?:\\/\\//g; \n");
+  }
+
+  #[test]
+  fn rust_block_comments_nest() {
+    assert_eq!(strip("a /* x /* y */ z */ b", "rs"), "a  b");
+  }
+
+  #[test]
+  fn hash_comments_only_start_words_in_shell_and_yaml() {
+    assert_eq!(strip("echo ${#list} $# # gone\n", "sh"), "echo ${#list} $# \n");
+    assert_eq!(strip("key: a#b # gone\n", "yaml"), "key: a#b \n");
+  }
+
+  #[test]
+  fn python_docstrings_go_and_other_strings_stay() {
+    let code = "def f():\n    \"\"\"Doc.\"\"\"\n    return '# kept'  # gone\n";
+    assert_eq!(strip(code, "py"), "def f():\n    \n    return '# kept'  \n");
+  }
+
+  #[test]
+  fn sgml_comments_are_removed() {
+    assert_eq!(strip("<p>a</p><!-- gone\n-->\n<p>b</p>", "html"), "<p>a</p>\n<p>b</p>");
+  }
+}
@@ -0,0 +1,31 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Files larger than this are not read
+pub const DEFAULT_MAX_FILE_SIZE: usize = 5 * 1024 * 1024;
+/// Texts larger than this are returned untouched by comment removal and minification
+pub const DEFAULT_MAX_PROCESS_SIZE: usize = 500 * 1024;
+
+// Read on every file, so kept outside the settings lock
+static MAX_FILE_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_FILE_SIZE);
+static MAX_PROCESS_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_PROCESS_SIZE);
+
+/// Size limits in bytes
+#[derive(Clone, Copy, serde::Serialize)]
+pub struct Limits {
+  pub max_file_size: usize,
+  pub max_process_size: usize,
+}
+
+pub fn max_file_size() -> usize {
+  MAX_FILE_SIZE.load(Ordering::Relaxed)
+}
+
+pub fn max_process_size() -> usize {
+  MAX_PROCESS_SIZE.load(Ordering::Relaxed)
+}
+
+/// Make `limits` the ones every later read and processing pass uses
+pub fn apply(limits: Limits) {
+  MAX_FILE_SIZE.store(limits.max_file_size, Ordering::Relaxed);
+  MAX_PROCESS_SIZE.store(limits.max_process_size, Ordering::Relaxed);
+}
@@ -0,0 +1,38 @@
+/// How far past the previous match a processed line is looked for; removed
+/// comment blocks are rarely longer
+const SEARCH_WINDOW: usize = 2000;
+
+/// `line` without any whitespace, for comparing lines whose spacing the
+/// processing changed
+fn squeezed(line: &str) -> String {
+  line.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Prefix each non-blank line of `processed` with the number of the line of
+/// `original` it came from, counting from `first_line`. Processing only
+/// removes text, so a processed line is found in the first original line at
+/// or after the previous match that contains it, ignoring whitespace; lines
+/// the minifier joined match the original line they start with. A line that
+/// matches nothing gets the number after the previous match.
+pub fn annotate(original: &str, processed: &str, first_line: usize) -> String {
+  let originals: Vec<String> = original.lines().map(squeezed).collect();
+  let mut next = 0;
+  let mut out = String::with_capacity(processed.len() + processed.len() / 8);
+
+  for line in processed.lines() {
+    let key = squeezed(line);
+    if key.is_empty() {
+      out.push('\n');
+      continue;
+    }
+    let found = (next..originals.len().min(next + SEARCH_WINDOW))
+      .find(|&i| originals[i].contains(&key) || (!originals[i].is_empty() && key.starts_with(&originals[i])));
+    let index = found.unwrap_or(next);
+    next = found.map_or(next + 1, |i| i + 1);
+    out.push_str(&format!("{}: {line}\n", first_line + index));
+  }
+  if !processed.ends_with('\n') {
+    out.pop();
+  }
+  out
+}
@@ -0,0 +1,172 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+#[derive(Clone, Copy)]
+enum Format {
+  /// `package-lock.json`, `npm-shrinkwrap.json`
+  Npm,
+  Yarn,
+  Pnpm,
+  /// `Cargo.lock`, `poetry.lock`: TOML `[[package]]` tables
+  TomlPackages,
+  Composer,
+}
+
+fn format(name: &str) -> Option<Format> {
+  Some(match name.to_lowercase().as_str() {
+    "package-lock.json" | "npm-shrinkwrap.json" => Format::Npm,
+    "yarn.lock" => Format::Yarn,
+    "pnpm-lock.yaml" => Format::Pnpm,
+    "cargo.lock" | "poetry.lock" => Format::TomlPackages,
+    "composer.lock" => Format::Composer,
+    _ => return None,
+  })
+}
+
+fn npm(content: &str, packages: &mut BTreeSet<String>) -> Option<()> {
+  let lock: Value = serde_json::from_str(content).ok()?;
+  // Version 2 and 3 key packages by their install path
+  if let Some(entries) = lock["packages"].as_object() {
+    for (path, package) in entries {
+      // The root project is keyed `""`, workspaces by their folder
+      let Some((_, name)) = path.rsplit_once("node_modules/") else {
+        continue;
+      };
+      if let Some(version) = package["version"].as_str() {
+        packages.insert(format!("{name}@{version}"));
+      }
+    }
+    return Some(());
+  }
+  // Version 1 nests dependencies
+  fn walk(dependencies: &Value, packages: &mut BTreeSet<String>) {
+    for (name, package) in dependencies.as_object().into_iter().flatten() {
+      if let Some(version) = package["version"].as_str() {
+        packages.insert(format!("{name}@{version}"));
+      }
+      walk(&package["dependencies"], packages);
+    }
+  }
+  walk(&lock["dependencies"], packages);
+  Some(())
+}
+
+/// Package name of a yarn or pnpm descriptor: `"@scope/name@^1.0.0"`,
+/// `name@npm:1.0.0`
+fn descriptor_name(descriptor: &str) -> &str {
+  let descriptor = descriptor.trim().trim_matches('"').trim_start_matches('/');
+  // A leading `@` starts a scope, not the version
+  match descriptor.get(1..).and_then(|rest| rest.find('@')) {
+    Some(at) => &descriptor[..at + 1],
+    None => descriptor,
+  }
+}
+
+fn yarn(content: &str, packages: &mut BTreeSet<String>) -> Option<()> {
+  let mut name = None;
+  for line in content.lines() {
+    if line.trim().is_empty() || line.starts_with('#') {
+      continue;
+    }
+    if !line.starts_with(' ') {
+      // `"a@^1.0.0", a@^1.1.0:` opens an entry; `__metadata:` is yarn's own
+      name = line
+        .strip_suffix(':')
+        .and_then(|descriptors| descriptors.split(',').next())
+        .map(descriptor_name)
+        .filter(|name| !name.is_empty() && *name != "__metadata");
+    } else if let Some(name) = name {
+      // `  version "1.2.3"` in yarn 1, `  version: 1.2.3` in later ones
+      let trimmed = line.trim();
+      if let Some(version) = trimmed.strip_prefix("version").filter(|rest| rest.starts_with([' ', ':'])) {
+        let version = version.trim_start_matches(':').trim().trim_matches('"');
+        packages.insert(format!("{name}@{version}"));
+      }
+    }
+  }
+  Some(())
+}
+
+fn pnpm(content: &str, packages: &mut BTreeSet<String>) -> Option<()> {
+  let mut in_packages = false;
+  for line in content.lines().filter(|line| !line.trim().is_empty()) {
+    if !line.starts_with(' ') {
+      in_packages = line.trim_end() == "packages:";
+      continue;
+    }
+    // Entries sit two spaces in: `  /name@1.0.0:` or `  /name/1.0.0:` (v5)
+    let Some(key) = line.strip_prefix("  ").filter(|key| !key.starts_with(' ')) else {
+      continue;
+    };
+    if !in_packages {
+      continue;
+    }
+    let key = key.trim_end().trim_end_matches(':').trim_matches(['\'', '"']).trim_start_matches('/');
+    // Peer suffixes: `name@1.0.0(react@18.2.0)`
+    let key = key.split('(').next().unwrap_or(key);
+    let name = descriptor_name(key);
+    let (name, version) = match key[name.len()..].strip_prefix('@') {
+      Some(version) => (name, version),
+      None => match key.rsplit_once('/') {
+        Some(pair) => pair,
+        None => continue,
+      },
+    };
+    packages.insert(format!("{name}@{version}"));
+  }
+  Some(())
+}
+
+fn toml_packages(content: &str, packages: &mut BTreeSet<String>) -> Option<()> {
+  let lock: toml::Value = toml::from_str(content).ok()?;
+  for package in lock.get("package")?.as_array()? {
+    if let (Some(name), Some(version)) = (
+      package.get("name").and_then(|n| n.as_str()),
+      package.get("version").and_then(|v| v.as_str()),
+    ) {
+      packages.insert(format!("{name}@{version}"));
+    }
+  }
+  Some(())
+}
+
+fn composer(content: &str, packages: &mut BTreeSet<String>) -> Option<()> {
+  let lock: Value = serde_json::from_str(content).ok()?;
+  for package in ["packages", "packages-dev"]
+    .iter()
+    .filter_map(|key| lock[key].as_array())
+    .flatten()
+  {
+    if let (Some(name), Some(version)) = (package["name"].as_str(), package["version"].as_str()) {
+      packages.insert(format!("{name}@{version}"));
+    }
+  }
+  Some(())
+}
+
+/// Whether `name` is a lockfile `summarize` knows
+pub fn is_lockfile(name: &str) -> bool {
+  format(name).is_some()
+}
+
+/// Reduce a lockfile to its locked packages, one sorted `name@version` per
+/// line. `None` when `name` is not a known lockfile or `content` does not
+/// parse as one.
+pub fn summarize(name: &str, content: &str) -> Option<String> {
+  let mut packages = BTreeSet::new();
+  match format(name)? {
+    Format::Npm => npm(content, &mut packages),
+    Format::Yarn => yarn(content, &mut packages),
+    Format::Pnpm => pnpm(content, &mut packages),
+    Format::TomlPackages => toml_packages(content, &mut packages),
+    Format::Composer => composer(content, &mut packages),
+  }?;
+
+  let mut summary = format!("# {} locked packages\n", packages.len());
+  for package in packages {
+    summary.push_str(&package);
+    summary.push('\n');
+  }
+  Some(summary)
+}
@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+  if std::env::args().nth(1).as_deref() == Some("--mcp") {
+    app_lib::run_mcp();
+    return;
+  }
   app_lib::run();
 }
@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use tiktoken_rs::CoreBPE;
+
+use crate::combined::language;
+use crate::report::ReportSkippedFile;
+use crate::store::StoredFile;
+
+/// Bumped whenever a field is renamed or removed
+const MANIFEST_VERSION: u32 = 1;
+
+/// A processed file as listed in the manifest
+#[derive(serde::Serialize)]
+pub struct ManifestFile {
+  pub id: String,
+  pub path: String,
+  pub language: String,
+  pub size_bytes: usize,
+  pub line_count: usize,
+  pub tokens: usize,
+  /// BLAKE3 hash of the processed content
+  pub hash: String,
+}
+
+/// Machine-readable description of a processed file set
+#[derive(serde::Serialize)]
+pub struct Manifest {
+  pub version: u32,
+  pub generated_ms: u64,
+  pub processing_mode: String,
+  pub files: Vec<ManifestFile>,
+  pub skipped: Vec<ReportSkippedFile>,
+  /// Requested ids with no processed file in the store
+  pub missing: Vec<String>,
+  pub total_bytes: usize,
+  pub total_tokens: usize,
+}
+
+pub fn build(
+  files: &[Arc<StoredFile>],
+  missing: Vec<String>,
+  processing_mode: String,
+  skipped: Vec<ReportSkippedFile>,
+  encoder: &CoreBPE,
+) -> Manifest {
+  let generated_ms = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map_or(0, |d| d.as_millis() as u64);
+
+  let files: Vec<ManifestFile> = files
+    .iter()
+    .map(|file| ManifestFile {
+      id: file.id.clone(),
+      path: file.label().to_string(),
+      language: language(file).to_string(),
+      size_bytes: file.content.len(),
+      line_count: file.content.lines().count(),
+      tokens: file.tokens(encoder),
+      hash: file.hash.clone(),
+    })
+    .collect();
+
+  Manifest {
+    version: MANIFEST_VERSION,
+    generated_ms,
+    processing_mode,
+    total_bytes: files.iter().map(|f| f.size_bytes).sum(),
+    total_tokens: files.iter().map(|f| f.tokens).sum(),
+    files,
+    skipped,
+    missing,
+  }
+}
+
+pub fn write(path: &Path, manifest: &Manifest) -> Result<(), String> {
+  let raw = serde_json::to_string_pretty(manifest).map_err(|e| format!("failed to serialize manifest: {e}"))?;
+  fs::write(path, raw).map_err(|e| format!("failed to write manifest to {}: {e}", path.display()))
+}
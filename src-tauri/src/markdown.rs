@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+static FRONTMATTER: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"\A\u{feff}?(?:---|\+\+\+)[ \t]*\r?\n[\s\S]*?\n(?:---|\.\.\.|\+\+\+)[ \t]*(?:\r?\n|\z)").unwrap());
+static HTML_COMMENT: Lazy<Regex> = Lazy::new(|| Regex::new(r"<!--[\s\S]*?-->").unwrap());
+/// `[label]: url "title"` on a line of its own
+static REFERENCE_DEFINITION: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r#"(?m)^ {0,3}\[([^\]]+)\]:[ \t]*<?([^\s>]+)>?(?:[ \t]+(?:"[^"]*"|'[^']*'|\([^)]*\)))?[ \t]*(?:\r?\n|\z)"#).unwrap()
+});
+/// Images, and images wrapped in a link (badges), inline or by reference
+static IMAGE: Lazy<Regex> = Lazy::new(|| {
+  let image = r"!\[[^\]]*\](?:\([^)]*\)|\[[^\]]*\])";
+  Regex::new(&format!(r"\[{image}\](?:\([^)]*\)|\[[^\]]*\])|{image}")).unwrap()
+});
+static HTML_IMAGE: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(?is)<picture\b.*?</picture>|<img\b[^>]*>|<source\b[^>]*>").unwrap());
+/// Wrappers left empty once their images are gone
+static EMPTY_HTML: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(?is)<a\b[^>]*>\s*</a>|<p\b[^>]*>\s*</p>|<div\b[^>]*>\s*</div>|<span\b[^>]*>\s*</span>").unwrap());
+/// `[text][label]` and `[text][]`
+static REFERENCE_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]+)\]\[([^\]]*)\]").unwrap());
+static BLANK_LINES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n(?:[ \t]*\n){2,}").unwrap());
+
+/// Whether `extension` is a Markdown file
+pub fn is_markdown(extension: &str) -> bool {
+  matches!(extension, "md" | "mdx" | "markdown")
+}
+
+/// Opening fence of a fenced code block: its character and length
+fn fence(line: &str) -> Option<(char, usize)> {
+  let trimmed = line.trim_start_matches(' ');
+  if line.len() - trimmed.len() > 3 {
+    return None;
+  }
+  let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+  let len = trimmed.chars().take_while(|c| *c == marker).count();
+  (len >= 3).then_some((marker, len))
+}
+
+/// `text` split into prose and fenced code blocks, in order; `true` marks code
+fn blocks(text: &str) -> Vec<(bool, String)> {
+  let mut blocks: Vec<(bool, String)> = Vec::new();
+  let mut open: Option<(char, usize)> = None;
+  for line in text.split_inclusive('\n') {
+    let is_code = match open {
+      Some((marker, len)) => {
+        let trimmed = line.trim();
+        if trimmed.chars().all(|c| c == marker) && trimmed.len() >= len {
+          open = None;
+        }
+        true
+      }
+      None => {
+        open = fence(line);
+        open.is_some()
+      }
+    };
+    match blocks.last_mut() {
+      Some((code, block)) if *code == is_code => block.push_str(line),
+      _ => blocks.push((is_code, line.to_string())),
+    }
+  }
+  blocks
+}
+
+/// Strip what costs tokens without telling a reader much: frontmatter, HTML
+/// comments, images and badges. Reference link definitions are folded into
+/// the links that use them. Headings, prose and code blocks are kept.
+pub fn slim(text: &str) -> String {
+  let text = FRONTMATTER.replace(text, "");
+
+  let mut blocks = blocks(&text);
+  let mut definitions = HashMap::new();
+  for (_, block) in blocks.iter_mut().filter(|(code, _)| !code) {
+    for captures in REFERENCE_DEFINITION.captures_iter(block) {
+      definitions
+        .entry(captures[1].to_lowercase())
+        .or_insert_with(|| captures[2].to_string());
+    }
+    let slimmed = HTML_COMMENT.replace_all(block, "");
+    let slimmed = REFERENCE_DEFINITION.replace_all(&slimmed, "");
+    let slimmed = IMAGE.replace_all(&slimmed, "");
+    let slimmed = HTML_IMAGE.replace_all(&slimmed, "");
+    *block = EMPTY_HTML.replace_all(&slimmed, "").into_owned();
+  }
+
+  let mut slimmed = String::with_capacity(text.len());
+  for (code, block) in blocks {
+    if code {
+      slimmed.push_str(&block);
+      continue;
+    }
+    let inlined = REFERENCE_LINK.replace_all(&block, |captures: &Captures| {
+      let label = if captures[2].is_empty() { &captures[1] } else { &captures[2] };
+      match definitions.get(&label.to_lowercase()) {
+        Some(url) => format!("[{}]({url})", &captures[1]),
+        None => captures[0].to_string(),
+      }
+    });
+    // Lines that held only badges are left blank, or with trailing spaces
+    let mut prose = String::with_capacity(inlined.len());
+    for line in inlined.split_inclusive('\n') {
+      prose.push_str(line.trim_end());
+      if line.ends_with('\n') {
+        prose.push('\n');
+      }
+    }
+    let prose = BLANK_LINES.replace_all(&prose, "\n\n");
+    slimmed.push_str(if slimmed.is_empty() { prose.trim_start_matches('\n') } else { &prose });
+  }
+  slimmed
+}
@@ -0,0 +1,48 @@
+/// Markers kept by default: notes, safety arguments and tool directives that
+/// tell a reader something the code does not
+pub const DEFAULT_MARKERS: &[&str] = &["TODO", "FIXME", "HACK", "SAFETY", "eslint-disable", "pragma"];
+
+/// How comment removal treats marked comments
+#[derive(Clone, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct MarkerOptions {
+  /// Keep comments containing one of `comment_markers`
+  pub keep_marked_comments: bool,
+  /// Markers to look for; `DEFAULT_MARKERS` when unset or empty
+  pub comment_markers: Option<Vec<String>>,
+}
+
+impl MarkerOptions {
+  pub fn markers(&self) -> KeepMarkers {
+    if !self.keep_marked_comments {
+      return KeepMarkers::default();
+    }
+    match &self.comment_markers {
+      Some(markers) if markers.iter().any(|m| !m.trim().is_empty()) => KeepMarkers(
+        markers
+          .iter()
+          .map(|m| m.trim().to_string())
+          .filter(|m| !m.is_empty())
+          .collect(),
+      ),
+      _ => KeepMarkers(DEFAULT_MARKERS.iter().map(|m| m.to_string()).collect()),
+    }
+  }
+}
+
+/// Comments containing any of these markers survive comment removal; with
+/// none, every comment is removed
+#[derive(Clone, Default)]
+pub struct KeepMarkers(Vec<String>);
+
+impl KeepMarkers {
+  /// Whether `comment` carries a marker and should be kept
+  pub fn keeps(&self, comment: &str) -> bool {
+    self.0.iter().any(|marker| comment.contains(marker.as_str()))
+  }
+
+  /// Whether every comment is removed
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+}
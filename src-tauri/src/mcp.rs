@@ -0,0 +1,265 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::combined::{self, CombinedOptions};
+use crate::fit::{self, FitStrategy};
+use crate::scan::{self, ScanFilters};
+use crate::settings::AppSettings;
+use crate::store::FileStore;
+use crate::tokens::{self, TokenModel, TOKENIZER};
+
+/// Protocol revision answered when the client does not name one
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+fn tool_list() -> Value {
+  json!([
+    {
+      "name": "read_repository",
+      "description": "Read the text files of a local file or folder, process them (strip comments, minify or outline) and return them as one document with a header per file. With max_tokens, files closest to the root are kept until the budget is spent.",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "path": { "type": "string", "description": "Absolute path of the file or folder" },
+          "mode": {
+            "type": "string",
+            "description": "raw, remove-comments, remove-doc-comments, remove-inline-comments, minify, minify-safe, minify-conservative, minify-aggressive (renames identifiers in JavaScript only) or outline",
+            "default": "minify"
+          },
+          "max_tokens": { "type": "integer", "minimum": 1, "description": "Token budget for the files' contents" },
+          "include_globs": { "type": "array", "items": { "type": "string" }, "description": "Only read files matching one of these globs (src/**/*.rs)" },
+          "exclude_globs": { "type": "array", "items": { "type": "string" }, "description": "Skip files and folders matching one of these globs" },
+          "format": { "type": "string", "enum": ["plain", "xml", "markdown"], "default": "plain" }
+        },
+        "required": ["path"]
+      }
+    },
+    {
+      "name": "count_tokens",
+      "description": "Count the tokens of a text for a model (an OpenAI encoding by default).",
+      "inputSchema": {
+        "type": "object",
+        "properties": {
+          "text": { "type": "string" },
+          "model": { "type": "string", "description": "Model or encoding name, like gpt-4o, claude-sonnet-4 or cl100k_base" }
+        },
+        "required": ["text"]
+      }
+    }
+  ])
+}
+
+#[derive(serde::Deserialize)]
+struct ReadRepositoryArgs {
+  path: String,
+  #[serde(default = "default_mode")]
+  mode: String,
+  max_tokens: Option<usize>,
+  include_globs: Option<Vec<String>>,
+  exclude_globs: Option<Vec<String>>,
+  #[serde(default)]
+  format: combined::CombinedFormat,
+}
+
+fn default_mode() -> String {
+  "minify".to_string()
+}
+
+#[derive(serde::Deserialize)]
+struct CountTokensArgs {
+  text: String,
+  model: Option<String>,
+}
+
+/// Load, process, fit and merge the files under `args.path`, like a drop
+/// followed by an export in the app
+fn read_repository(args: ReadRepositoryArgs, settings: &AppSettings) -> Result<String, String> {
+  let encoder = TOKENIZER.as_ref().map_err(|e| e.clone())?;
+  let options = settings.scan_options().with_filters(ScanFilters {
+    include_globs: args.include_globs,
+    exclude_globs: args.exclude_globs,
+    ..ScanFilters::default()
+  })?;
+  let root = Path::new(&args.path);
+  if !root.exists() {
+    return Err(format!("{} does not exist", args.path));
+  }
+
+  let (mut files, skipped, warning) = scan::read_path_guarded(root, &options);
+  let root_name = scan::root_names(std::slice::from_ref(&args.path)).remove(0);
+  files.iter_mut().for_each(|file| file.set_root(root, &root_name));
+
+  let store = FileStore::default();
+  let loaded = store.load(files);
+  let ids: Vec<String> = loaded.iter().map(|file| file.id.clone()).collect();
+  for file in loaded {
+    let Some(source) = store.source(&file.id) else {
+      continue;
+    };
+    let extension = Path::new(&file.name).extension().map_or_else(String::new, |ext| ext.to_string_lossy().to_string());
+    let processed = crate::process_text(source.content.to_string(), &args.mode, &extension, Default::default());
+    store.insert(file.id, file.name, file.path, &processed, None, None);
+  }
+
+  let (mut files, _) = store.get_many(&ids);
+  let mut left_out = 0;
+  if let Some(max_tokens) = args.max_tokens {
+    let selection = fit::select(&files, max_tokens, FitStrategy::PathPriority, encoder);
+    left_out = selection.skipped.len();
+    let (selected, _) = store.get_many(&selection.selected.into_iter().map(|file| file.id).collect::<Vec<_>>());
+    files = selected;
+  }
+
+  let options = CombinedOptions {
+    format: args.format,
+    ..CombinedOptions::default()
+  };
+  let output = combined::output(&files, Vec::new(), &options, None, encoder)?;
+  let mut text = output.content.unwrap_or_default();
+  // Tell the model what it is not seeing
+  let mut notes = Vec::new();
+  if left_out > 0 {
+    notes.push(format!("{left_out} files left out to stay within the token budget"));
+  }
+  if !skipped.is_empty() {
+    notes.push(format!("{} files skipped (binary, too large, hidden or generated)", skipped.len()));
+  }
+  if let Some(warning) = warning {
+    notes.push(warning.message);
+  }
+  if !notes.is_empty() {
+    text.push_str(&format!("\n[{}]\n", notes.join("; ")));
+  }
+  Ok(text)
+}
+
+fn call_tool(params: &Value, settings: &AppSettings) -> Result<Value, (i64, String)> {
+  let name = params["name"].as_str().ok_or((INVALID_PARAMS, "missing tool name".to_string()))?;
+  let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+  let invalid = |e: serde_json::Error| (INVALID_PARAMS, format!("invalid arguments for {name}: {e}"));
+
+  let result = match name {
+    "read_repository" => read_repository(serde_json::from_value(arguments).map_err(invalid)?, settings),
+    "count_tokens" => {
+      let args: CountTokensArgs = serde_json::from_value(arguments).map_err(invalid)?;
+      let model = TokenModel::parse(args.model.as_deref(), settings);
+      tokens::count(&model, &args.text, settings, false).map(|count| count.to_string())
+    }
+    other => return Err((INVALID_PARAMS, format!("unknown tool {other}"))),
+  };
+
+  // Tool failures are results the model can read, not protocol errors
+  Ok(match result {
+    Ok(text) => json!({ "content": [{ "type": "text", "text": text }], "isError": false }),
+    Err(message) => json!({ "content": [{ "type": "text", "text": message }], "isError": true }),
+  })
+}
+
+/// The response to one JSON-RPC message, or `None` for a notification
+fn handle(message: &Value, settings: &AppSettings) -> Option<Value> {
+  let id = message.get("id")?.clone();
+  let params = message.get("params").cloned().unwrap_or(Value::Null);
+  let result = match message["method"].as_str().unwrap_or_default() {
+    "initialize" => Ok(json!({
+      "protocolVersion": params["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION),
+      "capabilities": { "tools": {} },
+      "serverInfo": { "name": "textractor", "version": env!("CARGO_PKG_VERSION") }
+    })),
+    "ping" => Ok(json!({})),
+    "tools/list" => Ok(json!({ "tools": tool_list() })),
+    "tools/call" => call_tool(&params, settings),
+    other => Err((METHOD_NOT_FOUND, format!("unknown method {other}"))),
+  };
+
+  Some(match result {
+    Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+    Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+  })
+}
+
+/// Serve the Model Context Protocol over stdin and stdout, one JSON-RPC
+/// message per line, until stdin closes. Settings are the defaults, as the
+/// app's configuration is only known once Tauri runs.
+pub fn serve() {
+  let settings = AppSettings::default();
+  let stdin = std::io::stdin();
+  let mut stdout = std::io::stdout();
+
+  for line in stdin.lock().lines() {
+    let Ok(line) = line else {
+      break;
+    };
+    if line.trim().is_empty() {
+      continue;
+    }
+    let response = match serde_json::from_str::<Value>(&line) {
+      Ok(message) => handle(&message, &settings),
+      Err(e) => Some(json!({
+        "jsonrpc": "2.0",
+        "id": null,
+        "error": { "code": PARSE_ERROR, "message": format!("invalid JSON: {e}") }
+      })),
+    };
+    if let Some(response) = response {
+      if writeln!(stdout, "{response}").and_then(|_| stdout.flush()).is_err() {
+        break;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn request(method: &str, params: Value) -> Value {
+    let message = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+    handle(&message, &AppSettings::default()).expect("a response")
+  }
+
+  #[test]
+  fn initialize_answers_the_client_protocol_version() {
+    let response = request("initialize", json!({ "protocolVersion": "2025-03-26" }));
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["result"]["protocolVersion"], "2025-03-26");
+    assert_eq!(request("initialize", json!({}))["result"]["protocolVersion"], PROTOCOL_VERSION);
+  }
+
+  #[test]
+  fn tools_are_listed() {
+    let response = request("tools/list", Value::Null);
+    let names: Vec<&str> = response["result"]["tools"]
+      .as_array()
+      .expect("tool array")
+      .iter()
+      .filter_map(|tool| tool["name"].as_str())
+      .collect();
+    assert_eq!(names, ["read_repository", "count_tokens"]);
+  }
+
+  #[test]
+  fn count_tokens_returns_a_text_result() {
+    let response = request("tools/call", json!({ "name": "count_tokens", "arguments": { "text": "hello world" } }));
+    assert_eq!(response["result"]["isError"], false);
+    let count: usize = response["result"]["content"][0]["text"].as_str().and_then(|text| text.parse().ok()).expect("a count");
+    assert!(count > 0);
+  }
+
+  #[test]
+  fn errors_use_json_rpc_codes() {
+    assert_eq!(request("resources/list", Value::Null)["error"]["code"], METHOD_NOT_FOUND);
+    assert_eq!(request("tools/call", json!({ "name": "nope" }))["error"]["code"], INVALID_PARAMS);
+  }
+
+  #[test]
+  fn notifications_get_no_response() {
+    let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+    assert!(handle(&notification, &AppSettings::default()).is_none());
+  }
+}
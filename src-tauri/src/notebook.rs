@@ -0,0 +1,137 @@
+use serde_json::Value;
+
+/// Kernel languages by name, with the extension processing knows them by
+const LANGUAGES: &[(&str, &str)] = &[
+  ("python", "py"),
+  ("r", "r"),
+  ("julia", "jl"),
+  ("javascript", "js"),
+  ("typescript", "ts"),
+  ("rust", "rs"),
+  ("c++", "cpp"),
+  ("c", "c"),
+  ("java", "java"),
+  ("scala", "scala"),
+  ("kotlin", "kt"),
+  ("go", "go"),
+  ("c#", "cs"),
+  ("bash", "sh"),
+  ("ruby", "rb"),
+];
+
+/// Whether a file is a Jupyter notebook
+pub fn is_notebook(extension: &str) -> bool {
+  extension.eq_ignore_ascii_case("ipynb")
+}
+
+/// Extension of the language the notebook's kernel runs, Python when unsaid
+fn language(notebook: &Value) -> String {
+  let info = &notebook["metadata"]["language_info"];
+  if let Some(ext) = info["file_extension"].as_str().map(|e| e.trim_start_matches('.')) {
+    if !ext.is_empty() {
+      return ext.to_lowercase();
+    }
+  }
+  let name = info["name"]
+    .as_str()
+    .or_else(|| notebook["metadata"]["kernelspec"]["language"].as_str())
+    .unwrap_or("python")
+    .to_lowercase();
+  LANGUAGES
+    .iter()
+    .find(|(language, _)| *language == name)
+    .map_or_else(|| "py".to_string(), |(_, ext)| ext.to_string())
+}
+
+fn line_comment(extension: &str) -> &'static str {
+  match extension {
+    "js" | "ts" | "rs" | "cpp" | "c" | "java" | "scala" | "kt" | "go" | "cs" => "//",
+    _ => "#",
+  }
+}
+
+/// Cell sources and outputs are a string or a list of lines
+fn text(value: &Value) -> String {
+  match value {
+    Value::String(text) => text.clone(),
+    Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+    _ => String::new(),
+  }
+}
+
+/// Text of the printed and returned outputs of a code cell; images and
+/// rich displays are left out
+fn outputs(cell: &Value) -> String {
+  let mut out = String::new();
+  for output in cell["outputs"].as_array().into_iter().flatten() {
+    let part = match output["output_type"].as_str() {
+      Some("stream") => text(&output["text"]),
+      Some("execute_result") | Some("display_data") => text(&output["data"]["text/plain"]),
+      Some("error") => format!(
+        "{}: {}",
+        output["ename"].as_str().unwrap_or_default(),
+        output["evalue"].as_str().unwrap_or_default()
+      ),
+      _ => String::new(),
+    };
+    out.push_str(part.trim_end_matches('\n'));
+    if !part.is_empty() {
+      out.push('\n');
+    }
+  }
+  out
+}
+
+fn push_commented(script: &mut String, comment: &str, text: &str) {
+  for line in text.trim_end().lines() {
+    script.push_str(comment);
+    if !line.is_empty() {
+      script.push(' ');
+      script.push_str(line);
+    }
+    script.push('\n');
+  }
+}
+
+/// Rebuild a notebook as a script in its kernel's language, in the `# %%`
+/// cell format editors understand: code cells as code, markdown and raw
+/// cells as comments. Cell outputs are kept as comments under their cell
+/// only with `include_outputs`, since they are often the bulk of the file.
+/// Returns the script and its extension, or `None` when `content` is not a
+/// notebook.
+pub fn to_script(content: &str, include_outputs: bool) -> Option<(String, String)> {
+  let notebook: Value = serde_json::from_str(content).ok()?;
+  let cells = notebook["cells"].as_array()?;
+  let extension = language(&notebook);
+  let comment = line_comment(&extension);
+
+  let mut script = String::new();
+  for cell in cells {
+    let Some(kind) = cell["cell_type"].as_str() else {
+      continue;
+    };
+    let source = text(&cell["source"]);
+    if !script.is_empty() {
+      script.push('\n');
+    }
+    match kind {
+      "code" => {
+        script.push_str(&format!("{comment} %%\n"));
+        script.push_str(source.trim_end());
+        script.push('\n');
+        if include_outputs {
+          let out = outputs(cell);
+          if !out.is_empty() {
+            script.push_str(&format!("{comment} Output:\n"));
+            push_commented(&mut script, comment, &out);
+          }
+        }
+      }
+      _ => {
+        script.push_str(&format!("{comment} %% [{kind}]\n"));
+        push_commented(&mut script, comment, &source);
+      }
+    }
+  }
+  Some((script, extension))
+}
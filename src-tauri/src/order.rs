@@ -0,0 +1,156 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tiktoken_rs::CoreBPE;
+
+use crate::store::StoredFile;
+
+/// Order of the files in a combined document
+#[derive(Clone, Default, serde::Deserialize)]
+#[serde(tag = "by", rename_all = "kebab-case")]
+pub enum FileOrder {
+  /// As requested
+  #[default]
+  Given,
+  /// By path, byte by byte
+  Path,
+  /// Largest first
+  Size,
+  /// Most tokens first
+  Tokens,
+  /// Files before the files that import them, so definitions come first;
+  /// files in an import cycle follow in path order
+  Dependencies,
+  /// These ids first, in this order, then the rest as requested
+  Explicit { ids: Vec<String> },
+}
+
+/// Relative module paths a file imports: `import ... from "./x"`,
+/// `require("../x")`, `#include "x.h"`, `from .x import y`, `mod x;`
+static IMPORT: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r#"(?m)(?:\bfrom\s+|\bimport\s+|\brequire\(\s*)["'](\.{1,2}/[^"'\n]+)["']|^\s*#\s*include\s+"([^"\n]+)"|^\s*from\s+(\.+[\w.]*)\s+import\b|^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)\s*;"#,
+  )
+  .expect("valid regex")
+});
+
+/// `path` with `/` separators and without its extension
+fn stem(path: &str) -> String {
+  let path = path.replace('\\', "/");
+  match path.rsplit_once('.') {
+    Some((stem, ext)) if !ext.contains('/') && !stem.ends_with('/') && !stem.is_empty() => stem.to_string(),
+    _ => path,
+  }
+}
+
+/// `dir` joined with `relative`, with `.` and `..` resolved
+fn join(dir: &str, relative: &str) -> String {
+  let mut parts: Vec<&str> = dir.split('/').filter(|part| !part.is_empty()).collect();
+  for part in relative.split('/') {
+    match part {
+      "" | "." => {}
+      ".." => {
+        parts.pop();
+      }
+      part => parts.push(part),
+    }
+  }
+  let joined = parts.join("/");
+  if dir.starts_with('/') {
+    format!("/{joined}")
+  } else {
+    joined
+  }
+}
+
+/// Stems the imports of `file` may refer to, relative to its directory
+fn imported_stems(file: &StoredFile) -> Vec<String> {
+  let path = file.label().replace('\\', "/");
+  let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+  IMPORT
+    .captures_iter(&file.content)
+    .filter_map(|captures| {
+      if let Some(relative) = captures.get(1).or_else(|| captures.get(2)) {
+        return Some(stem(&join(dir, relative.as_str())));
+      }
+      if let Some(module) = captures.get(3) {
+        // `.` is this package, each further dot one level up
+        let module = module.as_str();
+        let dots = module.len() - module.trim_start_matches('.').len();
+        let relative = format!("{}{}", "../".repeat(dots - 1), module[dots..].replace('.', "/"));
+        return Some(join(dir, &relative));
+      }
+      captures.get(4).map(|name| join(dir, name.as_str()))
+    })
+    .collect()
+}
+
+/// Indices of `files` in dependency order
+fn topological(files: &[Arc<StoredFile>]) -> Vec<usize> {
+  // A module is found by its own stem or, for `index.js`, `__init__.py`
+  // and `mod.rs`, by its directory
+  let mut by_stem: HashMap<String, usize> = HashMap::new();
+  for (index, file) in files.iter().enumerate() {
+    let stem = stem(file.label());
+    for module in ["index", "__init__", "mod"] {
+      if let Some(dir) = stem.strip_suffix(&format!("/{module}")) {
+        by_stem.entry(dir.to_string()).or_insert(index);
+      }
+    }
+    by_stem.insert(stem, index);
+  }
+
+  let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); files.len()];
+  let mut pending = vec![0; files.len()];
+  for (index, file) in files.iter().enumerate() {
+    let imports: BTreeSet<usize> = imported_stems(file)
+      .iter()
+      .filter_map(|stem| by_stem.get(stem).copied())
+      .filter(|&imported| imported != index)
+      .collect();
+    pending[index] = imports.len();
+    for imported in imports {
+      dependents[imported].push(index);
+    }
+  }
+
+  // Kahn's algorithm, taking the ready file first in path order
+  let label = |index: usize| files[index].label();
+  let mut ready: BTreeSet<(&str, usize)> = (0..files.len()).filter(|&i| pending[i] == 0).map(|i| (label(i), i)).collect();
+  let mut order = Vec::with_capacity(files.len());
+  while let Some((_, index)) = ready.pop_first() {
+    order.push(index);
+    for &dependent in &dependents[index] {
+      pending[dependent] -= 1;
+      if pending[dependent] == 0 {
+        ready.insert((label(dependent), dependent));
+      }
+    }
+  }
+  let mut cycles: Vec<usize> = (0..files.len()).filter(|&i| pending[i] > 0).collect();
+  cycles.sort_by_key(|&i| label(i));
+  order.extend(cycles);
+  order
+}
+
+/// `files` in `order`; ties keep their requested order
+pub fn sort(files: &[Arc<StoredFile>], order: &FileOrder, encoder: &CoreBPE) -> Vec<Arc<StoredFile>> {
+  let mut sorted = files.to_vec();
+  match order {
+    FileOrder::Given => {}
+    FileOrder::Path => sorted.sort_by(|a, b| a.label().cmp(b.label())),
+    FileOrder::Size => sorted.sort_by_key(|file| Reverse(file.content.len())),
+    FileOrder::Tokens => sorted.sort_by_cached_key(|file| Reverse(file.tokens(encoder))),
+    FileOrder::Dependencies => {
+      sorted = topological(files).into_iter().map(|i| Arc::clone(&files[i])).collect();
+    }
+    FileOrder::Explicit { ids } => {
+      let rank: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+      sorted.sort_by_key(|file| rank.get(file.id.as_str()).copied().unwrap_or(usize::MAX));
+    }
+  }
+  sorted
+}
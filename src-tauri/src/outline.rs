@@ -0,0 +1,441 @@
+use tree_sitter::Node;
+
+use crate::scope::is_doc_comment;
+use crate::syntax_tree;
+
+/// How a language's syntax tree reduces to an outline
+struct Grammar {
+  /// Items kept verbatim, apart from the bodies of functions inside them:
+  /// imports, type definitions, constants, declarations
+  keep: &'static [&'static str],
+  /// Items whose body is elided, leaving the signature
+  functions: &'static [&'static str],
+  /// Items whose `body` is outlined item by item: classes, modules
+  containers: &'static [&'static str],
+  /// Items kept through the declaration in their field of this name, or
+  /// verbatim when they have none (`export ...`)
+  wrappers: &'static [(&'static str, &'static str)],
+  /// Expression statements kept when their expression is of one of these kinds
+  expressions: &'static [&'static str],
+  /// Plain comments directly above a kept item document it (Go, C)
+  adjacent_docs: bool,
+}
+
+const RUST: Grammar = Grammar {
+  keep: &[
+    "use_declaration",
+    "extern_crate_declaration",
+    "struct_item",
+    "enum_item",
+    "union_item",
+    "type_item",
+    "const_item",
+    "static_item",
+    "attribute_item",
+    "inner_attribute_item",
+    "function_signature_item",
+    "associated_type",
+    "macro_definition",
+    "foreign_mod_item",
+    "impl_item",
+    "trait_item",
+  ],
+  functions: &["function_item", "closure_expression"],
+  containers: &["mod_item"],
+  wrappers: &[],
+  expressions: &[],
+  adjacent_docs: false,
+};
+
+const PYTHON: Grammar = Grammar {
+  keep: &[
+    "import_statement",
+    "import_from_statement",
+    "future_import_statement",
+    "decorated_definition",
+    "type_alias_statement",
+  ],
+  functions: &["function_definition"],
+  containers: &["class_definition"],
+  wrappers: &[],
+  // Constants, class attributes and docstrings
+  expressions: &["assignment", "string"],
+  adjacent_docs: false,
+};
+
+const JS_KEEP: &[&str] = &[
+  "import_statement",
+  "lexical_declaration",
+  "variable_declaration",
+  "class_declaration",
+  "class",
+  "field_definition",
+  "public_field_definition",
+  "interface_declaration",
+  "type_alias_declaration",
+  "enum_declaration",
+  "abstract_class_declaration",
+  "ambient_declaration",
+  "function_signature",
+  "import_alias",
+];
+
+const JS_FUNCTIONS: &[&str] = &[
+  "function_declaration",
+  "generator_function_declaration",
+  "function_expression",
+  "function",
+  "generator_function",
+  "arrow_function",
+  "method_definition",
+];
+
+const JAVASCRIPT: Grammar = Grammar {
+  keep: JS_KEEP,
+  functions: JS_FUNCTIONS,
+  containers: &[],
+  wrappers: &[("export_statement", "declaration")],
+  expressions: &[],
+  adjacent_docs: false,
+};
+
+const TYPESCRIPT: Grammar = Grammar {
+  containers: &["internal_module", "module"],
+  ..JAVASCRIPT
+};
+
+const GO: Grammar = Grammar {
+  keep: &[
+    "package_clause",
+    "import_declaration",
+    "type_declaration",
+    "const_declaration",
+    "var_declaration",
+  ],
+  functions: &["function_declaration", "method_declaration", "func_literal"],
+  containers: &[],
+  wrappers: &[],
+  expressions: &[],
+  adjacent_docs: true,
+};
+
+const JAVA: Grammar = Grammar {
+  keep: &[
+    "package_declaration",
+    "import_declaration",
+    "class_declaration",
+    "interface_declaration",
+    "enum_declaration",
+    "record_declaration",
+    "annotation_type_declaration",
+    "module_declaration",
+  ],
+  functions: &[
+    "method_declaration",
+    "constructor_declaration",
+    "compact_constructor_declaration",
+    "lambda_expression",
+  ],
+  containers: &[],
+  wrappers: &[],
+  expressions: &[],
+  adjacent_docs: false,
+};
+
+const C: Grammar = Grammar {
+  keep: &[
+    "preproc_include",
+    "preproc_def",
+    "preproc_function_def",
+    "preproc_call",
+    "preproc_if",
+    "preproc_ifdef",
+    "type_definition",
+    "struct_specifier",
+    "union_specifier",
+    "enum_specifier",
+    "declaration",
+    "linkage_specification",
+  ],
+  functions: &["function_definition"],
+  containers: &[],
+  wrappers: &[],
+  expressions: &[],
+  adjacent_docs: true,
+};
+
+const CPP: Grammar = Grammar {
+  keep: &[
+    "preproc_include",
+    "preproc_def",
+    "preproc_function_def",
+    "preproc_call",
+    "preproc_if",
+    "preproc_ifdef",
+    "type_definition",
+    "struct_specifier",
+    "union_specifier",
+    "enum_specifier",
+    "class_specifier",
+    "declaration",
+    "linkage_specification",
+    "template_declaration",
+    "using_declaration",
+    "alias_declaration",
+    "namespace_alias_definition",
+    "concept_definition",
+  ],
+  functions: &["function_definition", "lambda_expression"],
+  containers: &["namespace_definition"],
+  ..C
+};
+
+fn grammar_for(ext: &str) -> Option<&'static Grammar> {
+  Some(match ext {
+    "rs" => &RUST,
+    "py" | "pyw" | "pyi" => &PYTHON,
+    "js" | "mjs" | "cjs" | "jsx" => &JAVASCRIPT,
+    "ts" | "mts" | "cts" | "tsx" => &TYPESCRIPT,
+    "go" => &GO,
+    "java" => &JAVA,
+    "c" | "h" => &C,
+    "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => &CPP,
+    _ => return None,
+  })
+}
+
+/// Replacements of byte ranges of the source, in any order
+struct Edits<'a> {
+  code: &'a str,
+  grammar: &'static Grammar,
+  edits: Vec<(usize, usize, String)>,
+}
+
+impl Edits<'_> {
+  fn text(&self, node: Node) -> &str {
+    &self.code[node.byte_range()]
+  }
+
+  /// Remove `node` together with its line when nothing else is on it
+  fn remove(&mut self, node: Node) {
+    let line_start = self.code[..node.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+    let start = if self.code[line_start..node.start_byte()].trim().is_empty() {
+      line_start
+    } else {
+      node.start_byte()
+    };
+    let rest = &self.code[node.end_byte()..];
+    let end = match rest.find('\n') {
+      Some(i) if rest[..i].trim().is_empty() => node.end_byte() + i + 1,
+      None if rest.trim().is_empty() => self.code.len(),
+      _ => node.end_byte(),
+    };
+    self.edits.push((start, end, String::new()));
+  }
+
+  /// Outline the items of `body`; `false` when none of them is kept
+  fn items(&mut self, body: Node) -> bool {
+    let mut kept = false;
+    let mut cursor = body.walk();
+    let children: Vec<Node> = body.named_children(&mut cursor).collect();
+    // Whether each comment runs, line after line, into a kept item
+    let mut documents = vec![false; children.len()];
+    for i in (0..children.len().saturating_sub(1)).rev() {
+      let (child, next) = (children[i], children[i + 1]);
+      documents[i] = is_comment(child)
+        && next.start_position().row <= child.end_position().row + 1
+        && if is_comment(next) { documents[i + 1] } else { self.keeps(next) };
+    }
+    for (i, &child) in children.iter().enumerate() {
+      if is_comment(child) {
+        if is_doc_comment(self.text(child)) || (self.grammar.adjacent_docs && documents[i]) {
+          kept = true;
+        } else {
+          self.remove(child);
+        }
+        continue;
+      }
+      if self.keeps(child) {
+        self.item(child);
+        kept = true;
+      } else {
+        self.remove(child);
+      }
+    }
+    kept
+  }
+
+  /// Whether the outline keeps `node`, an item of a file, class or module
+  fn keeps(&self, node: Node) -> bool {
+    let grammar = self.grammar;
+    let kind = node.kind();
+    if kind == "expression_statement" {
+      return node.named_child(0).is_some_and(|expr| grammar.expressions.contains(&expr.kind()));
+    }
+    [grammar.keep, grammar.functions, grammar.containers]
+      .iter()
+      .any(|kinds| kinds.contains(&kind))
+      || grammar.wrappers.iter().any(|(wrapper, _)| *wrapper == kind)
+  }
+
+  /// Outline a kept item
+  fn item(&mut self, node: Node) {
+    let kind = node.kind();
+    if self.grammar.functions.contains(&kind) {
+      return self.function(node);
+    }
+    if self.grammar.containers.contains(&kind) {
+      if let Some(body) = node.child_by_field_name("body") {
+        let before = self.edits.len();
+        // An indented block cannot be empty
+        if !self.items(body) && !self.text(body).starts_with('{') {
+          self.edits.truncate(before);
+          self.edits.push((body.start_byte(), body.end_byte(), "...".to_string()));
+        }
+        return;
+      }
+    }
+    let wrapped = self.grammar.wrappers.iter().find(|(wrapper, _)| *wrapper == kind);
+    match wrapped.and_then(|(_, field)| node.child_by_field_name(field)) {
+      Some(declaration) => self.item(declaration),
+      None => self.nested(node),
+    }
+  }
+
+  /// Elide the bodies of the functions anywhere inside `node`
+  fn nested(&mut self, node: Node) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+      if self.grammar.functions.contains(&child.kind()) {
+        self.function(child);
+      } else if self.grammar.containers.contains(&child.kind()) {
+        self.item(child);
+      } else {
+        self.nested(child);
+      }
+    }
+  }
+
+  /// Replace the body of a function with a placeholder, keeping a Python
+  /// docstring
+  fn function(&mut self, node: Node) {
+    let Some(body) = node.child_by_field_name("body") else {
+      return;
+    };
+    if self.text(body).starts_with('{') {
+      self.edits.push((body.start_byte(), body.end_byte(), "{ ... }".to_string()));
+      return;
+    }
+    // Expression bodies (`x => x + 1`) are as short as a signature
+    if body.kind() != "block" {
+      return;
+    }
+    let docstring = body
+      .named_child(0)
+      .filter(|first| first.kind() == "expression_statement")
+      .filter(|first| first.named_child(0).is_some_and(|expr| expr.kind() == "string"));
+    match docstring {
+      Some(docstring) => {
+        let line_start = self.code[..docstring.start_byte()].rfind('\n').map_or(0, |i| i + 1);
+        let indent = &self.code[line_start..docstring.start_byte()];
+        let indent = if indent.trim().is_empty() { indent } else { "" };
+        self.edits.push((docstring.end_byte(), body.end_byte(), format!("\n{indent}...")));
+      }
+      None => self.edits.push((body.start_byte(), body.end_byte(), "...".to_string())),
+    }
+  }
+
+  fn apply(mut self) -> String {
+    self.edits.sort_by_key(|&(start, _, _)| start);
+    let mut out = String::with_capacity(self.code.len());
+    let mut copied = 0;
+    for (start, end, replacement) in self.edits {
+      // Edits inside an already elided range have nothing left to change
+      if start < copied {
+        continue;
+      }
+      out.push_str(&self.code[copied..start]);
+      out.push_str(&replacement);
+      copied = end;
+    }
+    out.push_str(&self.code[copied..]);
+    collapse_blank_lines(&out)
+  }
+}
+
+fn is_comment(node: Node) -> bool {
+  node.kind().contains("comment")
+}
+
+/// Runs of blank lines, left where items were dropped, become one
+fn collapse_blank_lines(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  let mut blank = true;
+  for line in text.lines() {
+    let is_blank = line.trim().is_empty();
+    if is_blank && blank {
+      continue;
+    }
+    blank = is_blank;
+    out.push_str(line);
+    out.push('\n');
+  }
+  if !text.ends_with('\n') {
+    out.pop();
+  }
+  out
+}
+
+/// Reduce `code` to its skeleton: imports, type definitions, constants,
+/// function signatures with their bodies elided, and doc comments. `None`
+/// for languages without a grammar or code that fails to parse.
+pub fn outline(code: &str, extension: &str) -> Option<String> {
+  let ext = extension.trim_start_matches('.').to_lowercase();
+  let grammar = grammar_for(&ext)?;
+  let tree = syntax_tree::parse(code, &ext)?;
+  let mut edits = Edits {
+    code,
+    grammar,
+    edits: Vec::new(),
+  };
+  edits.items(tree.root_node());
+  Some(edits.apply())
+}
+
+/// Rows a function body spans, for cutting bodies down line by line
+pub struct BodyRows {
+  /// Row the body's first statement is on, or its `{`
+  pub start: usize,
+  pub end: usize,
+  /// Delimited by braces, whose rows are not statements
+  pub braced: bool,
+}
+
+/// The bodies of the functions in `code`, nested ones included, in source
+/// order. `None` for languages without a grammar or code that fails to parse.
+pub fn function_bodies(code: &str, extension: &str) -> Option<Vec<BodyRows>> {
+  let ext = extension.trim_start_matches('.').to_lowercase();
+  let grammar = grammar_for(&ext)?;
+  let tree = syntax_tree::parse(code, &ext)?;
+  let mut bodies = Vec::new();
+  let mut stack = vec![tree.root_node()];
+  while let Some(node) = stack.pop() {
+    if grammar.functions.contains(&node.kind()) {
+      // Expression bodies (`x => x + 1`) have no lines to cut
+      let body = node
+        .child_by_field_name("body")
+        .filter(|body| code[body.byte_range()].starts_with('{') || body.kind() == "block");
+      if let Some(body) = body {
+        bodies.push(BodyRows {
+          start: body.start_position().row,
+          end: body.end_position().row,
+          braced: code[body.byte_range()].starts_with('{'),
+        });
+      }
+    }
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.named_children(&mut cursor).collect();
+    stack.extend(children.into_iter().rev());
+  }
+  Some(bodies)
+}
@@ -0,0 +1,237 @@
+use std::path::Path;
+
+use tiktoken_rs::CoreBPE;
+
+use crate::budget::BudgetTracker;
+
+/// A processed file to place in a generated bundle
+#[derive(serde::Deserialize)]
+pub struct OutputFile {
+  pub path: String,
+  pub content: String,
+}
+
+/// How files are laid out in a generated bundle
+#[derive(serde::Deserialize)]
+#[serde(default)]
+pub struct BundleLayout {
+  /// Line written before each file. `{path}`, `{name}`, `{ext}` and `{tokens}`
+  /// are replaced with the file's path, file name, extension and token count.
+  pub header: String,
+  /// Text written between consecutive files
+  pub separator: String,
+  /// Wrap each file's content in a Markdown code fence tagged with its extension
+  pub fence: bool,
+}
+
+impl Default for BundleLayout {
+  fn default() -> Self {
+    Self {
+      header: "/* --- {path} --- */".to_string(),
+      separator: "\n".to_string(),
+      fence: false,
+    }
+  }
+}
+
+#[derive(serde::Serialize)]
+pub struct GeneratedBundle {
+  pub text: String,
+  pub tokens: usize,
+  /// Path of the file that pushed the running total over the session budget
+  pub budget_exceeded_at: Option<String>,
+}
+
+/// A fence longer than any backtick run in `content`, so the content cannot close it early
+pub(crate) fn fence_for(content: &str) -> String {
+  let longest = content
+    .split(|c| c != '`')
+    .map(str::len)
+    .max()
+    .unwrap_or(0);
+  "`".repeat(longest.max(2) + 1)
+}
+
+/// `template` with each `{name}` placeholder `value` knows replaced, in one
+/// pass, so a path or name containing `{...}` is written as it is
+fn fill(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+  let mut out = String::with_capacity(template.len());
+  let mut rest = template;
+  while let Some(start) = rest.find('{') {
+    out.push_str(&rest[..start]);
+    let after = &rest[start + 1..];
+    let filled = after.find('}').and_then(|end| Some((value(&after[..end])?, end)));
+    match filled {
+      Some((filled, end)) => {
+        out.push_str(&filled);
+        rest = &after[end + 1..];
+      }
+      None => {
+        out.push('{');
+        rest = after;
+      }
+    }
+  }
+  out.push_str(rest);
+  out
+}
+
+/// Lay out `files` as one text. With a `budget`, the tokens of each file's
+/// section are added to it as the bundle grows.
+pub fn render(
+  files: &[OutputFile],
+  layout: &BundleLayout,
+  encoder: &CoreBPE,
+  mut budget: Option<&mut BudgetTracker>,
+) -> GeneratedBundle {
+  let mut text = String::new();
+  let mut budget_exceeded_at = None;
+
+  for (index, file) in files.iter().enumerate() {
+    if index > 0 {
+      text.push_str(&layout.separator);
+    }
+    let section_start = text.len();
+
+    let path = Path::new(&file.path);
+    let name = path.file_name().map_or_else(|| file.path.clone(), |n| n.to_string_lossy().into_owned());
+    let ext = path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+
+    if !layout.header.is_empty() {
+      let header = fill(&layout.header, |placeholder| match placeholder {
+        "path" => Some(file.path.clone()),
+        "name" => Some(name.clone()),
+        "ext" => Some(ext.clone()),
+        "tokens" => Some(encoder.encode_ordinary(&file.content).len().to_string()),
+        _ => None,
+      });
+      text.push_str(&header);
+      text.push('\n');
+    }
+
+    let fence = layout.fence.then(|| fence_for(&file.content));
+    if let Some(fence) = &fence {
+      text.push_str(fence);
+      text.push_str(&ext);
+      text.push('\n');
+    }
+    text.push_str(&file.content);
+    if !file.content.ends_with('\n') {
+      text.push('\n');
+    }
+    if let Some(fence) = &fence {
+      text.push_str(fence);
+      text.push('\n');
+    }
+
+    if let Some(tracker) = budget.as_deref_mut() {
+      if tracker.add(encoder.encode_ordinary(&text[section_start..]).len()) {
+        budget_exceeded_at = Some(file.path.clone());
+      }
+    }
+  }
+
+  GeneratedBundle {
+    tokens: encoder.encode_ordinary(&text).len(),
+    text,
+    budget_exceeded_at,
+  }
+}
+
+/// One token-bounded part of a long output
+#[derive(serde::Serialize)]
+pub struct OutputChunk {
+  /// 1-based part number
+  pub part: usize,
+  pub total: usize,
+  /// `header` line followed by the part's content
+  pub content: String,
+  /// Tokens in the part's content, excluding the header
+  pub tokens: usize,
+}
+
+/// Split `content` into parts of at most `chunk_tokens` tokens, each repeating
+/// the last `overlap` tokens of the previous part. Cuts fall on exact token
+/// boundaries, moved forward where needed so no UTF-8 character is split.
+/// `header` may use `{part}` and `{total}`.
+pub fn chunk(content: &str, chunk_tokens: usize, overlap: usize, header: &str, encoder: &CoreBPE) -> Result<Vec<OutputChunk>, String> {
+  if chunk_tokens == 0 {
+    return Err("chunk size must be at least one token".to_string());
+  }
+  if overlap >= chunk_tokens {
+    return Err("overlap must be smaller than the chunk size".to_string());
+  }
+
+  // Byte offset at which each token starts, plus the end of the content
+  let tokens = encoder.encode_ordinary(content);
+  let mut offsets = Vec::with_capacity(tokens.len() + 1);
+  let mut offset = 0;
+  for &token in &tokens {
+    offsets.push(offset);
+    offset += encoder._decode_native(&[token]).len();
+  }
+  offsets.push(content.len());
+  let char_boundary = |mut at: usize| {
+    while !content.is_char_boundary(at) {
+      at += 1;
+    }
+    at
+  };
+
+  let mut ranges = Vec::new();
+  let mut start = 0;
+  loop {
+    let end = (start + chunk_tokens).min(tokens.len());
+    ranges.push((start, end));
+    if end == tokens.len() {
+      break;
+    }
+    start = end - overlap;
+  }
+
+  let total = ranges.len();
+  Ok(ranges
+    .into_iter()
+    .enumerate()
+    .map(|(index, (start, end))| {
+      let text = &content[char_boundary(offsets[start])..char_boundary(offsets[end])];
+      let mut part = String::new();
+      if !header.is_empty() {
+        part.push_str(&fill(header, |placeholder| match placeholder {
+          "part" => Some((index + 1).to_string()),
+          "total" => Some(total.to_string()),
+          _ => None,
+        }));
+        part.push('\n');
+      }
+      part.push_str(text);
+      OutputChunk {
+        part: index + 1,
+        total,
+        content: part,
+        tokens: end - start,
+      }
+    })
+    .collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn placeholders_in_substituted_values_are_kept() {
+    let header = fill("/* {path} ({ext}) {unknown} */", |placeholder| match placeholder {
+      "path" => Some("src/{ext}/a.rs".to_string()),
+      "ext" => Some("rs".to_string()),
+      _ => None,
+    });
+    assert_eq!(header, "/* src/{ext}/a.rs (rs) {unknown} */");
+  }
+
+  #[test]
+  fn unclosed_braces_are_copied() {
+    let part = |placeholder: &str| (placeholder == "part").then(|| "1".to_string());
+    assert_eq!(fill("{ {part}/{total", part), "{ 1/{total");
+  }
+}
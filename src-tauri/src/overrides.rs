@@ -0,0 +1,68 @@
+use globset::{GlobBuilder, GlobMatcher};
+
+/// A processing mode for the files `pattern` matches, in place of the mode
+/// of the run
+#[derive(Clone, serde::Deserialize)]
+pub struct ModeOverride {
+  /// A glob (`*.json`, `docs/**/*.md`) or a bare extension (`md`, `.md`).
+  /// Globs without a `/` match file names, others paths at any depth.
+  pub pattern: String,
+  pub mode: String,
+}
+
+struct Compiled {
+  matcher: GlobMatcher,
+  /// Matched against the whole path rather than the file name
+  on_path: bool,
+  mode: String,
+}
+
+/// `ModeOverride`s compiled for matching, in the order given
+pub struct ModeOverrides(Vec<Compiled>);
+
+fn compile(pattern: &str) -> Result<(GlobMatcher, bool), String> {
+  let is_glob = pattern.contains(['*', '?', '[', '{', '/']);
+  let on_path = pattern.contains('/');
+  let glob = if !is_glob {
+    GlobBuilder::new(&format!("*.{}", pattern.trim_start_matches('.')))
+      .case_insensitive(true)
+      .build()
+  } else if on_path {
+    GlobBuilder::new(&format!("**/{}", pattern.trim_start_matches("./").trim_start_matches('/')))
+      .literal_separator(true)
+      .build()
+  } else {
+    GlobBuilder::new(pattern).build()
+  };
+  let glob = glob.map_err(|e| format!("invalid override pattern {pattern}: {e}"))?;
+  Ok((glob.compile_matcher(), on_path))
+}
+
+impl ModeOverrides {
+  pub fn new(overrides: &[ModeOverride]) -> Result<Self, String> {
+    let compiled = overrides
+      .iter()
+      .filter(|o| !o.pattern.trim().is_empty())
+      .map(|o| {
+        let (matcher, on_path) = compile(o.pattern.trim())?;
+        Ok(Compiled {
+          matcher,
+          on_path,
+          mode: o.mode.trim().to_string(),
+        })
+      })
+      .collect::<Result<_, String>>()?;
+    Ok(Self(compiled))
+  }
+
+  /// Mode of the first override matching the file at `path` named `name`
+  pub fn mode_for(&self, path: &str, name: &str) -> Option<&str> {
+    let path = if path.is_empty() { name } else { path }.replace('\\', "/");
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    self
+      .0
+      .iter()
+      .find(|o| o.matcher.is_match(if o.on_path { path.as_str() } else { file_name }))
+      .map(|o| o.mode.as_str())
+  }
+}
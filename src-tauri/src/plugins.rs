@@ -0,0 +1,254 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use wasmi::{Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// Instructions, roughly, one call into a plugin may execute
+const FUEL: u64 = 1_000_000_000;
+/// Most linear memory a plugin may grow to, in bytes
+const MAX_MEMORY: usize = 512 * 1024 * 1024;
+
+/// A processor for file formats textractor does not know, loaded from a
+/// `.wasm` file in the plugins directory.
+///
+/// Plugins import nothing, so they cannot reach the filesystem, the network
+/// or the clock; a module with imports is rejected. They export:
+///
+/// - `memory`
+/// - `textractor_alloc(len: i32) -> i32`, space for `len` bytes of input
+/// - `textractor_extensions() -> i64`, a comma-separated list of the
+///   extensions handled
+/// - `textractor_process(ptr: i32, len: i32) -> i64`, the UTF-8 text a file
+///   with the content at `ptr` becomes
+///
+/// An `i64` result is a pointer in its high 32 bits and a length in its low 32.
+pub struct Plugin {
+  pub info: PluginInfo,
+  module: Module,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct PluginInfo {
+  /// File name without `.wasm`
+  pub name: String,
+  pub path: String,
+  /// Lowercase extensions, without the dot
+  pub extensions: Vec<String>,
+}
+
+/// A running plugin with fresh memory
+struct Session {
+  store: Store<StoreLimits>,
+  instance: Instance,
+  memory: Memory,
+}
+
+impl Session {
+  fn start(engine: &Engine, module: &Module) -> Result<Self, String> {
+    let mut store = Store::new(engine, StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build());
+    store.limiter(|limits| limits);
+    store.set_fuel(FUEL).map_err(|e| e.to_string())?;
+    let instance = Linker::new(engine)
+      .instantiate(&mut store, module)
+      .and_then(|pre| pre.start(&mut store))
+      .map_err(|e| e.to_string())?;
+    let memory = instance
+      .get_memory(&store, "memory")
+      .ok_or_else(|| "no exported memory".to_string())?;
+    Ok(Self { store, instance, memory })
+  }
+
+  /// The bytes an `i64` result points at. The range is checked against the
+  /// plugin's memory before anything is allocated for it, since the plugin
+  /// picks the length.
+  fn read(&self, packed: i64) -> Result<Vec<u8>, String> {
+    let (ptr, len) = ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize);
+    ptr
+      .checked_add(len)
+      .and_then(|end| self.memory.data(&self.store).get(ptr..end))
+      .map(<[u8]>::to_vec)
+      .ok_or_else(|| format!("result of {len} bytes at {ptr} lies outside the plugin's memory"))
+  }
+
+  fn extensions(&mut self) -> Result<Vec<String>, String> {
+    let packed = self
+      .instance
+      .get_typed_func::<(), i64>(&self.store, "textractor_extensions")
+      .and_then(|f| f.call(&mut self.store, ()))
+      .map_err(|e| e.to_string())?;
+    let list = String::from_utf8(self.read(packed)?).map_err(|_| "extension list is not UTF-8".to_string())?;
+    Ok(
+      list
+        .split(',')
+        .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect(),
+    )
+  }
+
+  fn process(&mut self, content: &str) -> Result<String, String> {
+    let len = i32::try_from(content.len()).map_err(|_| "file too large for a plugin".to_string())?;
+    let alloc = self
+      .instance
+      .get_typed_func::<i32, i32>(&self.store, "textractor_alloc")
+      .map_err(|e| e.to_string())?;
+    let process = self
+      .instance
+      .get_typed_func::<(i32, i32), i64>(&self.store, "textractor_process")
+      .map_err(|e| e.to_string())?;
+
+    let ptr = alloc.call(&mut self.store, len).map_err(|e| e.to_string())?;
+    self
+      .memory
+      .write(&mut self.store, ptr as u32 as usize, content.as_bytes())
+      .map_err(|e| e.to_string())?;
+    let packed = process.call(&mut self.store, (ptr, len)).map_err(|e| e.to_string())?;
+    String::from_utf8(self.read(packed)?).map_err(|_| "output is not UTF-8".to_string())
+  }
+}
+
+impl Plugin {
+  fn load(engine: &Engine, path: &Path) -> Result<Self, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let module = Module::new(engine, &bytes[..]).map_err(|e| e.to_string())?;
+    if let Some(import) = module.imports().next() {
+      return Err(format!("imports {}::{}; plugins may not import anything", import.module(), import.name()));
+    }
+    let extensions = Session::start(engine, &module)?.extensions()?;
+    let name = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().to_string());
+    Ok(Self {
+      info: PluginInfo {
+        name,
+        path: path.to_string_lossy().to_string(),
+        extensions,
+      },
+      module,
+    })
+  }
+
+  /// What a file with `content` becomes. Every call starts the plugin
+  /// afresh, so nothing carries over from one file to the next.
+  pub fn process(&self, content: &str) -> Result<String, String> {
+    Session::start(self.module.engine(), &self.module)?
+      .process(content)
+      .map_err(|e| format!("plugin {} failed: {e}", self.info.name))
+  }
+}
+
+/// The first of `plugins` handling files with extension `ext`
+pub fn for_extension<'a>(plugins: &'a [Arc<Plugin>], ext: &str) -> Option<&'a Arc<Plugin>> {
+  let ext = ext.to_ascii_lowercase();
+  plugins.iter().find(|plugin| plugin.info.extensions.contains(&ext))
+}
+
+/// Result of scanning the plugins directory
+#[derive(serde::Serialize)]
+pub struct PluginList {
+  pub plugins: Vec<PluginInfo>,
+  /// Files that could not be loaded, with the reason
+  pub failed: Vec<String>,
+}
+
+/// Tauri-managed plugins found in `dir`
+pub struct PluginRegistry {
+  dir: Option<PathBuf>,
+  engine: Engine,
+  plugins: Mutex<Vec<Arc<Plugin>>>,
+}
+
+impl PluginRegistry {
+  pub fn load(dir: Option<PathBuf>) -> Self {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let registry = Self {
+      dir,
+      engine: Engine::new(&config),
+      plugins: Mutex::new(Vec::new()),
+    };
+    for failure in registry.reload().failed {
+      log::warn!("Skipping plugin {}", failure);
+    }
+    registry
+  }
+
+  /// Load every `.wasm` file in the plugins directory again, in name order.
+  /// When two plugins claim an extension the first one handles it.
+  pub fn reload(&self) -> PluginList {
+    let mut paths: Vec<PathBuf> = self
+      .dir
+      .as_ref()
+      .and_then(|dir| fs::read_dir(dir).ok())
+      .into_iter()
+      .flatten()
+      .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+      .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wasm")))
+      .collect();
+    paths.sort();
+
+    let mut plugins = Vec::new();
+    let mut failed = Vec::new();
+    for path in paths {
+      match Plugin::load(&self.engine, &path) {
+        Ok(plugin) => plugins.push(Arc::new(plugin)),
+        Err(e) => failed.push(format!("{}: {e}", path.display())),
+      }
+    }
+    let list = PluginList {
+      plugins: plugins.iter().map(|plugin| plugin.info.clone()).collect(),
+      failed,
+    };
+    *self.plugins.lock().unwrap_or_else(|e| e.into_inner()) = plugins;
+    list
+  }
+
+  pub fn list(&self) -> Vec<PluginInfo> {
+    let plugins = self.plugins.lock().unwrap_or_else(|e| e.into_inner());
+    plugins.iter().map(|plugin| plugin.info.clone()).collect()
+  }
+
+  /// The plugins currently loaded
+  pub fn loaded(&self) -> Vec<Arc<Plugin>> {
+    self.plugins.lock().unwrap_or_else(|e| e.into_inner()).clone()
+  }
+
+  /// The plugins directory, created if missing
+  pub fn dir(&self) -> Result<PathBuf, String> {
+    let dir = self.dir.clone().ok_or_else(|| "plugins directory unavailable".to_string())?;
+    fs::create_dir_all(&dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    Ok(dir)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A module exporting one page of `memory` and a `textractor_extensions`
+  /// that claims 4 GiB less one byte at address 0
+  const OUT_OF_RANGE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // magic, version
+    0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7e, // type: () -> i64
+    0x03, 0x02, 0x01, 0x00, // function 0 has type 0
+    0x05, 0x03, 0x01, 0x00, 0x01, // memory of one page
+    0x07, 0x22, 0x02, // two exports
+    0x06, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0x00, //
+    0x15, b't', b'e', b'x', b't', b'r', b'a', b'c', b't', b'o', b'r', b'_', b'e', b'x', b't', b'e', b'n', b's', b'i',
+    b'o', b'n', b's', 0x00, 0x00, //
+    0x0a, 0x0a, 0x01, 0x08, 0x00, 0x42, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x0b, // i64.const 0xffff_ffff
+  ];
+
+  #[test]
+  fn results_outside_plugin_memory_are_rejected() {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let module = Module::new(&engine, OUT_OF_RANGE).expect("valid module");
+    let mut session = Session::start(&engine, &module).expect("module starts");
+
+    let error = session.extensions().expect_err("range is outside memory");
+    assert!(error.contains("outside the plugin's memory"), "{error}");
+    assert_eq!(session.read(4).map(|bytes| bytes.len()), Ok(4));
+    assert!(session.read(i64::from(u32::MAX) << 32).is_err());
+  }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+
+/// Built-in input prices in USD per million tokens, matched by model name
+/// prefix. These are list prices at the time of writing and drift over time;
+/// users can override or extend them with `set_model_price`.
+const INPUT_PRICES: &[(&str, f64)] = &[
+  ("gpt-5-nano", 0.05),
+  ("gpt-5-mini", 0.25),
+  ("gpt-5", 1.25),
+  ("gpt-4.1-nano", 0.10),
+  ("gpt-4.1-mini", 0.40),
+  ("gpt-4.1", 2.00),
+  ("gpt-4o-mini", 0.15),
+  ("gpt-4o", 2.50),
+  ("o4-mini", 1.10),
+  ("o3", 2.00),
+  ("claude-opus-4", 15.00),
+  ("claude-sonnet-4", 3.00),
+  ("claude-haiku-4", 1.00),
+  ("claude-3-7-sonnet", 3.00),
+  ("claude-3-5-sonnet", 3.00),
+  ("claude-3-5-haiku", 0.80),
+  ("gemini-2.5-pro", 1.25),
+  ("gemini-2.5-flash", 0.30),
+  ("gemini-2.0-flash", 0.10),
+];
+
+/// Token count of one file to price
+#[derive(serde::Deserialize)]
+pub struct CostInput {
+  pub id: String,
+  pub tokens: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct FileCost {
+  pub id: String,
+  pub tokens: usize,
+  pub cost_usd: f64,
+}
+
+#[derive(serde::Serialize)]
+pub struct CostEstimate {
+  pub model: String,
+  pub usd_per_million: f64,
+  pub files: Vec<FileCost>,
+  pub total_tokens: usize,
+  pub total_cost_usd: f64,
+}
+
+/// Input price for `model`: the longest matching user override, else the
+/// longest matching built-in entry
+pub fn input_price(model: &str, overrides: &BTreeMap<String, f64>) -> Option<f64> {
+  let model = model.trim().to_lowercase();
+  let longest = |entries: &mut dyn Iterator<Item = (&str, f64)>| {
+    entries
+      .filter(|(prefix, _)| model.starts_with(prefix))
+      .max_by_key(|(prefix, _)| prefix.len())
+      .map(|(_, price)| price)
+  };
+  longest(&mut overrides.iter().map(|(k, v)| (k.as_str(), *v)))
+    .or_else(|| longest(&mut INPUT_PRICES.iter().copied()))
+}
+
+pub fn estimate(model: &str, files: Vec<CostInput>, overrides: &BTreeMap<String, f64>) -> Result<CostEstimate, String> {
+  let usd_per_million = input_price(model, overrides).ok_or_else(|| format!("no price known for model {model}"))?;
+  let cost = |tokens: usize| tokens as f64 * usd_per_million / 1_000_000.0;
+
+  let files: Vec<FileCost> = files
+    .into_iter()
+    .map(|file| FileCost {
+      cost_usd: cost(file.tokens),
+      id: file.id,
+      tokens: file.tokens,
+    })
+    .collect();
+  let total_tokens = files.iter().map(|f| f.tokens).sum();
+
+  Ok(CostEstimate {
+    model: model.to_string(),
+    usd_per_million,
+    files,
+    total_tokens,
+    total_cost_usd: cost(total_tokens),
+  })
+}
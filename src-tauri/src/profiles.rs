@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::combined::CombinedFormat;
+use crate::limits::Limits;
+use crate::scan::ScanFilters;
+
+/// Profile used when none was ever activated
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Preferences applied when a command does not specify them
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Profile {
+  /// Processing mode used when `process_files_with_progress` gets an empty one
+  pub mode: Option<String>,
+  /// Filters for loads that do not bring their own
+  pub filters: ScanFilters,
+  /// Size limits in bytes, over the ones in the app settings
+  pub max_file_size: Option<usize>,
+  pub max_process_size: Option<usize>,
+  /// Model or encoding name token counts use
+  pub tokenizer: Option<String>,
+  /// Layout of combined output
+  pub output_format: Option<CombinedFormat>,
+}
+
+impl Profile {
+  /// `base` with this profile's size limits applied over it
+  pub fn limits(&self, base: Limits) -> Limits {
+    Limits {
+      max_file_size: self.max_file_size.filter(|&n| n > 0).unwrap_or(base.max_file_size),
+      max_process_size: self.max_process_size.filter(|&n| n > 0).unwrap_or(base.max_process_size),
+    }
+  }
+}
+
+/// Contents of `textractor.toml`
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+struct ProfileFile {
+  active: Option<String>,
+  profiles: BTreeMap<String, Profile>,
+}
+
+/// Names of the saved profiles and the active one
+#[derive(serde::Serialize)]
+pub struct ProfileList {
+  pub active: String,
+  pub profiles: Vec<String>,
+}
+
+/// Tauri-managed profiles together with the file they are persisted to
+pub struct ProfileStore {
+  path: Option<PathBuf>,
+  file: Mutex<ProfileFile>,
+}
+
+impl ProfileStore {
+  /// Load profiles from `path`, starting empty when the file is missing or
+  /// unreadable
+  pub fn load(path: Option<PathBuf>) -> Self {
+    let file = path
+      .as_ref()
+      .and_then(|p| fs::read_to_string(p).ok())
+      .and_then(|raw| match toml::from_str(&raw) {
+        Ok(file) => Some(file),
+        Err(e) => {
+          log::warn!("Ignoring invalid profiles file: {}", e);
+          None
+        }
+      })
+      .unwrap_or_default();
+    Self {
+      path,
+      file: Mutex::new(file),
+    }
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, ProfileFile> {
+    self.file.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  pub fn active_name(&self) -> String {
+    self.lock().active.clone().unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+  }
+
+  /// The profile called `name`, or the active one; empty when never saved
+  pub fn get(&self, name: Option<&str>) -> Profile {
+    let name = name.map_or_else(|| self.active_name(), str::to_string);
+    self.lock().profiles.get(&name).cloned().unwrap_or_default()
+  }
+
+  pub fn list(&self) -> ProfileList {
+    ProfileList {
+      active: self.active_name(),
+      profiles: self.lock().profiles.keys().cloned().collect(),
+    }
+  }
+
+  /// Save `profile` as `name`, making it the active one when `activate` is set
+  pub fn set(&self, name: &str, profile: Profile, activate: bool) -> Result<(), String> {
+    let mut file = self.lock();
+    file.profiles.insert(name.to_string(), profile);
+    if activate {
+      file.active = Some(name.to_string());
+    }
+
+    if let Some(path) = &self.path {
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create settings directory: {e}"))?;
+      }
+      let raw = toml::to_string_pretty(&*file).map_err(|e| format!("failed to serialize profiles: {e}"))?;
+      fs::write(path, raw).map_err(|e| format!("failed to save profiles: {e}"))?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,136 @@
+/// An inclusive, 1-based line range; `end` of `None` runs to the end of the file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineRange {
+  pub start: usize,
+  pub end: Option<usize>,
+}
+
+/// Parse a range list such as `100-250,400-450`. A single number selects one
+/// line and an open end (`400-`) runs to the end of the file. Ranges are
+/// returned sorted with overlapping or adjacent ones merged.
+pub fn parse(spec: &str) -> Result<Vec<LineRange>, String> {
+  let ranges = spec
+    .split(',')
+    .map(str::trim)
+    .filter(|part| !part.is_empty())
+    .map(|part| {
+      let invalid = || format!("invalid line range: {part}");
+      let number = |s: &str| s.trim().parse::<usize>().ok().filter(|&n| n > 0).ok_or_else(invalid);
+      let range = match part.split_once('-') {
+        Some((start, end)) if end.trim().is_empty() => LineRange { start: number(start)?, end: None },
+        Some((start, end)) => LineRange { start: number(start)?, end: Some(number(end)?) },
+        None => {
+          let line = number(part)?;
+          LineRange { start: line, end: Some(line) }
+        }
+      };
+      match range.end {
+        Some(end) if end < range.start => Err(invalid()),
+        _ => Ok(range),
+      }
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+
+  if ranges.is_empty() {
+    return Err(format!("no line ranges in: {spec}"));
+  }
+
+  Ok(merge(ranges))
+}
+
+/// Sort ranges and merge overlapping or adjacent ones
+pub fn merge(mut ranges: Vec<LineRange>) -> Vec<LineRange> {
+  ranges.sort_by_key(|r| r.start);
+  let mut merged: Vec<LineRange> = Vec::with_capacity(ranges.len());
+  for range in ranges {
+    match merged.last_mut() {
+      Some(last) if last.end.map_or(true, |end| range.start <= end + 1) => {
+        last.end = match (last.end, range.end) {
+          (Some(a), Some(b)) => Some(a.max(b)),
+          _ => None,
+        };
+      }
+      _ => merged.push(range),
+    }
+  }
+  merged
+}
+
+/// Split a `path:ranges` selector such as `src/server.rs:100-250,400-450`.
+/// Without a range suffix the whole file is selected.
+pub fn parse_selector(selector: &str) -> Result<(String, Option<Vec<LineRange>>), String> {
+  match selector.rsplit_once(':') {
+    // Only treat the suffix as ranges if it looks like one, so `C:\dir\file`
+    // and other paths containing colons still work
+    Some((path, spec)) if !path.is_empty() && !spec.is_empty() && spec.chars().all(|c| c.is_ascii_digit() || matches!(c, '-' | ',' | ' ')) => {
+      Ok((path.to_string(), Some(parse(spec)?)))
+    }
+    _ => Ok((selector.to_string(), None)),
+  }
+}
+
+/// A selected slice of a file
+struct Slice<'a> {
+  start: usize,
+  end: usize,
+  text: &'a str,
+}
+
+/// The slices of `content` covered by `ranges`, clamped to the file length,
+/// together with the file's line count
+fn slices<'a>(content: &'a str, ranges: &[LineRange]) -> (Vec<Slice<'a>>, usize) {
+  // Byte offset at which each line starts, plus the end of the content
+  let mut offsets = vec![0];
+  offsets.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+  if offsets.last() != Some(&content.len()) {
+    offsets.push(content.len());
+  }
+  let line_count = offsets.len() - 1;
+
+  let slices = ranges
+    .iter()
+    .filter(|r| r.start <= line_count)
+    .map(|r| {
+      let end = r.end.map_or(line_count, |e| e.min(line_count));
+      Slice {
+        start: r.start,
+        end,
+        text: &content[offsets[r.start - 1]..offsets[end]],
+      }
+    })
+    .collect();
+  (slices, line_count)
+}
+
+fn omission_marker(first: usize, last: usize) -> String {
+  if first == last {
+    format!("... (line {first} omitted)\n")
+  } else {
+    format!("... (lines {first}-{last} omitted)\n")
+  }
+}
+
+/// Keep only the lines of `content` covered by `ranges`, running `process` on
+/// each slice, with the number of its first line, separately and marking
+/// every gap with an omission line. Markers are added after processing so
+/// comment removal cannot strip them.
+pub fn extract_with(content: &str, ranges: &[LineRange], process: impl Fn(&str, usize) -> String) -> String {
+  let (slices, line_count) = slices(content, ranges);
+  let mut out = String::new();
+  let mut next_line = 1;
+  for slice in slices {
+    if slice.start > next_line {
+      out.push_str(&omission_marker(next_line, slice.start - 1));
+    }
+    let processed = process(slice.text, slice.start);
+    out.push_str(&processed);
+    if !processed.is_empty() && !processed.ends_with('\n') {
+      out.push('\n');
+    }
+    next_line = slice.end + 1;
+  }
+  if next_line <= line_count {
+    out.push_str(&omission_marker(next_line, line_count));
+  }
+  out
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::session::write_json;
+
+/// How many paths the history keeps
+const MAX_RECENT: usize = 20;
+
+/// A file or folder that was loaded
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecentPath {
+  pub path: String,
+  pub is_dir: bool,
+  /// When it was last loaded, in milliseconds since the Unix epoch
+  pub opened_ms: u64,
+}
+
+/// Tauri-managed history of loaded paths, most recent first, persisted to
+/// `path` on every change
+pub struct RecentPaths {
+  path: Option<PathBuf>,
+  entries: Mutex<Vec<RecentPath>>,
+}
+
+impl RecentPaths {
+  pub fn load(path: Option<PathBuf>) -> Self {
+    let entries = path
+      .as_ref()
+      .and_then(|p| fs::read_to_string(p).ok())
+      .and_then(|raw| match serde_json::from_str(&raw) {
+        Ok(entries) => Some(entries),
+        Err(e) => {
+          log::warn!("Ignoring invalid recent paths file: {}", e);
+          None
+        }
+      })
+      .unwrap_or_default();
+
+    Self {
+      path,
+      entries: Mutex::new(entries),
+    }
+  }
+
+  pub fn list(&self) -> Vec<RecentPath> {
+    self.entries.lock().unwrap_or_else(|e| e.into_inner()).clone()
+  }
+
+  /// The path at `index` in `list` order
+  pub fn get(&self, index: usize) -> Option<RecentPath> {
+    self.entries.lock().unwrap_or_else(|e| e.into_inner()).get(index).cloned()
+  }
+
+  /// Move `paths` to the front of the history, in the order given
+  pub fn record(&self, paths: &[String]) -> Result<(), String> {
+    let opened_ms = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map_or(0, |d| d.as_millis() as u64);
+
+    let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+    for path in paths.iter().rev() {
+      entries.retain(|entry| entry.path != *path);
+      entries.insert(
+        0,
+        RecentPath {
+          path: path.clone(),
+          is_dir: std::path::Path::new(path).is_dir(),
+          opened_ms,
+        },
+      );
+    }
+    entries.truncate(MAX_RECENT);
+
+    match &self.path {
+      Some(path) => write_json(path, &*entries),
+      None => Ok(()),
+    }
+  }
+}
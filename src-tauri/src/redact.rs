@@ -0,0 +1,99 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+/// A category of personal data that redaction masks
+#[derive(Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+  Email,
+  Phone,
+  /// IPv4 and IPv6 addresses
+  IpAddress,
+}
+
+/// How many items of each kind were masked in one file
+#[derive(Clone, Copy, Default, serde::Serialize)]
+pub struct RedactionCounts {
+  pub email: usize,
+  pub phone: usize,
+  pub ip_address: usize,
+}
+
+static EMAIL: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b").expect("valid regex")
+});
+static IPV4: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d{1,3}(?:\.\d{1,3}){3}\b").expect("valid regex"));
+static IPV6: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"(?i)(?:\b[0-9a-f]{1,4}|:)(?::[0-9a-f]{0,4}){2,7}(?:\.\d{1,3}){0,3}").expect("valid regex"));
+static PHONE: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\d{2,5}(?:[ .-]\d{2,5}){1,4}").expect("valid regex")
+});
+
+/// Whether `text[start..end]` stands alone rather than continuing a longer
+/// number, version or identifier
+fn stands_alone(text: &str, start: usize, end: usize) -> bool {
+  let before = text[..start].chars().next_back();
+  let after = text[end..].chars().next();
+  let joins = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '.' | ':' | '-'));
+  // A sentence may end right after it
+  let ends_sentence = matches!(after, Some('.' | ':')) && !text[end + 1..].starts_with(char::is_alphanumeric);
+  !joins(before) && (ends_sentence || !joins(after))
+}
+
+fn is_ipv4(text: &str, start: usize, end: usize) -> bool {
+  let port = text[end..].strip_prefix(':').is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+  text[start..end].parse::<Ipv4Addr>().is_ok() && (port || stands_alone(text, start, end))
+}
+
+fn is_ipv6(text: &str, start: usize, end: usize) -> bool {
+  let candidate = &text[start..end];
+  // `a::b` paths in C++ or Rust parse too; addresses carry a digit
+  candidate.contains(|c: char| c.is_ascii_digit())
+    && candidate.parse::<Ipv6Addr>().is_ok()
+    && stands_alone(text, start, end)
+}
+
+fn is_phone(text: &str, start: usize, end: usize) -> bool {
+  let candidate = &text[start..end];
+  let digits = candidate.chars().filter(char::is_ascii_digit).count();
+  // Spaced groups alone are as likely to be a list of numbers, unless they
+  // open with a trunk prefix
+  let formatted = candidate.starts_with(['+', '0']) || candidate.contains(['(', '-']);
+  (10..=15).contains(&digits) && formatted && stands_alone(text, start, end)
+}
+
+/// Replace the matches of `pattern` that `accept` confirms with `mask`
+fn mask(text: &str, pattern: &Regex, mask: &str, accept: fn(&str, usize, usize) -> bool, count: &mut usize) -> String {
+  pattern
+    .replace_all(text, |caps: &Captures| {
+      let found = caps.get(0).expect("whole match");
+      if accept(text, found.start(), found.end()) {
+        *count += 1;
+        mask.to_string()
+      } else {
+        found.as_str().to_string()
+      }
+    })
+    .into_owned()
+}
+
+/// Mask the personal data of the `kinds` asked for in `text`, counting what
+/// was masked
+pub fn redact(text: &str, kinds: &[PiiKind]) -> (String, RedactionCounts) {
+  let mut counts = RedactionCounts::default();
+  let mut working = text.to_string();
+  // Emails first: their domains could pass for addresses or numbers
+  if kinds.contains(&PiiKind::Email) {
+    working = mask(&working, &EMAIL, "[REDACTED_EMAIL]", |_, _, _| true, &mut counts.email);
+  }
+  if kinds.contains(&PiiKind::IpAddress) {
+    working = mask(&working, &IPV6, "[REDACTED_IP]", is_ipv6, &mut counts.ip_address);
+    working = mask(&working, &IPV4, "[REDACTED_IP]", is_ipv4, &mut counts.ip_address);
+  }
+  if kinds.contains(&PiiKind::Phone) {
+    working = mask(&working, &PHONE, "[REDACTED_PHONE]", is_phone, &mut counts.phone);
+  }
+  (working, counts)
+}
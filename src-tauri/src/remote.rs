@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::build::RepoBuilder;
+use git2::{FetchOptions, Repository};
+
+/// A repository cloned into a temporary directory, deleted on drop
+pub struct ClonedRepo {
+  dir: PathBuf,
+}
+
+impl ClonedRepo {
+  pub fn path(&self) -> &Path {
+    &self.dir
+  }
+}
+
+impl Drop for ClonedRepo {
+  fn drop(&mut self) {
+    if let Err(e) = fs::remove_dir_all(&self.dir) {
+      log::warn!("Failed to remove clone at {}: {}", self.dir.display(), e);
+    }
+  }
+}
+
+fn temp_dir() -> PathBuf {
+  let nanos = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map_or(0, |d| d.as_nanos());
+  std::env::temp_dir().join(format!("textractor-clone-{}-{}", std::process::id(), nanos))
+}
+
+fn clone_into(url: &str, dir: &Path, branch: Option<&str>, shallow: bool) -> Result<Repository, git2::Error> {
+  let mut fetch = FetchOptions::new();
+  if shallow {
+    fetch.depth(1);
+  }
+  let mut builder = RepoBuilder::new();
+  builder.fetch_options(fetch);
+  if let Some(branch) = branch {
+    builder.branch(branch);
+  }
+  builder.clone(url, dir)
+}
+
+fn checkout(repo: &Repository, reference: &str) -> Result<(), git2::Error> {
+  let object = repo
+    .revparse_single(reference)
+    .or_else(|_| repo.revparse_single(&format!("origin/{reference}")))?;
+  repo.checkout_tree(&object, Some(git2::build::CheckoutBuilder::new().force()))?;
+  repo.set_head_detached(object.peel_to_commit()?.id())
+}
+
+/// Clone `url` into a fresh temporary directory, checked out at `reference`
+/// (a branch, tag or commit) or the default branch. Only the latest commit is
+/// fetched when the transport allows it and the reference is a branch; tags,
+/// commits and local paths need the full history.
+pub fn shallow_clone(url: &str, reference: Option<&str>) -> Result<ClonedRepo, String> {
+  let cloned = ClonedRepo { dir: temp_dir() };
+  if clone_into(url, cloned.path(), reference, true).is_ok() {
+    return Ok(cloned);
+  }
+
+  let _ = fs::remove_dir_all(cloned.path());
+  let repo =
+    clone_into(url, cloned.path(), None, false).map_err(|e| format!("failed to clone {url}: {}", e.message()))?;
+  if let Some(reference) = reference {
+    checkout(&repo, reference).map_err(|e| format!("unknown ref {reference} in {url}: {}", e.message()))?;
+  }
+  Ok(cloned)
+}
@@ -0,0 +1,76 @@
+use regex::{NoExpand, Regex, RegexBuilder};
+
+/// A search-and-replace rule applied to every file before processing
+#[derive(serde::Deserialize)]
+pub struct ReplacementRule {
+  pub pattern: String,
+  pub replacement: String,
+  /// Treat `pattern` as a regular expression; `replacement` may then use `$1`-style groups
+  #[serde(default)]
+  pub regex: bool,
+  #[serde(default)]
+  pub case_insensitive: bool,
+}
+
+struct CompiledRule {
+  regex: Regex,
+  replacement: String,
+  expand: bool,
+}
+
+fn compile(rules: &[ReplacementRule]) -> Result<Vec<CompiledRule>, String> {
+  rules
+    .iter()
+    .enumerate()
+    .map(|(index, rule)| {
+      if rule.pattern.is_empty() {
+        return Err(format!("rule {} has an empty pattern", index + 1));
+      }
+      let source = if rule.regex {
+        rule.pattern.clone()
+      } else {
+        regex::escape(&rule.pattern)
+      };
+      let regex = RegexBuilder::new(&source)
+        .case_insensitive(rule.case_insensitive)
+        .build()
+        .map_err(|e| format!("rule {} has an invalid pattern: {e}", index + 1))?;
+      Ok(CompiledRule {
+        regex,
+        replacement: rule.replacement.clone(),
+        expand: rule.regex,
+      })
+    })
+    .collect()
+}
+
+/// Applies every rule in order to each content string. Returns, per input,
+/// the new content if anything changed, plus the number of matches per rule.
+pub fn apply(contents: &[&str], rules: &[ReplacementRule]) -> Result<(Vec<Option<String>>, Vec<usize>), String> {
+  let compiled = compile(rules)?;
+  let mut rule_hits = vec![0; compiled.len()];
+
+  let replaced = contents
+    .iter()
+    .map(|content| {
+      let mut working: Option<String> = None;
+      for (index, rule) in compiled.iter().enumerate() {
+        let current = working.as_deref().unwrap_or(content);
+        let hits = rule.regex.find_iter(current).count();
+        if hits == 0 {
+          continue;
+        }
+        rule_hits[index] += hits;
+        let next = if rule.expand {
+          rule.regex.replace_all(current, rule.replacement.as_str()).into_owned()
+        } else {
+          rule.regex.replace_all(current, NoExpand(&rule.replacement)).into_owned()
+        };
+        working = Some(next);
+      }
+      working
+    })
+    .collect();
+
+  Ok((replaced, rule_hits))
+}
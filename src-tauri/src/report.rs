@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// A processed file as listed in an exported report
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ReportFile {
+  pub path: String,
+  pub size_bytes: u64,
+  pub original_tokens: usize,
+  pub processed_tokens: usize,
+}
+
+/// A file that was left out of the extraction, with the reason shown to the user
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ReportSkippedFile {
+  pub path: String,
+  pub reason: String,
+}
+
+/// Everything the frontend knows about an extraction run
+#[derive(serde::Deserialize)]
+pub struct ReportInput {
+  pub title: Option<String>,
+  pub processing_mode: String,
+  pub files: Vec<ReportFile>,
+  #[serde(default)]
+  pub skipped: Vec<ReportSkippedFile>,
+}
+
+#[derive(Default, serde::Serialize)]
+struct LanguageBreakdown {
+  files: usize,
+  tokens: usize,
+}
+
+fn language_of(path: &str) -> String {
+  Path::new(path)
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_else(|| "other".to_string())
+}
+
+fn escape_html(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for ch in text.chars() {
+    match ch {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&#39;"),
+      other => escaped.push(other),
+    }
+  }
+  escaped
+}
+
+fn savings_percent(original: usize, processed: usize) -> f64 {
+  if original == 0 {
+    return 0.0;
+  }
+  (original as f64 - processed as f64) / original as f64 * 100.0
+}
+
+const REPORT_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#1f2328}\
+table{border-collapse:collapse;width:100%;margin-bottom:2rem}\
+th,td{border-bottom:1px solid #d0d7de;padding:.35rem .6rem;text-align:left}\
+td.num,th.num{text-align:right;font-variant-numeric:tabular-nums}\
+.bar{background:#0969da;height:.8rem;border-radius:2px}\
+.summary span{display:inline-block;margin-right:2rem}";
+
+/// Render a self-contained HTML report; the raw data is embedded as JSON for
+/// anyone who wants to build their own charts from it.
+pub fn render_report(input: &ReportInput) -> String {
+  let title = input.title.as_deref().unwrap_or("Contextractor report");
+  let original_total: usize = input.files.iter().map(|f| f.original_tokens).sum();
+  let processed_total: usize = input.files.iter().map(|f| f.processed_tokens).sum();
+
+  let mut languages: BTreeMap<String, LanguageBreakdown> = BTreeMap::new();
+  for file in &input.files {
+    let entry = languages.entry(language_of(&file.path)).or_default();
+    entry.files += 1;
+    entry.tokens += file.processed_tokens;
+  }
+
+  let data = serde_json::json!({
+    "processing_mode": input.processing_mode,
+    "files": input.files,
+    "languages": languages,
+    "skipped": input.skipped,
+  });
+  // `</` would terminate the script element early
+  let data_json = data.to_string().replace("</", "<\\/");
+
+  let mut html = String::new();
+  let _ = write!(
+    html,
+    "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\"><title>{title}</title><style>{REPORT_STYLE}</style></head><body>",
+    title = escape_html(title),
+  );
+  let _ = write!(html, "<h1>{}</h1>", escape_html(title));
+  let _ = write!(
+    html,
+    "<p class=\"summary\"><span>Mode: <b>{}</b></span><span>Files: <b>{}</b></span><span>Tokens: <b>{}</b> &rarr; <b>{}</b></span><span>Saved: <b>{:.1}%</b></span><span>Skipped: <b>{}</b></span></p>",
+    escape_html(&input.processing_mode),
+    input.files.len(),
+    original_total,
+    processed_total,
+    savings_percent(original_total, processed_total),
+    input.skipped.len(),
+  );
+
+  html.push_str("<h2>Languages</h2><table><tr><th>Language</th><th class=\"num\">Files</th><th class=\"num\">Tokens</th><th></th></tr>");
+  let max_tokens = languages.values().map(|l| l.tokens).max().unwrap_or(0).max(1);
+  for (language, breakdown) in &languages {
+    let _ = write!(
+      html,
+      "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td style=\"width:40%\"><div class=\"bar\" style=\"width:{:.1}%\"></div></td></tr>",
+      escape_html(language),
+      breakdown.files,
+      breakdown.tokens,
+      breakdown.tokens as f64 / max_tokens as f64 * 100.0,
+    );
+  }
+  html.push_str("</table>");
+
+  html.push_str("<h2>Files</h2><table><tr><th>Path</th><th class=\"num\">Size</th><th class=\"num\">Original tokens</th><th class=\"num\">Processed tokens</th><th class=\"num\">Saved</th></tr>");
+  for file in &input.files {
+    let _ = write!(
+      html,
+      "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}%</td></tr>",
+      escape_html(&file.path),
+      file.size_bytes,
+      file.original_tokens,
+      file.processed_tokens,
+      savings_percent(file.original_tokens, file.processed_tokens),
+    );
+  }
+  html.push_str("</table>");
+
+  if !input.skipped.is_empty() {
+    html.push_str("<h2>Skipped files</h2><table><tr><th>Path</th><th>Reason</th></tr>");
+    for skipped in &input.skipped {
+      let _ = write!(
+        html,
+        "<tr><td>{}</td><td>{}</td></tr>",
+        escape_html(&skipped.path),
+        escape_html(&skipped.reason),
+      );
+    }
+    html.push_str("</table>");
+  }
+
+  let _ = write!(
+    html,
+    "<script type=\"application/json\" id=\"report-data\">{data_json}</script></body></html>"
+  );
+  html
+}
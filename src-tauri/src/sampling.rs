@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use tiktoken_rs::CoreBPE;
+
+/// File names that usually mark where a program or package starts
+const ENTRY_POINTS: &[&str] = &[
+  "main.rs", "lib.rs", "build.rs", "main.go", "main.py", "__main__.py", "app.py", "manage.py", "setup.py",
+  "index.js", "index.ts", "index.tsx", "main.js", "main.ts", "main.tsx", "app.js", "app.ts", "app.tsx",
+  "server.js", "server.ts", "main.c", "main.cpp", "main.cc", "program.cs", "main.java", "application.java",
+  "main.kt", "main.swift", "main.dart", "main.rb", "index.php",
+];
+
+/// Manifests and overview documents that describe a project
+const PROJECT_FILES: &[&str] = &[
+  "readme.md", "readme", "readme.txt", "readme.rst", "cargo.toml", "package.json", "pyproject.toml",
+  "go.mod", "pom.xml", "build.gradle", "build.gradle.kts", "gemfile", "composer.json", "cmakelists.txt",
+  "makefile", "dockerfile",
+];
+
+/// A loaded file offered to the sampler
+#[derive(serde::Deserialize)]
+pub struct SampleInput {
+  pub id: String,
+  pub path: String,
+  pub content: String,
+}
+
+/// Why a file was picked
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleReason {
+  ProjectFile,
+  EntryPoint,
+  LargestOfLanguage,
+  DirectoryRepresentative,
+}
+
+#[derive(serde::Serialize)]
+pub struct SampledFile {
+  pub id: String,
+  pub path: String,
+  pub tokens: usize,
+  pub reason: SampleReason,
+}
+
+#[derive(serde::Serialize)]
+pub struct Sample {
+  pub files: Vec<SampledFile>,
+  pub total_tokens: usize,
+  pub skipped_count: usize,
+}
+
+struct Candidate<'a> {
+  file: &'a SampleInput,
+  name: String,
+  dir: String,
+  language: String,
+  tokens: usize,
+}
+
+fn candidate<'a>(file: &'a SampleInput, encoder: &CoreBPE) -> Candidate<'a> {
+  let path = Path::new(&file.path);
+  Candidate {
+    file,
+    name: path
+      .file_name()
+      .map(|n| n.to_string_lossy().to_lowercase())
+      .unwrap_or_default(),
+    dir: path.parent().map(|p| p.to_string_lossy().into_owned()).unwrap_or_default(),
+    language: path
+      .extension()
+      .map(|e| e.to_string_lossy().to_lowercase())
+      .unwrap_or_default(),
+    tokens: encoder.encode_ordinary(&file.content).len(),
+  }
+}
+
+/// Pick a representative subset of `files` that fits in `budget` tokens.
+///
+/// Files are taken in tiers — project manifests and READMEs, entry points,
+/// the largest file of each language, then one file per directory — and a
+/// file is only added while it still fits in the remaining budget.
+pub fn sample(files: &[SampleInput], budget: usize, encoder: &CoreBPE) -> Sample {
+  let candidates: Vec<Candidate> = files.iter().map(|f| candidate(f, encoder)).collect();
+  let mut tiers: Vec<(SampleReason, Vec<usize>)> = Vec::new();
+
+  // Shallow paths first, so the root README wins over a vendored one
+  let mut by_depth: Vec<usize> = (0..candidates.len()).collect();
+  by_depth.sort_by_key(|&i| (candidates[i].file.path.matches(['/', '\\']).count(), candidates[i].file.path.clone()));
+
+  let matching = |names: &[&str]| -> Vec<usize> {
+    by_depth
+      .iter()
+      .copied()
+      .filter(|&i| names.contains(&candidates[i].name.as_str()))
+      .collect()
+  };
+  tiers.push((SampleReason::ProjectFile, matching(PROJECT_FILES)));
+  tiers.push((SampleReason::EntryPoint, matching(ENTRY_POINTS)));
+
+  let mut largest: BTreeMap<&str, usize> = BTreeMap::new();
+  for (i, c) in candidates.iter().enumerate().filter(|(_, c)| !c.language.is_empty()) {
+    let entry = largest.entry(&c.language).or_insert(i);
+    if c.tokens > candidates[*entry].tokens {
+      *entry = i;
+    }
+  }
+  let mut largest: Vec<usize> = largest.into_values().collect();
+  largest.sort_by_key(|&i| std::cmp::Reverse(candidates[i].tokens));
+  tiers.push((SampleReason::LargestOfLanguage, largest));
+
+  // The median-sized file is a fairer picture of a directory than its
+  // largest or smallest one
+  let mut by_dir: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+  for (i, c) in candidates.iter().enumerate() {
+    by_dir.entry(&c.dir).or_default().push(i);
+  }
+  let representatives = by_dir
+    .into_values()
+    .map(|mut members| {
+      members.sort_by_key(|&i| candidates[i].tokens);
+      members[members.len() / 2]
+    })
+    .collect();
+  tiers.push((SampleReason::DirectoryRepresentative, representatives));
+
+  let mut chosen: HashSet<usize> = HashSet::new();
+  let mut picked = Vec::new();
+  let mut total_tokens = 0;
+  for (reason, indices) in tiers {
+    for i in indices {
+      let c = &candidates[i];
+      if chosen.contains(&i) || total_tokens + c.tokens > budget {
+        continue;
+      }
+      chosen.insert(i);
+      total_tokens += c.tokens;
+      picked.push(SampledFile {
+        id: c.file.id.clone(),
+        path: c.file.path.clone(),
+        tokens: c.tokens,
+        reason,
+      });
+    }
+  }
+
+  Sample {
+    skipped_count: candidates.len() - picked.len(),
+    files: picked,
+    total_tokens,
+  }
+}
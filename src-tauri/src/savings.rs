@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use rayon::prelude::*;
+use tiktoken_rs::CoreBPE;
+
+use crate::language;
+use crate::store::StoredFile;
+
+/// Most of a file processed for an estimate; the rest is assumed to shrink
+/// in the same proportion
+const SAMPLE_BYTES: usize = 64 * 1024;
+
+/// Predicted effect of a mode on one file
+#[derive(serde::Serialize)]
+pub struct FileSavings {
+  pub id: String,
+  pub path: String,
+  pub bytes: usize,
+  pub estimated_bytes: usize,
+  pub tokens: usize,
+  pub estimated_tokens: usize,
+  /// Share of the sampled bytes inside comments
+  pub comment_density: f64,
+  /// Only the start of the file was processed
+  pub sampled: bool,
+}
+
+/// Result of `estimate_savings`
+#[derive(serde::Serialize)]
+pub struct SavingsEstimate {
+  pub mode: String,
+  pub files: Vec<FileSavings>,
+  pub bytes: usize,
+  pub estimated_bytes: usize,
+  pub tokens: usize,
+  pub estimated_tokens: usize,
+  /// Share of the tokens the mode is expected to remove
+  pub saved_ratio: f64,
+  /// Requested ids with no loaded file in the store
+  pub missing: Vec<String>,
+}
+
+/// The first whole lines of `content` within `SAMPLE_BYTES`, or all of it
+fn sample(content: &str) -> &str {
+  if content.len() <= SAMPLE_BYTES {
+    return content;
+  }
+  let mut end = SAMPLE_BYTES;
+  while !content.is_char_boundary(end) {
+    end -= 1;
+  }
+  match content[..end].rfind('\n') {
+    Some(newline) => &content[..=newline],
+    None => &content[..end],
+  }
+}
+
+/// `total` scaled by `part / whole`
+fn scale(total: usize, part: usize, whole: usize) -> usize {
+  if whole == 0 {
+    return total;
+  }
+  (total as f64 * part as f64 / whole as f64).round() as usize
+}
+
+fn estimate_file(file: &StoredFile, mode: &str, encoder: &CoreBPE) -> FileSavings {
+  let ext = language::detect(&file.name, &file.content).unwrap_or_else(|| "txt".to_string());
+  let sample = sample(&file.content);
+  let processed = crate::process_text(sample.to_string(), mode, &ext, Default::default());
+  let comment_bytes: usize = crate::comment_ranges(sample, &language::resolve(&ext))
+    .iter()
+    .map(|range| range.len())
+    .sum();
+
+  let tokens = file.tokens(encoder);
+  let sampled = sample.len() < file.content.len();
+  let (estimated_bytes, estimated_tokens) = if sampled {
+    let sample_tokens = encoder.encode_ordinary(sample).len();
+    let processed_tokens = encoder.encode_ordinary(&processed).len();
+    (
+      scale(file.content.len(), processed.len(), sample.len()),
+      scale(tokens, processed_tokens, sample_tokens),
+    )
+  } else {
+    (processed.len(), encoder.encode_ordinary(&processed).len())
+  };
+
+  FileSavings {
+    id: file.id.clone(),
+    path: file.label().to_string(),
+    bytes: file.content.len(),
+    estimated_bytes,
+    tokens,
+    estimated_tokens,
+    comment_density: if sample.is_empty() { 0.0 } else { comment_bytes as f64 / sample.len() as f64 },
+    sampled,
+  }
+}
+
+/// Predicted savings of processing `files` with `mode`, from a sample of
+/// each, in parallel
+pub fn estimate(files: &[Arc<StoredFile>], missing: Vec<String>, mode: &str, encoder: &CoreBPE) -> SavingsEstimate {
+  let files: Vec<FileSavings> = files.par_iter().map(|file| estimate_file(file, mode, encoder)).collect();
+  let bytes = files.iter().map(|file| file.bytes).sum();
+  let estimated_bytes = files.iter().map(|file| file.estimated_bytes).sum();
+  let tokens: usize = files.iter().map(|file| file.tokens).sum();
+  let estimated_tokens: usize = files.iter().map(|file| file.estimated_tokens).sum();
+  SavingsEstimate {
+    mode: mode.to_string(),
+    files,
+    bytes,
+    estimated_bytes,
+    tokens,
+    estimated_tokens,
+    saved_ratio: if tokens == 0 { 0.0 } else { 1.0 - estimated_tokens as f64 / tokens as f64 },
+    missing,
+  }
+}
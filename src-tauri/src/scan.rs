@@ -0,0 +1,926 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use crate::archive;
+use crate::binary::{self, BinaryInfo};
+use crate::error::TextractorError;
+use crate::generated;
+use crate::gitattributes::LinguistRules;
+use crate::language;
+use crate::limits;
+
+
+/// File info returned from read_files_from_paths
+#[derive(Clone, serde::Serialize)]
+pub struct FileInfo {
+  pub name: String,
+  pub path: String,
+  pub content: String,
+  pub is_text: bool,
+  /// Size on disk when the file was read
+  pub size: u64,
+  /// Modification time in milliseconds since the Unix epoch, when available
+  pub modified_ms: Option<u64>,
+  /// Language key from the extension, the file name (`Makefile`), a shebang
+  /// or the content; the extension comment handling knows it by
+  pub language: Option<String>,
+  /// Looks generated or minified (`*.min.js`, `// Code generated`, very long
+  /// lines); such files are skipped unless generated files are included
+  pub generated: bool,
+  /// Name of the dropped file or folder the file was found under, unique
+  /// among the roots of one load
+  pub root: Option<String>,
+  /// `/`-separated path below `root`, starting with the root's name; shown
+  /// in exports instead of the absolute `path`
+  pub relative_path: Option<String>,
+  /// Type and dimensions of a file that is not text, whose `content` is
+  /// then their one-line description
+  pub binary: Option<BinaryInfo>,
+}
+
+impl FileInfo {
+  /// Record that the file was found under the dropped `root`, known as `root_name`
+  pub fn set_root(&mut self, root: &Path, root_name: &str) {
+    let Ok(relative) = Path::new(&self.path).strip_prefix(root) else {
+      return;
+    };
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    self.relative_path = Some(if relative.is_empty() {
+      root_name.to_string()
+    } else {
+      format!("{root_name}/{relative}")
+    });
+    self.root = Some(root_name.to_string());
+  }
+}
+
+/// A name for each dropped path: its last component, numbered when two roots
+/// would otherwise share one (`src`, `src (2)`)
+pub fn root_names(paths: &[String]) -> Vec<String> {
+  let mut names: Vec<String> = Vec::with_capacity(paths.len());
+  for path in paths {
+    let trimmed = path.trim_end_matches(['/', '\\']);
+    let base = Path::new(trimmed)
+      .file_name()
+      .map_or_else(|| trimmed.to_string(), |name| name.to_string_lossy().to_string());
+    let mut name = base.clone();
+    let mut count = 1;
+    while names.contains(&name) {
+      count += 1;
+      name = format!("{base} ({count})");
+    }
+    names.push(name);
+  }
+  names
+}
+
+pub fn modified_ms(metadata: &fs::Metadata) -> Option<u64> {
+  metadata
+    .modified()
+    .ok()?
+    .duration_since(std::time::UNIX_EPOCH)
+    .ok()
+    .map(|d| d.as_millis() as u64)
+}
+
+/// List of text file extensions (matches frontend TEXT_FILE_EXTENSIONS)
+const TEXT_EXTENSIONS: &[&str] = &[
+    "txt", "md", "json", "ipynb", "xml", "html", "htm", "css", "scss", "sass", "less",
+    "js", "mjs", "cjs", "ts", "mts", "tsx", "jsx", "vue", "svelte", "astro",
+    "py", "pyw", "pyx", "rb", "php", "java", "c", "h", "cpp", "hpp", "cc",
+    "cs", "go", "rs", "swift", "kt", "kts", "scala", "groovy", "clj", "cljs",
+    "ex", "exs", "erl", "hrl", "hs", "elm", "lua", "r", "R", "jl", "pl", "pm",
+    "sh", "bash", "zsh", "fish", "ps1", "psm1", "bat", "cmd",
+    "sql", "graphql", "gql", "prisma", "proto",
+    "yaml", "yml", "toml", "ini", "conf", "env", "cfg",
+    "dockerfile", "containerfile", "makefile", "cmake",
+    "gitignore", "gitattributes", "npmrc", "nvmrc", "editorconfig",
+    "lock", "log", "csv", "tsv",
+    "m", "asm", "s", "nasm", "f90", "f95", "f03", "f08", "cob", "cbl",
+    "vb", "vba", "bas", "cls", "v", "vh", "sv", "svh", "vhd", "vhdl",
+    "tex", "latex", "sty", "ltx", "dtx", "f", "for", "f77", "vim", "nim", "nims", "nimble",
+    "zig", "tf", "tfvars", "hcl", "ml", "mli", "fs", "fsi", "fsx",
+];
+
+/// Check if file is likely a text file
+pub fn is_text_file(path: &Path) -> bool {
+    // Check by extension, the user config first
+    if let Some(ext) = path.extension() {
+        let ext_lower = ext.to_string_lossy().to_lowercase();
+        if let Some(is_text) = language::text_override(&ext_lower) {
+            return is_text;
+        }
+        if TEXT_EXTENSIONS.contains(&ext_lower.as_str()) {
+            return true;
+        }
+    }
+
+    // Check for dot files (e.g., .gitignore, .env)
+    if let Some(name) = path.file_name() {
+        let name_str = name.to_string_lossy();
+        if name_str.starts_with('.') && !name_str.contains('.') {
+            return true;
+        }
+        // Common dotfiles
+        let dotfiles = [".gitignore", ".gitattributes", ".npmrc", ".nvmrc", 
+                        ".editorconfig", ".prettierrc", ".eslintrc", ".babelrc",
+                        ".env", ".env.local", ".env.development", ".env.production"];
+        if dotfiles.contains(&name_str.as_ref()) {
+            return true;
+        }
+        if language::from_filename(&name_str).is_some() {
+            return true;
+        }
+    }
+
+    // Extensionless scripts announce themselves with a shebang
+    path.extension().is_none() && has_shebang(path)
+}
+
+fn has_shebang(path: &Path) -> bool {
+    let mut start = [0u8; 2];
+    fs::File::open(path)
+        .and_then(|mut file| std::io::Read::read_exact(&mut file, &mut start))
+        .is_ok_and(|_| &start == b"#!")
+}
+
+/// Directories of dependencies, build output and caches that walks skip
+/// unless the settings name others
+pub const DEFAULT_EXCLUDED_DIRS: &[&str] = &[
+    "node_modules", "target", ".venv", "dist", "build", "__pycache__", ".next", "vendor",
+];
+
+/// Content one load reads into memory unless the settings allow another amount
+pub const DEFAULT_MAX_LOAD_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Bytes of content a load may still read, shared by every path it reads
+/// and by the clones of its `ScanOptions`
+#[derive(Clone, Default)]
+pub struct LoadBudget {
+    /// `None` reads without limit
+    limit: Option<u64>,
+    used: Arc<AtomicU64>,
+}
+
+impl LoadBudget {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit: Some(limit),
+            used: Arc::default(),
+        }
+    }
+
+    /// Count `bytes` as read; `false`, counting nothing, when they would take
+    /// the load over its limit
+    fn take(&self, bytes: u64) -> bool {
+        let Some(limit) = self.limit else {
+            return true;
+        };
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&total| total <= limit)
+            })
+            .is_ok()
+    }
+}
+
+/// Options that decide which files a directory scan yields
+#[derive(Clone, Default)]
+pub struct ScanOptions {
+    /// Keep files that `.gitattributes` marks `linguist-generated` or
+    /// `linguist-vendored`, or that look generated or minified; they are
+    /// skipped by default, as GitHub hides them
+    pub include_generated: bool,
+    /// Names of directories never descended into
+    pub excluded_dirs: Vec<String>,
+    /// Content the load may read before it stops
+    pub load_budget: LoadBudget,
+    filters: ScanFilters,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+/// Per-load filters chosen by the user, applied before any content is read
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ScanFilters {
+    /// Only read files with one of these extensions (`rs`, `.toml`, ...)
+    pub allowed_extensions: Option<Vec<String>>,
+    /// Never read files with one of these extensions
+    pub blocked_extensions: Option<Vec<String>>,
+    /// Only read files whose path relative to the dropped directory matches
+    /// one of these globs (`src/**/*.rs`)
+    pub include_globs: Option<Vec<String>>,
+    /// Skip files and directories whose relative path matches one of these globs
+    pub exclude_globs: Option<Vec<String>>,
+    /// Read through symbolic links below the dropped directory; without
+    /// this, linked files and directories are left out
+    pub follow_symlinks: bool,
+    /// Read no deeper than this many levels below a dropped directory; `1`
+    /// reads only the files directly inside it
+    pub max_depth: Option<usize>,
+    /// Stop reading a dropped path after this many files, with a warning;
+    /// `DEFAULT_MAX_FILES` when unset
+    pub max_files: Option<usize>,
+}
+
+/// Files read from one dropped path unless the load asks for another cap,
+/// enough for large repositories but not for a whole home directory
+pub const DEFAULT_MAX_FILES: usize = 50_000;
+
+fn build_glob_set(globs: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let glob = GlobBuilder::new(glob.trim())
+            .literal_separator(true)
+            .build()
+            .map_err(|e| format!("invalid glob {glob}: {e}"))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| format!("invalid globs: {e}"))
+}
+
+fn normalize_extension(ext: &str) -> String {
+    ext.trim().trim_start_matches('.').to_lowercase()
+}
+
+impl ScanOptions {
+    pub fn with_filters(mut self, filters: ScanFilters) -> Result<Self, String> {
+        let normalize = |list: Option<Vec<String>>| {
+            list.map(|exts| exts.iter().map(|e| normalize_extension(e)).collect::<Vec<_>>())
+        };
+        let globs = |list: &Option<Vec<String>>| {
+            list.as_deref().filter(|l| !l.is_empty()).map(build_glob_set).transpose()
+        };
+        self.include = globs(&filters.include_globs)?;
+        self.exclude = globs(&filters.exclude_globs)?;
+        self.filters = ScanFilters {
+            allowed_extensions: normalize(filters.allowed_extensions),
+            blocked_extensions: normalize(filters.blocked_extensions),
+            ..filters
+        };
+        Ok(self)
+    }
+
+    /// Most files read from one dropped path
+    pub fn max_files(&self) -> usize {
+        self.filters.max_files.unwrap_or(DEFAULT_MAX_FILES)
+    }
+
+    /// Whether a directory, given by its path relative to the scan root, is
+    /// excluded outright
+    fn excludes_dir(&self, relative: &Path) -> bool {
+        self.exclude.as_ref().is_some_and(|set| set.is_match(relative))
+    }
+
+    /// Whether a file, given by its path relative to the scan root, is inside
+    /// one of `excluded_dirs`; for listings that are not walked
+    pub fn in_excluded_dir(&self, relative: &Path) -> bool {
+        relative
+            .parent()
+            .is_some_and(|parent| parent.iter().any(|dir| self.excluded_dirs.iter().any(|name| dir == name.as_str())))
+    }
+
+    /// Whether a file passes the per-load filters; `relative` is its path
+    /// relative to the scan root, or just its name for a file dropped directly
+    pub fn accepts_file(&self, relative: &Path) -> bool {
+        if self.exclude.as_ref().is_some_and(|set| set.is_match(relative)) {
+            return false;
+        }
+        if self.include.as_ref().is_some_and(|set| !set.is_match(relative)) {
+            return false;
+        }
+
+        let ext = relative
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        if let Some(allowed) = &self.filters.allowed_extensions {
+            if !allowed.contains(&ext) {
+                return false;
+            }
+        }
+        if let Some(blocked) = &self.filters.blocked_extensions {
+            if !ext.is_empty() && blocked.contains(&ext) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Per-project ignore file, in .gitignore syntax, read from the root of a
+/// dropped directory
+pub const IGNORE_FILE: &str = ".textractorignore";
+
+fn load_ignore_file(root: &Path) -> Option<Gitignore> {
+    let path = root.join(IGNORE_FILE);
+    if !path.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(e) = builder.add(&path) {
+        log::warn!("Problem in {}: {}", path.display(), e);
+    }
+    match builder.build() {
+        Ok(ignore) => Some(ignore),
+        Err(e) => {
+            log::warn!("Ignoring invalid {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Walk a directory, skipping hidden files and directories, paths listed in
+/// the root's `.textractorignore`, and generated and vendored paths (unless
+/// `options` includes them).
+///
+/// Files are yielded in lexicographic order of their `/`-separated relative
+/// paths, compared byte by byte, so the order is the same on every platform
+/// and filesystem (`a.txt` before `a/b`). Each directory comes just before
+/// its contents.
+pub fn walk_directory(root: &Path, options: &ScanOptions) -> impl Iterator<Item = walkdir::DirEntry> {
+    walk_directory_reporting(root, options, |_| {})
+}
+
+/// Orders siblings so that the walk yields files by relative path: a directory
+/// sorts as its name followed by `/`, where the paths of its contents go on
+fn by_relative_path(a: &walkdir::DirEntry, b: &walkdir::DirEntry) -> std::cmp::Ordering {
+    let key = |entry: &walkdir::DirEntry| {
+        let separator: &[u8] = if entry.file_type().is_dir() { b"/" } else { b"" };
+        let name = entry.file_name().as_encoded_bytes().to_vec();
+        [name, separator.to_vec()].concat()
+    };
+    key(a).cmp(&key(b))
+}
+
+/// Most linked directories one walk descends into
+const MAX_FOLLOWED_LINKS: usize = 256;
+
+/// Keeps a walk that follows symbolic links from reading a directory twice,
+/// through a cycle or two links to the same place, or from climbing above
+/// its root
+struct LinkGuard {
+    root: PathBuf,
+    /// Canonical paths of the directories walked so far
+    visited: HashSet<PathBuf>,
+    followed: usize,
+}
+
+impl LinkGuard {
+    fn new(root: &Path) -> Self {
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        Self {
+            visited: HashSet::from([root.clone()]),
+            root,
+            followed: 0,
+        }
+    }
+
+    /// Whether to descend into the directory at `path`
+    fn admit(&mut self, path: &Path, is_link: bool) -> bool {
+        let Ok(canonical) = fs::canonicalize(path) else {
+            return false;
+        };
+        if self.visited.contains(&canonical) {
+            return false;
+        }
+        if is_link {
+            if self.root.starts_with(&canonical) {
+                log::warn!("Not following link to an ancestor of the scan root: {}", path.display());
+                return false;
+            }
+            if self.followed == MAX_FOLLOWED_LINKS {
+                log::warn!("Followed {} linked directories, not following {}", MAX_FOLLOWED_LINKS, path.display());
+                return false;
+            }
+            self.followed += 1;
+        }
+        self.visited.insert(canonical)
+    }
+}
+
+/// `walk_directory`, calling `on_hidden` with each dotfile or dot-directory
+/// below `root` that is left out; dot-directories are not descended into.
+pub fn walk_directory_reporting(
+    root: &Path,
+    options: &ScanOptions,
+    mut on_hidden: impl FnMut(&Path),
+) -> impl Iterator<Item = walkdir::DirEntry> {
+    let mut linguist = (!options.include_generated).then(|| LinguistRules::for_root(root));
+    let dir_options = options.clone();
+    let file_options = options.clone();
+    let dir_root = root.to_path_buf();
+    let file_root = root.to_path_buf();
+    let ignore_file = load_ignore_file(root);
+    let follow_symlinks = options.filters.follow_symlinks;
+    let mut links = follow_symlinks.then(|| LinkGuard::new(root));
+
+    WalkDir::new(root)
+        .follow_links(follow_symlinks)
+        .max_depth(options.filters.max_depth.unwrap_or(usize::MAX))
+        .sort_by(by_relative_path)
+        .into_iter()
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                on_hidden(entry.path());
+                return false;
+            }
+            let is_dir = entry.file_type().is_dir();
+            if ignore_file.as_ref().is_some_and(|ignore| ignore.matched(entry.path(), is_dir).is_ignore()) {
+                return false;
+            }
+            if is_dir
+                && (dir_options.excluded_dirs.iter().any(|name| entry.file_name() == name.as_str())
+                    || dir_options.excludes_dir(entry.path().strip_prefix(&dir_root).unwrap_or(entry.path())))
+            {
+                return false;
+            }
+            let admitted = match links.as_mut() {
+                None => !entry.path_is_symlink(),
+                Some(links) => !is_dir || links.admit(entry.path(), entry.path_is_symlink()),
+            };
+            if !admitted {
+                return false;
+            }
+            let Some(rules) = linguist.as_mut() else {
+                return true;
+            };
+            if rules.is_excluded(entry.path()) {
+                return false;
+            }
+            // Directories come before their contents, so nested
+            // .gitattributes files are loaded in time
+            if entry.file_type().is_dir() {
+                rules.add_dir(entry.path());
+            }
+            true
+        })
+        .filter_map(|e| e.ok())
+        .filter(move |entry| {
+            entry.file_type().is_dir()
+                || file_options.accepts_file(entry.path().strip_prefix(&file_root).unwrap_or(entry.path()))
+        })
+}
+
+/// How long a scan waits for the next file before treating the path as unresponsive
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Emitted as `scan-warning` when a path had to be abandoned part-way through a scan
+#[derive(Clone, serde::Serialize)]
+pub struct ScanWarning {
+    pub path: String,
+    pub message: String,
+}
+
+/// Read a file or walk a directory on a helper thread so that a dead network
+/// mount cannot hang the scan: if no file arrives within `IO_TIMEOUT` the path
+/// is abandoned with a warning and whatever was read so far is returned. The
+/// helper thread is left to finish (or stay blocked) on its own.
+pub fn read_path_guarded(path: &Path, options: &ScanOptions) -> (Vec<FileInfo>, Vec<SkippedFile>, Option<ScanWarning>) {
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let (_, warning) = read_path_streaming(path, options, |file_info| files.push(file_info), |file| skipped.push(file));
+    (files, skipped, warning)
+}
+
+fn scanned(path: &Path) -> Scanned {
+    match read_file(path) {
+        Ok(file_info) => Scanned::File(file_info),
+        Err(error) => Scanned::Skipped(SkippedFile {
+            path: path.to_string_lossy().to_string(),
+            reason: SkipReason::of(&error),
+            message: error.to_string(),
+        }),
+    }
+}
+
+/// Files read in parallel at a time by `read_path_streaming`
+const READ_BATCH: usize = 64;
+/// Longest a slow walk holds back the files it has found before they are read
+const READ_BATCH_WAIT: Duration = Duration::from_millis(100);
+
+/// Read the files in `batch` in parallel and send them in order, emptying
+/// it; `false` once the receiver is gone
+fn read_batch(batch: &mut Vec<PathBuf>, tx: &mpsc::Sender<Scanned>) -> bool {
+    let read: Vec<Scanned> = batch.par_iter().map(|path| scanned(path)).collect();
+    batch.clear();
+    read.into_iter().all(|file| tx.send(file).is_ok())
+}
+
+/// Sent from the reading thread of `read_path_streaming`
+enum Scanned {
+    File(FileInfo),
+    Skipped(SkippedFile),
+}
+
+/// Like `read_path_guarded`, but hands each file to `on_file` as soon as it
+/// is read, and each file that cannot be loaded to `on_skip`. Returns the
+/// number of files read. Reading stops with a warning once
+/// `ScanOptions::max_files` files have been read.
+pub fn read_path_streaming(
+    path: &Path,
+    options: &ScanOptions,
+    mut on_file: impl FnMut(FileInfo),
+    mut on_skip: impl FnMut(SkippedFile),
+) -> (usize, Option<ScanWarning>) {
+    let (tx, rx) = mpsc::channel();
+    let root = path.to_path_buf();
+    let include_generated = options.include_generated;
+    let max_files = options.max_files();
+    let load_budget = options.load_budget.clone();
+    let options = options.clone();
+
+    std::thread::spawn(move || {
+        if !root.exists() {
+            log::warn!("Path does not exist: {}", root.display());
+        } else if root.is_file() {
+            // Single file; an archive stands for the files inside it
+            if archive::is_archive(&root) {
+                let file_tx = tx.clone();
+                archive::read_archive(
+                    &root,
+                    &options,
+                    &mut |file_info| {
+                        let _ = file_tx.send(Scanned::File(file_info));
+                    },
+                    &mut |file| {
+                        let _ = tx.send(Scanned::Skipped(file));
+                    },
+                );
+                return;
+            }
+            if !options.accepts_file(Path::new(root.file_name().unwrap_or_default())) {
+                return;
+            }
+            let _ = tx.send(scanned(&root));
+        } else if root.is_dir() {
+            // Directory - walk recursively
+            let hidden_tx = tx.clone();
+            let on_hidden = move |path: &Path| {
+                let _ = hidden_tx.send(Scanned::Skipped(SkippedFile {
+                    path: path.to_string_lossy().to_string(),
+                    reason: SkipReason::Hidden,
+                    message: "hidden file or directory".to_string(),
+                }));
+            };
+            // Files are read in parallel a batch at a time while the walk
+            // goes on, and sent in walk order
+            let mut batch = Vec::with_capacity(READ_BATCH);
+            let mut batch_started = Instant::now();
+            for entry in walk_directory_reporting(&root, &options, on_hidden) {
+                if entry.path().is_file() {
+                    if batch.is_empty() {
+                        batch_started = Instant::now();
+                    }
+                    batch.push(entry.into_path());
+                }
+                let due = batch.len() >= READ_BATCH || (!batch.is_empty() && batch_started.elapsed() >= READ_BATCH_WAIT);
+                if due && !read_batch(&mut batch, &tx) {
+                    return;
+                }
+            }
+            read_batch(&mut batch, &tx);
+        }
+    });
+
+    let mut count = 0;
+    loop {
+        match rx.recv_timeout(IO_TIMEOUT) {
+            Ok(Scanned::File(file_info)) if file_info.generated && !include_generated => {
+                on_skip(SkippedFile {
+                    path: file_info.path,
+                    reason: SkipReason::Generated,
+                    message: "generated or minified file".to_string(),
+                });
+            }
+            // Dropping the receiver stops the reading thread at its next file
+            Ok(Scanned::File(_)) if count == max_files => {
+                log::warn!("Read {} files, skipping the rest: {}", max_files, path.display());
+                let warning = ScanWarning {
+                    path: path.to_string_lossy().to_string(),
+                    message: format!(
+                        "Stopped after {max_files} files; drop a smaller folder or raise the file limit to read the rest"
+                    ),
+                };
+                return (count, Some(warning));
+            }
+            Ok(Scanned::File(file_info)) if !load_budget.take(file_info.content.len() as u64) => {
+                log::warn!("Load budget used up, skipping the rest: {}", path.display());
+                let warning = ScanWarning {
+                    path: path.to_string_lossy().to_string(),
+                    message: format!(
+                        "Stopped after {count} files: the load reached its memory budget; drop a smaller folder or raise the load limit to read the rest"
+                    ),
+                };
+                return (count, Some(warning));
+            }
+            Ok(Scanned::File(file_info)) => {
+                count += 1;
+                on_file(file_info);
+            }
+            Ok(Scanned::Skipped(file)) => on_skip(file),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return (count, None),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                log::warn!("Path stopped responding, skipping the rest: {}", path.display());
+                let warning = ScanWarning {
+                    path: path.to_string_lossy().to_string(),
+                    message: format!(
+                        "No response for {} seconds (slow or disconnected drive?); {} files were read before giving up",
+                        IO_TIMEOUT.as_secs(),
+                        count
+                    ),
+                };
+                return (count, Some(warning));
+            }
+        }
+    }
+}
+
+/// Why a file was left out of a load
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Larger than the configured maximum file size
+    TooLarge,
+    /// Could not be read, or is not valid UTF-8
+    Unreadable,
+    Binary,
+    /// Dotfile, or inside a dot-directory
+    Hidden,
+    /// Looks generated or minified
+    Generated,
+    /// Dropped again while still loaded, and unchanged since
+    AlreadyLoaded,
+}
+
+impl SkipReason {
+    pub fn of(error: &TextractorError) -> Self {
+        match error {
+            TextractorError::FileTooLarge { .. } => SkipReason::TooLarge,
+            TextractorError::BinaryFile { .. } => SkipReason::Binary,
+            _ => SkipReason::Unreadable,
+        }
+    }
+}
+
+/// A file left out of a load, reported so users can see why it is missing
+#[derive(Clone, serde::Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: SkipReason,
+    pub message: String,
+}
+
+/// Read a single file and return FileInfo; non-text files come back with
+/// metadata only, described in `content`
+pub fn read_single_file(path: &Path) -> Option<FileInfo> {
+    match read_file(path) {
+        Ok(file_info) => Some(file_info),
+        Err(TextractorError::BinaryFile { .. }) => {
+            let metadata = fs::metadata(path).ok()?;
+            let binary = binary::inspect(path, metadata.len());
+            Some(FileInfo {
+                name: path.file_name()?.to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                content: binary.description.clone(),
+                is_text: false,
+                size: metadata.len(),
+                modified_ms: modified_ms(&metadata),
+                language: language::detect(&path.file_name()?.to_string_lossy(), ""),
+                generated: false,
+                root: None,
+                relative_path: None,
+                binary: Some(binary),
+            })
+        }
+        Err(_) => None,
+    }
+}
+
+/// Read a text file, or say why it cannot be loaded
+/// Files at least this large are read in chunks
+const CHUNKED_READ_MIN: u64 = 1024 * 1024;
+const READ_CHUNK: u64 = 256 * 1024;
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
+
+/// Read a text file of `size` bytes. Large files are read in chunks into a
+/// buffer of their size, each chunk validated as it arrives, so a file that
+/// is not UTF-8 is given up on at the first bad chunk rather than read whole.
+fn read_text(path: &Path, size: u64) -> io::Result<String> {
+    if size < CHUNKED_READ_MIN {
+        return fs::read_to_string(path);
+    }
+    let mut file = fs::File::open(path)?;
+    let mut bytes = Vec::with_capacity(size as usize);
+    // Bytes known to be valid; a character cut off at the end of a chunk is
+    // checked with the next one
+    let mut valid = 0;
+    loop {
+        let read = (&mut file).take(READ_CHUNK).read_to_end(&mut bytes)?;
+        match std::str::from_utf8(&bytes[valid..]) {
+            Ok(_) => valid = bytes.len(),
+            Err(e) if e.error_len().is_none() && read > 0 => valid += e.valid_up_to(),
+            Err(_) => return Err(invalid_utf8()),
+        }
+        if read == 0 {
+            break;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_utf8())
+}
+
+/// The first `max_bytes` of a text file, cut back to a character boundary,
+/// without reading the rest; also the size of the whole file
+pub fn read_text_prefix(path: &Path, max_bytes: usize) -> io::Result<(String, u64)> {
+    let file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut bytes = Vec::with_capacity(size.min(max_bytes as u64) as usize);
+    file.take(max_bytes as u64).read_to_end(&mut bytes)?;
+    match String::from_utf8(bytes) {
+        Ok(text) => Ok((text, size)),
+        // Only a character cut off at `max_bytes`
+        Err(e) if e.utf8_error().error_len().is_none() => {
+            let valid = e.utf8_error().valid_up_to();
+            let mut bytes = e.into_bytes();
+            bytes.truncate(valid);
+            Ok((String::from_utf8(bytes).map_err(|_| invalid_utf8())?, size))
+        }
+        Err(_) => Err(invalid_utf8()),
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<FileInfo, TextractorError> {
+    let path_str = path.to_string_lossy().to_string();
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => {
+            return Err(TextractorError::IoError {
+                path: path_str,
+                message: "not a file".to_string(),
+            })
+        }
+    };
+
+    // Check file size
+    let metadata = fs::metadata(path).map_err(|e| {
+        log::warn!("Failed to read file metadata: {} - {}", path_str, e);
+        TextractorError::io(path, &e)
+    })?;
+    let max_file_size = limits::max_file_size() as u64;
+    if metadata.len() > max_file_size {
+        log::warn!("File too large, skipping: {}", path_str);
+        return Err(TextractorError::FileTooLarge {
+            path: path_str,
+            size: metadata.len(),
+            limit: max_file_size,
+        });
+    }
+    if !is_text_file(path) {
+        return Err(TextractorError::BinaryFile { path: path_str });
+    }
+
+    match read_text(path, metadata.len()) {
+        Ok(content) => Ok(FileInfo {
+            language: language::detect(&name, &content),
+            generated: generated::is_generated(&name, &content),
+            name,
+            path: path_str,
+            content,
+            is_text: true,
+            size: metadata.len(),
+            modified_ms: modified_ms(&metadata),
+            root: None,
+            relative_path: None,
+            binary: None,
+        }),
+        Err(e) => {
+            log::warn!("Failed to read file as text: {} - {}", path_str, e);
+            Err(TextractorError::io(path, &e))
+        }
+    }
+}
+
+/// Result of the metadata-only pre-scan of a drop, emitted as `scan-estimate`
+#[derive(Clone, serde::Serialize)]
+pub struct ScanEstimate {
+    pub id: u64,
+    pub paths: Vec<String>,
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
+/// Count the files under `paths` and their total size without reading any content
+pub fn estimate(paths: &[String], options: &ScanOptions) -> (usize, u64) {
+    let mut file_count = 0;
+    let mut total_bytes = 0;
+
+    for path_str in paths {
+        let path = Path::new(path_str);
+        if path.is_file() {
+            if !options.accepts_file(Path::new(path.file_name().unwrap_or_default())) {
+                continue;
+            }
+            file_count += 1;
+            total_bytes += fs::metadata(path).map_or(0, |m| m.len());
+        } else if path.is_dir() {
+            // The load stops at the file cap, so counting further is wasted
+            let mut path_count = 0;
+            for entry in walk_directory(path, options) {
+                if let Ok(metadata) = entry.metadata() {
+                    if metadata.is_file() {
+                        path_count += 1;
+                        total_bytes += metadata.len();
+                        if path_count == options.max_files() {
+                            break;
+                        }
+                    }
+                }
+            }
+            file_count += path_count;
+        }
+    }
+
+    (file_count, total_bytes)
+}
+
+/// How long a pre-scanned drop waits for `confirm_scan` before it is dropped
+pub const CONFIRM_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Drops that have been estimated and are waiting for the user's decision
+#[derive(Default)]
+pub struct PendingScans {
+    next_id: AtomicU64,
+    waiting: Mutex<HashMap<u64, mpsc::Sender<Option<ScanFilters>>>>,
+}
+
+impl PendingScans {
+    /// Register a new scan; the receiver yields the user's decision: the
+    /// filters to read with, or `None` to cancel
+    pub fn register(&self) -> (u64, mpsc::Receiver<Option<ScanFilters>>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, rx) = mpsc::channel();
+        self.waiting.lock().unwrap_or_else(|e| e.into_inner()).insert(id, tx);
+        (id, rx)
+    }
+
+    /// Deliver the decision for scan `id`
+    pub fn resolve(&self, id: u64, decision: Option<ScanFilters>) -> Result<(), String> {
+        let sender = self
+            .waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&id)
+            .ok_or_else(|| format!("no pending scan with id {id}"))?;
+        sender
+            .send(decision)
+            .map_err(|_| format!("scan {id} is no longer waiting"))
+    }
+
+    pub fn forget(&self, id: u64) {
+        self.waiting.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walks_yield_files_in_relative_path_order() {
+        let root = std::env::temp_dir().join(format!("textractor-walk-{}", std::process::id()));
+        for dir in ["a", "a/b", "B"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["a.txt", "a/b/c.txt", "a/z.txt", "a-b.txt", "B/x.txt", "b.txt"] {
+            fs::write(root.join(file), "x").unwrap();
+        }
+
+        let files: Vec<String> = walk_directory(&root, &ScanOptions::default())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.path().strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        fs::remove_dir_all(&root).unwrap();
+
+        let mut sorted = files.clone();
+        sorted.sort();
+        assert_eq!(files, sorted);
+        assert_eq!(files, ["B/x.txt", "a-b.txt", "a.txt", "a/b/c.txt", "a/z.txt", "b.txt"]);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::markers::KeepMarkers;
+
+/// Which comments comment removal strips
+#[derive(Clone, Copy)]
+pub enum CommentScope {
+  All,
+  /// Documentation comments only; implementation comments stay
+  Docs,
+  /// Implementation comments only; documentation stays
+  Inline,
+}
+
+/// Decides, comment by comment, what comment removal strips
+pub struct CommentFilter<'a> {
+  pub scope: CommentScope,
+  pub keep: &'a KeepMarkers,
+}
+
+impl CommentFilter<'_> {
+  /// Whether `comment`, including its delimiters, should be removed
+  pub fn removes(&self, comment: &str) -> bool {
+    let in_scope = match self.scope {
+      CommentScope::All => true,
+      CommentScope::Docs => is_doc_comment(comment),
+      CommentScope::Inline => !is_doc_comment(comment),
+    };
+    in_scope && !self.keep.keeps(comment)
+  }
+}
+
+/// Whether `comment` is documentation: `///` and `//!` lines, `/** */` and
+/// `/*! */` blocks (JSDoc, Javadoc, Doxygen), Haskell `-- |` and `{- | -}`,
+/// OCaml `(** *)`, LuaDoc `---`, roxygen `#'`, and Python docstrings
+pub fn is_doc_comment(comment: &str) -> bool {
+  let starts = |prefix: &str| comment.starts_with(prefix);
+  if starts("////") || starts("/**/") || starts("(**)") || starts("----") {
+    return false;
+  }
+  if ["///", "//!", "/**", "/*!", "(**", "---", "#'"].iter().any(|prefix| starts(prefix)) {
+    return true;
+  }
+  if let Some(rest) = comment.strip_prefix("--").or_else(|| comment.strip_prefix("{-")) {
+    return rest.trim_start().starts_with(['|', '^']);
+  }
+  // Docstrings are string literals, possibly prefixed (`r"""`)
+  comment
+    .trim_start_matches(['r', 'R', 'u', 'U', 'b', 'B'])
+    .starts_with(['"', '\''])
+}
@@ -0,0 +1,39 @@
+use std::fs;
+use std::path::Path;
+
+use rhai::{CallFnOptions, Engine, Scope, AST};
+
+/// Function a processing script defines
+const HOOK: &str = "process";
+
+/// A user's Rhai script whose `process(content, path, language)` runs on
+/// every file after the built-in modes and returns its new content
+pub struct ProcessScript {
+  engine: Engine,
+  ast: AST,
+}
+
+impl ProcessScript {
+  /// Compile the script at `path`, checking that it defines the hook
+  pub fn load(path: &Path) -> Result<Self, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let mut engine = Engine::new();
+    engine.on_print(|text| log::info!("Processing script: {}", text));
+    engine.on_debug(|text, _, position| log::debug!("Processing script at {}: {}", position, text));
+    let ast = engine.compile(&source).map_err(|e| format!("{}: {e}", path.display()))?;
+    if !ast.iter_functions().any(|f| f.name == HOOK && f.params.len() == 3) {
+      return Err(format!("{} does not define {HOOK}(content, path, language)", path.display()));
+    }
+    Ok(Self { engine, ast })
+  }
+
+  /// `content` as the hook returns it
+  pub fn run(&self, content: String, path: &str, language: &str) -> Result<String, String> {
+    // Only the hook runs; statements outside functions are ignored
+    let options = CallFnOptions::new().eval_ast(false);
+    self
+      .engine
+      .call_fn_with_options(options, &mut Scope::new(), &self.ast, HOOK, (content, path.to_string(), language.to_string()))
+      .map_err(|e| format!("{HOOK} script failed: {e}"))
+  }
+}
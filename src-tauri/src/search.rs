@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+
+use crate::store::StoredFile;
+
+/// Longest snippet returned for a match, in bytes
+const SNIPPET_LEN: usize = 200;
+
+/// How `search_files` matches
+#[derive(Clone, Copy)]
+pub struct SearchOptions {
+  /// The query is a regular expression rather than literal text
+  pub regex: bool,
+  pub case_sensitive: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct SearchMatch {
+  pub file_id: String,
+  pub path: String,
+  /// 1-based
+  pub line: usize,
+  /// Byte offset of the match in its line
+  pub column: usize,
+  /// The line, shortened around the match when long
+  pub snippet: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct SearchResults {
+  /// In file order, then line order
+  pub matches: Vec<SearchMatch>,
+  /// Files with at least one match
+  pub matched_files: usize,
+  /// More matches were found than returned
+  pub truncated: bool,
+  pub missing: Vec<String>,
+}
+
+/// The matcher for `query`
+pub fn matcher(query: &str, options: SearchOptions) -> Result<Regex, String> {
+  let pattern = if options.regex { query.to_string() } else { regex::escape(query) };
+  RegexBuilder::new(&pattern)
+    .case_insensitive(!options.case_sensitive)
+    .build()
+    .map_err(|e| format!("invalid search pattern: {e}"))
+}
+
+/// At most `SNIPPET_LEN` bytes of `line` around the match at `start..end`
+fn snippet(line: &str, start: usize, end: usize) -> String {
+  let line = line.trim_end_matches(['\r', '\n']);
+  if line.len() <= SNIPPET_LEN {
+    return line.to_string();
+  }
+  let context = SNIPPET_LEN.saturating_sub(end - start) / 2;
+  let mut from = start.saturating_sub(context);
+  while !line.is_char_boundary(from) {
+    from -= 1;
+  }
+  let mut to = (end + context).max(from + SNIPPET_LEN).min(line.len());
+  while !line.is_char_boundary(to) {
+    to += 1;
+  }
+  line[from..to].to_string()
+}
+
+fn search_file(file: &StoredFile, matcher: &Regex, limit: usize) -> Vec<SearchMatch> {
+  let mut matches = Vec::new();
+  for (index, line) in file.content.split_inclusive('\n').enumerate() {
+    for found in matcher.find_iter(line) {
+      if matches.len() == limit {
+        return matches;
+      }
+      matches.push(SearchMatch {
+        file_id: file.id.clone(),
+        path: file.label().to_string(),
+        line: index + 1,
+        column: found.start(),
+        snippet: snippet(line, found.start(), found.end()),
+      });
+    }
+  }
+  matches
+}
+
+/// Every match of `matcher` in `files`, searched in parallel, up to `limit`
+pub fn search(files: &[Arc<StoredFile>], missing: Vec<String>, matcher: &Regex, limit: usize) -> SearchResults {
+  // One more than the limit tells whether results were cut
+  let per_file: Vec<Vec<SearchMatch>> = files.par_iter().map(|file| search_file(file, matcher, limit + 1)).collect();
+  let matched_files = per_file.iter().filter(|matches| !matches.is_empty()).count();
+  let mut matches: Vec<SearchMatch> = per_file.into_iter().flatten().collect();
+  let truncated = matches.len() > limit;
+  matches.truncate(limit);
+  SearchResults {
+    matches,
+    matched_files,
+    truncated,
+    missing,
+  }
+}
+
+/// Ids of the `files` that contain a match of `matcher`, or with `invert`
+/// those that do not, in the order given
+pub fn filter(files: &[Arc<StoredFile>], matcher: &Regex, invert: bool) -> Vec<String> {
+  files
+    .par_iter()
+    .filter(|file| matcher.is_match(&file.content) != invert)
+    .map(|file| file.id.clone())
+    .collect()
+}
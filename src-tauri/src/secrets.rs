@@ -0,0 +1,77 @@
+/// What a masked value becomes
+const MASK: &str = "***";
+
+/// Whether `name` is a dotenv file: `.env`, `.env.local`, `prod.env`
+pub fn is_env_file(name: &str) -> bool {
+  let name = name.to_lowercase();
+  name == ".env" || name.starts_with(".env.") || name.ends_with(".env")
+}
+
+/// Whether `name` is a file that commonly holds credentials: dotenv files,
+/// package manager and network login files, and anything named `credentials`
+pub fn is_secret_file(name: &str) -> bool {
+  let lower = name.to_lowercase();
+  is_env_file(name) || [".npmrc", ".pypirc", ".netrc", ".pgpass"].contains(&lower.as_str()) || lower.contains("credentials")
+}
+
+/// The masked form of `value`, keeping its quotes and a trailing comma so the
+/// line still reads as the same syntax
+fn mask_value(value: &str) -> String {
+  let (value, comma) = match value.strip_suffix(',') {
+    Some(value) => (value.trim_end(), ","),
+    None => (value, ""),
+  };
+  match value.chars().next() {
+    Some(quote @ ('"' | '\'' | '`')) => format!("{quote}{MASK}{quote}{comma}"),
+    _ => format!("{MASK}{comma}"),
+  }
+}
+
+/// Whether a quoted `value` continues on the next lines
+fn opens_multiline(value: &str) -> Option<char> {
+  let quote = value.chars().next().filter(|c| matches!(c, '"' | '\'' | '`'))?;
+  let closed = value.len() > 1 && value.trim_end_matches(',').trim_end().ends_with(quote);
+  (!closed).then_some(quote)
+}
+
+/// Replace every value in `text` with `***`, keeping keys, sections, comments
+/// and layout. Understands `KEY=value` (dotenv, ini, `.npmrc`), `key: value`
+/// (YAML, JSON) and `export KEY=value` lines.
+pub fn mask_values(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  // The quote closing a multi-line value being skipped
+  let mut open_quote = None;
+  for line in text.split_inclusive('\n') {
+    let newline = &line[line.trim_end_matches(['\r', '\n']).len()..];
+    if let Some(quote) = open_quote {
+      if line.contains(quote) {
+        open_quote = None;
+      }
+      continue;
+    }
+
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with(['#', ';', '[']) || trimmed.starts_with("//") && !trimmed.contains('=') {
+      out.push_str(line);
+      continue;
+    }
+    // `.npmrc` keys hold a registry URL: `//registry.npmjs.org/:_authToken=`
+    let separator = if trimmed.starts_with("//") { line.find('=') } else { line.find(['=', ':']) };
+    let Some(separator) = separator else {
+      out.push_str(line);
+      continue;
+    };
+    let rest = &line[separator + 1..];
+    let value = rest.trim();
+    if value.is_empty() || matches!(value, "{" | "[") {
+      out.push_str(line);
+      continue;
+    }
+    open_quote = opens_multiline(value);
+    let value_start = separator + 1 + (rest.len() - rest.trim_start().len());
+    out.push_str(&line[..value_start]);
+    out.push_str(&mask_value(value));
+    out.push_str(newline);
+  }
+  out
+}
@@ -0,0 +1,116 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::settings::SelectionEndpoint;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_OUTLINE_LINES: usize = 40;
+const MAX_OUTLINE_LINE_CHARS: usize = 160;
+
+/// Declarations worth showing in an outline, across the common languages
+static DECLARATION: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r"^\s*(?:(?:pub(?:\([^)]*\))?|export|default|public|private|protected|internal|static|abstract|final|async|unsafe|extern|const|open|override|sealed|partial)\s+)*(?:fn|def|class|struct|enum|trait|impl|interface|type|function|module|mod|namespace|record|object|protocol|extension|func|sub|package)\b",
+  )
+  .expect("valid regex")
+});
+
+/// A loaded file offered for selection; only its path and outline leave the machine
+#[derive(serde::Deserialize)]
+pub struct SelectionInput {
+  pub id: String,
+  pub path: String,
+  pub content: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct RankedFile {
+  pub id: String,
+  pub path: String,
+  /// 1 is the most relevant file
+  pub rank: usize,
+}
+
+/// Declaration lines of `content`, trimmed, capped to keep prompts small
+pub fn outline(content: &str) -> Vec<String> {
+  content
+    .lines()
+    .filter(|line| DECLARATION.is_match(line))
+    .map(|line| line.trim().chars().take(MAX_OUTLINE_LINE_CHARS).collect())
+    .take(MAX_OUTLINE_LINES)
+    .collect()
+}
+
+fn prompt(task: &str, files: &[SelectionInput]) -> String {
+  let mut prompt = String::from(
+    "You are helping choose which files of a codebase are needed to work on a task.\n\
+     Below is the task, then every file path with an outline of its declarations.\n\
+     Reply with only a JSON array of the relevant file paths, most relevant first.\n\
+     Leave out files that are not needed.\n\n",
+  );
+  prompt.push_str("Task:\n");
+  prompt.push_str(task.trim());
+  prompt.push_str("\n\nFiles:\n");
+  for file in files {
+    prompt.push_str(&file.path);
+    prompt.push('\n');
+    for line in outline(&file.content) {
+      prompt.push_str("    ");
+      prompt.push_str(&line);
+      prompt.push('\n');
+    }
+  }
+  prompt
+}
+
+/// Send the request to an OpenAI-compatible chat completions endpoint and
+/// return the reply text
+fn complete(endpoint: &SelectionEndpoint, prompt: &str) -> Result<String, String> {
+  let mut request = ureq::post(&endpoint.url).timeout(REQUEST_TIMEOUT);
+  if let Some(key) = endpoint.api_key.as_deref() {
+    request = request.set("Authorization", &format!("Bearer {key}"));
+  }
+
+  let response: serde_json::Value = request
+    .send_json(serde_json::json!({
+      "model": endpoint.model,
+      "temperature": 0,
+      "messages": [{ "role": "user", "content": prompt }],
+    }))
+    .map_err(|e| format!("selection request failed: {e}"))?
+    .into_json()
+    .map_err(|e| format!("selection response is not JSON: {e}"))?;
+
+  response["choices"][0]["message"]["content"]
+    .as_str()
+    .map(str::to_string)
+    .ok_or_else(|| "selection response has no message content".to_string())
+}
+
+/// The JSON array of paths in a reply, tolerating surrounding prose or code fences
+fn parse_paths(reply: &str) -> Result<Vec<String>, String> {
+  let start = reply.find('[').ok_or("model reply does not contain a JSON array")?;
+  let end = reply.rfind(']').filter(|&end| end > start).ok_or("model reply does not contain a JSON array")?;
+  serde_json::from_str(&reply[start..=end]).map_err(|e| format!("model reply is not a list of paths: {e}"))
+}
+
+/// Ask the configured model which files matter for `task`. Paths the model
+/// invents or repeats are dropped; files it leaves out are not returned.
+pub fn rank(endpoint: &SelectionEndpoint, task: &str, files: &[SelectionInput]) -> Result<Vec<RankedFile>, String> {
+  let reply = complete(endpoint, &prompt(task, files))?;
+  let mut by_path: HashMap<&str, &SelectionInput> = files.iter().map(|f| (f.path.as_str(), f)).collect();
+
+  let ranked = parse_paths(&reply)?
+    .iter()
+    .filter_map(|path| by_path.remove(path.trim()))
+    .enumerate()
+    .map(|(index, file)| RankedFile {
+      id: file.id.clone(),
+      path: file.path.clone(),
+      rank: index + 1,
+    })
+    .collect();
+  Ok(ranked)
+}
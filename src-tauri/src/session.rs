@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::scan::ScanFilters;
+
+/// A loaded file as remembered between runs
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct SessionEntry {
+  pub id: String,
+  pub path: String,
+}
+
+/// The working set the frontend last reported
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct WorkingSet {
+  pub mode: String,
+  pub files: Vec<SessionEntry>,
+}
+
+/// An export setup saved to a file of the user's choosing with `save_session`
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SavedSession {
+  /// Files and directories that were dropped; scanned again on load
+  pub roots: Vec<String>,
+  /// Filters the roots were loaded with
+  pub filters: Option<ScanFilters>,
+  /// Paths of the files picked for export; `None` keeps every loaded file
+  pub selected: Option<Vec<String>>,
+  /// `path:ranges` line selections
+  pub selections: Vec<String>,
+  pub mode: String,
+  /// Processing options as the frontend passes them to processing
+  pub options: Option<serde_json::Value>,
+}
+
+impl SavedSession {
+  pub fn save(&self, path: &Path) -> Result<(), String> {
+    write_json(path, self)
+  }
+
+  pub fn load(path: &Path) -> Result<Self, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    serde_json::from_str(&raw).map_err(|e| format!("{} is not a saved session: {e}", path.display()))
+  }
+}
+
+/// Tauri-managed working set, written to `path` when the app exits
+pub struct WorkingSetState {
+  path: Option<PathBuf>,
+  current: Mutex<Option<WorkingSet>>,
+}
+
+impl WorkingSetState {
+  pub fn new(path: Option<PathBuf>) -> Self {
+    Self {
+      path,
+      current: Mutex::new(None),
+    }
+  }
+
+  pub fn set(&self, working_set: WorkingSet) {
+    *self.current.lock().unwrap_or_else(|e| e.into_inner()) = Some(working_set);
+  }
+
+  /// Persist the working set; nothing is written if the frontend never reported one
+  pub fn persist(&self) -> Result<(), String> {
+    let (Some(path), Some(working_set)) = (&self.path, self.current.lock().unwrap_or_else(|e| e.into_inner()).clone()) else {
+      return Ok(());
+    };
+    write_json(path, &working_set)
+  }
+
+  /// The working set saved by the previous run
+  pub fn load_last(&self) -> Result<WorkingSet, String> {
+    let path = self.path.as_ref().ok_or("app data directory unavailable")?;
+    let raw = fs::read_to_string(path).map_err(|e| format!("no previous session: {e}"))?;
+    serde_json::from_str(&raw).map_err(|e| format!("previous session is corrupt: {e}"))
+  }
+}
+
+/// A fresh id for a record created at `created_ms`: the time, then a random
+/// suffix so records created in the same millisecond differ, both in hex
+pub(crate) fn new_id(created_ms: u64) -> String {
+  let mut suffix = [0u8; 4];
+  if let Err(e) = getrandom::getrandom(&mut suffix) {
+    log::warn!("No randomness for a record id: {}", e);
+  }
+  let suffix: String = suffix.iter().map(|b| format!("{b:02x}")).collect();
+  format!("{created_ms:x}{suffix}")
+}
+
+pub(crate) fn write_json(path: &Path, value: &impl serde::Serialize) -> Result<(), String> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent).map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+  }
+  let raw = serde_json::to_string(value).map_err(|e| format!("failed to serialize session: {e}"))?;
+  fs::write(path, raw).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
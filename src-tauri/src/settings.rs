@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::http_api::HttpApiSettings;
+use crate::keychain;
+use crate::language::{self, LanguageConfig};
+use crate::limits::{self, Limits};
+use crate::scan::{self, ScanOptions};
+use crate::template::PromptTemplate;
+
+/// User preferences persisted as JSON in the app config directory
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+  /// Upper bound on worker threads used for processing and token counting;
+  /// `None` uses every available core.
+  pub max_threads: Option<usize>,
+  /// Enables exact Claude token counts via the Anthropic API. Like the other
+  /// keys and tokens, kept in the OS keychain and never serialized; settings
+  /// files from before are still read, and their keys moved over.
+  #[serde(skip_serializing)]
+  pub anthropic_api_key: Option<String>,
+  /// Enables exact Gemini token counts via the Gemini API
+  #[serde(skip_serializing)]
+  pub gemini_api_key: Option<String>,
+  /// Access tokens for fetching repositories through the GitHub and GitLab
+  /// APIs; private repositories need one, and they raise the rate limit
+  #[serde(skip_serializing)]
+  pub github_token: Option<String>,
+  #[serde(skip_serializing)]
+  pub gitlab_token: Option<String>,
+  /// Model used to pick relevant files for a task; unset disables the feature
+  pub selection_endpoint: Option<SelectionEndpoint>,
+  /// Scan files marked `linguist-generated` / `linguist-vendored` too
+  pub include_generated: bool,
+  /// Directory names walks skip (`node_modules`, `target`); `None` uses the
+  /// built-in list, an empty list skips none
+  pub excluded_dirs: Option<Vec<String>>,
+  /// Hugging Face `tokenizer.json` files used for exact counts, keyed by
+  /// lowercase model name or name prefix (`llama-3.1`)
+  pub tokenizer_files: BTreeMap<String, String>,
+  /// Input prices in USD per million tokens, keyed by lowercase model name
+  /// prefix; these take precedence over the built-in table
+  pub model_prices: BTreeMap<String, f64>,
+  /// Largest file read from disk, in bytes; `None` uses the built-in default
+  pub max_file_size: Option<usize>,
+  /// Most content, in bytes, one load reads into memory before it stops with
+  /// a warning; `None` uses the built-in default
+  pub max_load_bytes: Option<u64>,
+  /// Largest text comment removal and minification are applied to, in bytes;
+  /// `None` uses the built-in default
+  pub max_process_size: Option<usize>,
+  /// Comment syntax, extension aliases and text extensions added to the
+  /// built-in tables
+  pub languages: LanguageConfig,
+  /// Port and token of the local HTTP API; `None` keeps it off
+  pub http_api: Option<HttpApiSettings>,
+  /// Prompt templates exports can be laid out with, keyed by name
+  pub templates: BTreeMap<String, PromptTemplate>,
+  /// Rhai script whose `process(content, path, language)` runs on every
+  /// processed file after the built-in modes
+  pub process_script: Option<String>,
+}
+
+/// An OpenAI-compatible chat completions endpoint
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct SelectionEndpoint {
+  pub url: String,
+  pub model: String,
+  pub api_key: Option<String>,
+}
+
+/// Providers whose key or token `set_secret` stores, which are also the
+/// names of their keychain entries
+pub const SECRET_PROVIDERS: [&str; 4] = ["anthropic", "gemini", "github", "gitlab"];
+
+impl AppSettings {
+  /// The key or token of `provider`, one of `SECRET_PROVIDERS`
+  fn secret_mut(&mut self, provider: &str) -> Option<&mut Option<String>> {
+    match provider {
+      "anthropic" => Some(&mut self.anthropic_api_key),
+      "gemini" => Some(&mut self.gemini_api_key),
+      "github" => Some(&mut self.github_token),
+      "gitlab" => Some(&mut self.gitlab_token),
+      _ => None,
+    }
+  }
+
+  /// Fill the keys and tokens in from the keychain, first moving any the
+  /// settings file still holds into it. Returns whether one was moved.
+  fn load_secrets(&mut self) -> bool {
+    let mut moved = false;
+    for provider in SECRET_PROVIDERS {
+      let Some(secret) = self.secret_mut(provider) else {
+        continue;
+      };
+      match secret.as_deref() {
+        Some(plain) => match keychain::set(provider, Some(plain)) {
+          Ok(()) => moved = true,
+          Err(e) => log::warn!("Keeping the {} key in memory only: {}", provider, e),
+        },
+        None => *secret = keychain::get(provider),
+      }
+    }
+    moved
+  }
+
+  /// Number of worker threads to actually use
+  pub fn effective_threads(&self) -> usize {
+    let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+    self
+      .max_threads
+      .filter(|&n| n > 0)
+      .map_or(available, |n| n.min(available))
+  }
+
+  pub fn limits(&self) -> Limits {
+    Limits {
+      max_file_size: self.max_file_size.unwrap_or(limits::DEFAULT_MAX_FILE_SIZE),
+      max_process_size: self.max_process_size.unwrap_or(limits::DEFAULT_MAX_PROCESS_SIZE),
+    }
+  }
+
+  /// Directory names walks skip
+  pub fn excluded_dirs(&self) -> Vec<String> {
+    match &self.excluded_dirs {
+      Some(dirs) => dirs.clone(),
+      None => scan::DEFAULT_EXCLUDED_DIRS.iter().map(|dir| dir.to_string()).collect(),
+    }
+  }
+
+  /// The template called `name`, if one is given
+  pub fn template(&self, name: Option<&str>) -> Result<Option<PromptTemplate>, String> {
+    name
+      .map(|name| self.templates.get(name).cloned().ok_or_else(|| format!("no prompt template named {name}")))
+      .transpose()
+  }
+
+  pub fn scan_options(&self) -> ScanOptions {
+    let mut options = ScanOptions::default();
+    options.include_generated = self.include_generated;
+    options.excluded_dirs = self.excluded_dirs();
+    options.load_budget = scan::LoadBudget::new(self.max_load_bytes.unwrap_or(scan::DEFAULT_MAX_LOAD_BYTES));
+    options
+  }
+}
+
+/// Tauri-managed settings together with the file they are persisted to
+pub struct SettingsState {
+  path: Option<PathBuf>,
+  settings: Mutex<AppSettings>,
+}
+
+impl SettingsState {
+  /// Load settings from `path`, falling back to defaults when the file is
+  /// missing or unreadable.
+  pub fn load(path: Option<PathBuf>) -> Self {
+    let mut settings: AppSettings = path
+      .as_ref()
+      .and_then(|p| fs::read_to_string(p).ok())
+      .and_then(|raw| match serde_json::from_str(&raw) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+          log::warn!("Ignoring invalid settings file: {}", e);
+          None
+        }
+      })
+      .unwrap_or_default();
+    let moved = settings.load_secrets();
+    limits::apply(settings.limits());
+    language::apply(&settings.languages);
+
+    let state = Self {
+      path,
+      settings: Mutex::new(settings),
+    };
+    // Rewrite the file without the keys now in the keychain
+    if moved {
+      if let Err(e) = state.update(|_| {}) {
+        log::warn!("Failed to remove moved keys from the settings file: {}", e);
+      }
+    }
+    state
+  }
+
+  pub fn get(&self) -> AppSettings {
+    self.settings.lock().unwrap_or_else(|e| e.into_inner()).clone()
+  }
+
+  /// Apply `change` and persist the result
+  pub fn update(&self, change: impl FnOnce(&mut AppSettings)) -> Result<AppSettings, String> {
+    let mut settings = self.settings.lock().unwrap_or_else(|e| e.into_inner());
+    change(&mut settings);
+
+    if let Some(path) = &self.path {
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("failed to create settings directory: {e}"))?;
+      }
+      let raw = serde_json::to_string_pretty(&*settings).map_err(|e| format!("failed to serialize settings: {e}"))?;
+      fs::write(path, raw).map_err(|e| format!("failed to save settings: {e}"))?;
+    }
+
+    Ok(settings.clone())
+  }
+
+  /// Store (or clear, with `None`) the key or token of `provider` in the
+  /// keychain and in the settings in use
+  pub fn set_secret(&self, provider: &str, secret: Option<String>) -> Result<(), String> {
+    let mut settings = self.settings.lock().unwrap_or_else(|e| e.into_inner());
+    let current = settings.secret_mut(provider).ok_or_else(|| format!("unknown provider: {provider}"))?;
+    keychain::set(provider, secret.as_deref())?;
+    *current = secret;
+    Ok(())
+  }
+}
@@ -0,0 +1,227 @@
+use std::path::Path;
+
+/// `LinkFlags` bits of a shell link header
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x1;
+const HAS_LINK_INFO: u32 = 0x2;
+const HAS_NAME: u32 = 0x4;
+const HAS_RELATIVE_PATH: u32 = 0x8;
+const IS_UNICODE: u32 = 0x80;
+/// `LinkInfoFlags` bits
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+const COMMON_NETWORK_RELATIVE_LINK: u32 = 0x2;
+
+const LINK_HEADER_SIZE: usize = 0x4c;
+const LINK_CLSID: [u8; 16] = [
+  0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+  Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// NUL-terminated single-byte string at `offset`
+fn c_str(data: &[u8], offset: usize) -> Option<String> {
+  let bytes = data.get(offset..)?;
+  let end = bytes.iter().position(|&b| b == 0)?;
+  Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+/// NUL-terminated UTF-16 string at `offset`
+fn wide_str(data: &[u8], offset: usize) -> Option<String> {
+  let units: Vec<u16> = data
+    .get(offset..)?
+    .chunks_exact(2)
+    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+    .take_while(|&unit| unit != 0)
+    .collect();
+  Some(String::from_utf16_lossy(&units))
+}
+
+/// Target path recorded in a `LinkInfo` structure: a local path, or a share
+/// (`\\server\share`) with the rest of the path after it
+fn link_info_target(info: &[u8]) -> Option<String> {
+  let header_size = u32_at(info, 4)? as usize;
+  let flags = u32_at(info, 8)?;
+  let suffix_offset = u32_at(info, 24)? as usize;
+  // Headers of 0x24 bytes and up add Unicode versions of the strings
+  let unicode = header_size >= 0x24;
+  let suffix = if unicode {
+    wide_str(info, u32_at(info, 32)? as usize)
+  } else {
+    c_str(info, suffix_offset)
+  }
+  .unwrap_or_default();
+
+  if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+    let base = if unicode {
+      wide_str(info, u32_at(info, 28)? as usize)
+    } else {
+      c_str(info, u32_at(info, 16)? as usize)
+    }?;
+    return Some(format!("{base}{suffix}"));
+  }
+  if flags & COMMON_NETWORK_RELATIVE_LINK != 0 {
+    let network = info.get(u32_at(info, 20)? as usize..)?;
+    let net_name_offset = u32_at(network, 8)? as usize;
+    let net_name = if net_name_offset > 0x14 {
+      wide_str(network, u32_at(network, 20)? as usize)
+    } else {
+      c_str(network, net_name_offset)
+    }?;
+    return Some(if suffix.is_empty() {
+      net_name
+    } else {
+      format!("{net_name}\\{suffix}")
+    });
+  }
+  None
+}
+
+/// Target of a Windows shortcut (`.lnk`) file, per the Shell Link format:
+/// the absolute path in its link info, else its path relative to the
+/// shortcut's own folder
+fn lnk_target(data: &[u8], lnk: &Path) -> Option<String> {
+  if u32_at(data, 0)? as usize != LINK_HEADER_SIZE || data.get(4..20)? != LINK_CLSID {
+    return None;
+  }
+  let flags = u32_at(data, 20)?;
+  let mut offset = LINK_HEADER_SIZE;
+  if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+    offset += 2 + u16_at(data, offset)? as usize;
+  }
+  if flags & HAS_LINK_INFO != 0 {
+    let size = u32_at(data, offset)? as usize;
+    if let Some(target) = data.get(offset..offset + size).and_then(link_info_target) {
+      return Some(target);
+    }
+    offset += size;
+  }
+
+  // String data: a character count, then the characters without a terminator
+  let unit = if flags & IS_UNICODE != 0 { 2 } else { 1 };
+  let read_string = |offset: &mut usize| -> Option<String> {
+    let count = u16_at(data, *offset)? as usize;
+    let bytes = data.get(*offset + 2..*offset + 2 + count * unit)?;
+    *offset += 2 + count * unit;
+    Some(if unit == 2 {
+      String::from_utf16_lossy(&bytes.chunks_exact(2).map(|p| u16::from_le_bytes([p[0], p[1]])).collect::<Vec<_>>())
+    } else {
+      String::from_utf8_lossy(bytes).into_owned()
+    })
+  };
+  if flags & HAS_NAME != 0 {
+    read_string(&mut offset)?;
+  }
+  if flags & HAS_RELATIVE_PATH == 0 {
+    return None;
+  }
+  let relative = read_string(&mut offset)?.replace('\\', std::path::MAIN_SEPARATOR_STR);
+  Some(lnk.parent()?.join(relative).to_string_lossy().into_owned())
+}
+
+/// Target of a Finder alias, resolved by the system without asking the user
+/// or mounting volumes
+#[cfg(target_os = "macos")]
+fn alias_target(path: &Path) -> Option<String> {
+  use std::os::unix::ffi::OsStrExt;
+  use std::ptr;
+
+  use core_foundation_sys::base::{kCFAllocatorDefault, CFIndex, CFRelease};
+  use core_foundation_sys::url::{
+    kCFBookmarkResolutionWithoutMountingMask, kCFBookmarkResolutionWithoutUIMask, CFURLCreateBookmarkDataFromFile,
+    CFURLCreateByResolvingBookmarkData, CFURLCreateFromFileSystemRepresentation, CFURLGetFileSystemRepresentation,
+  };
+
+  // Alias files start with bookmark data; anything else is not worth asking about
+  let mut magic = [0u8; 4];
+  std::io::Read::read_exact(&mut std::fs::File::open(path).ok()?, &mut magic).ok()?;
+  if &magic != b"book" {
+    return None;
+  }
+
+  let bytes = path.as_os_str().as_bytes();
+  // SAFETY: every object created here is released once, after its last use,
+  // and the buffer handed to CoreFoundation outlives the call
+  unsafe {
+    let url = CFURLCreateFromFileSystemRepresentation(kCFAllocatorDefault, bytes.as_ptr(), bytes.len() as CFIndex, 0);
+    if url.is_null() {
+      return None;
+    }
+    let bookmark = CFURLCreateBookmarkDataFromFile(kCFAllocatorDefault, url, ptr::null_mut());
+    CFRelease(url.cast());
+    if bookmark.is_null() {
+      return None;
+    }
+    let resolved = CFURLCreateByResolvingBookmarkData(
+      kCFAllocatorDefault,
+      bookmark,
+      kCFBookmarkResolutionWithoutUIMask | kCFBookmarkResolutionWithoutMountingMask,
+      ptr::null(),
+      ptr::null(),
+      ptr::null_mut(),
+      ptr::null_mut(),
+    );
+    CFRelease(bookmark.cast());
+    if resolved.is_null() {
+      return None;
+    }
+    let mut buffer = [0u8; 4096];
+    let ok = CFURLGetFileSystemRepresentation(resolved, 1, buffer.as_mut_ptr(), buffer.len() as CFIndex);
+    CFRelease(resolved.cast());
+    if ok == 0 {
+      return None;
+    }
+    let end = buffer.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&buffer[..end]).into_owned())
+  }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn alias_target(_path: &Path) -> Option<String> {
+  None
+}
+
+/// `path` without the `\\?\` prefix Windows gives long and UNC paths, which
+/// other programs and the frontend do not expect: `\\?\C:\x` becomes `C:\x`
+/// and `\\?\UNC\server\share` becomes `\\server\share`
+fn normalize(path: &str) -> String {
+  if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+    format!(r"\\{share}")
+  } else if let Some(local) = path.strip_prefix(r"\\?\").filter(|rest| rest.get(1..2) == Some(":")) {
+    local.to_string()
+  } else {
+    path.to_string()
+  }
+}
+
+/// The file or folder a dropped path stands for: the target of a Windows
+/// shortcut or a Finder alias when it exists, else the path itself, in its
+/// normalized form
+pub fn resolve(path: &str) -> String {
+  let path = normalize(path);
+  let as_path = Path::new(&path);
+  let is_lnk = as_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("lnk"));
+  let target = if is_lnk {
+    std::fs::read(as_path).ok().and_then(|data| lnk_target(&data, as_path))
+  } else if as_path.is_file() {
+    alias_target(as_path)
+  } else {
+    None
+  };
+
+  match target.map(|target| normalize(&target)) {
+    Some(target) if Path::new(&target).exists() => {
+      log::info!("Resolved {} to {}", path, target);
+      target
+    }
+    Some(target) => {
+      log::warn!("Shortcut {} points to {}, which does not exist", path, target);
+      path
+    }
+    None => path,
+  }
+}
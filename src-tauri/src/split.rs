@@ -0,0 +1,83 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::store::StoredFile;
+
+/// Group name of the files directly in the root
+const ROOT_GROUP: &str = ".";
+
+/// How an export is split into one document per group of files
+#[derive(Clone, serde::Deserialize)]
+#[serde(default)]
+pub struct ExportSplit {
+  /// Leading directories of a path naming its group; 1 gives one document
+  /// per top-level directory
+  pub depth: usize,
+  /// Directories forming a group of their own whatever their depth, such as
+  /// `packages/api`; the longest one holding a file wins
+  pub groups: Vec<String>,
+}
+
+impl Default for ExportSplit {
+  fn default() -> Self {
+    Self {
+      depth: 1,
+      groups: Vec::new(),
+    }
+  }
+}
+
+impl ExportSplit {
+  /// The group of the file at `path`
+  fn group_of(&self, path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let named = self
+      .groups
+      .iter()
+      .map(|group| group.trim_matches('/'))
+      .filter(|group| !group.is_empty() && path.starts_with(&format!("{group}/")))
+      .max_by_key(|group| group.len());
+    if let Some(group) = named {
+      return group.to_string();
+    }
+    let dirs: Vec<&str> = path.split('/').collect();
+    let dirs = &dirs[..dirs.len() - 1];
+    match dirs.len().min(self.depth.max(1)) {
+      0 => ROOT_GROUP.to_string(),
+      depth => dirs[..depth].join("/"),
+    }
+  }
+
+  /// `files` by group, in the order each group's first file comes, keeping
+  /// their order within it
+  pub fn groups(&self, files: &[Arc<StoredFile>]) -> Vec<(String, Vec<Arc<StoredFile>>)> {
+    let mut groups: Vec<(String, Vec<Arc<StoredFile>>)> = Vec::new();
+    for file in files {
+      let group = self.group_of(file.label());
+      match groups.iter_mut().find(|(name, _)| *name == group) {
+        Some((_, members)) => members.push(Arc::clone(file)),
+        None => groups.push((group, vec![Arc::clone(file)])),
+      }
+    }
+    groups
+  }
+}
+
+/// Where the document of `group` goes when the export is written to `path`:
+/// `out/context.md` becomes `out/context.packages-api.md`
+pub fn part_path(path: &str, group: &str) -> String {
+  let slug: String = match group {
+    ROOT_GROUP => "root".to_string(),
+    group => group
+      .chars()
+      .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+      .collect(),
+  };
+  let path = Path::new(path);
+  let name = match (path.file_stem(), path.extension()) {
+    (Some(stem), Some(ext)) => format!("{}.{slug}.{}", stem.to_string_lossy(), ext.to_string_lossy()),
+    (Some(stem), None) => format!("{}.{slug}", stem.to_string_lossy()),
+    _ => slug,
+  };
+  path.with_file_name(name).to_string_lossy().to_string()
+}
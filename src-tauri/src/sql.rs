@@ -0,0 +1,23 @@
+use crate::lexer;
+
+/// `code` with each statement on one line: whitespace between tokens becomes
+/// a single space and a line break follows every `;`. Strings, quoted
+/// identifiers and `$$` bodies are kept as written. Comments are dropped, as
+/// a `--` comment joined onto its statement would swallow the rest of it.
+pub fn compact(code: &str) -> String {
+  let Some(syntax) = lexer::syntax_for("sql") else {
+    return code.to_string();
+  };
+  let stripped = lexer::strip_comments(code, syntax, |_| true);
+  let (protected, strings) = crate::protect_lexed_strings(&stripped, syntax);
+
+  let mut out = String::with_capacity(protected.len());
+  for statement in protected.split_inclusive(';') {
+    let statement = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !statement.is_empty() {
+      out.push_str(&statement);
+      out.push('\n');
+    }
+  }
+  crate::restore_strings(out.trim_end(), &strings)
+}
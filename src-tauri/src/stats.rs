@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::store::StoredFile;
+use crate::{combined, language, languages};
+
+/// File, line and byte counts of a set of files; every line is exactly one
+/// of blank, comment or code
+#[derive(Clone, Default, serde::Serialize)]
+pub struct LineCounts {
+  pub files: usize,
+  pub lines: usize,
+  pub blank: usize,
+  /// Lines holding nothing but comments or docstrings
+  pub comment: usize,
+  pub code: usize,
+  pub bytes: u64,
+}
+
+impl LineCounts {
+  fn add(&mut self, other: &LineCounts) {
+    self.files += other.files;
+    self.lines += other.lines;
+    self.blank += other.blank;
+    self.comment += other.comment;
+    self.code += other.code;
+    self.bytes += other.bytes;
+  }
+}
+
+#[derive(serde::Serialize)]
+pub struct LanguageStats {
+  pub language: String,
+  #[serde(flatten)]
+  pub counts: LineCounts,
+}
+
+/// Result of `compute_stats`
+#[derive(serde::Serialize)]
+pub struct StatsReport {
+  /// Most code lines first
+  pub languages: Vec<LanguageStats>,
+  pub total: LineCounts,
+  /// Requested ids with no loaded file
+  pub missing: Vec<String>,
+}
+
+/// Display name of the language `language::detect` names `key`, falling
+/// back to the comment table's name for the extension it resolves to
+fn language_name(key: Option<&str>) -> String {
+  match key.map_or("Text", combined::language_name) {
+    "Text" => key
+      .map(language::resolve)
+      .as_deref()
+      .and_then(languages::name_of)
+      .unwrap_or("Text")
+      .to_string(),
+    name => name.to_string(),
+  }
+}
+
+/// Counts for one file whose comments lie at `comments`, sorted by start
+fn count(content: &str, comments: &[Range<usize>]) -> LineCounts {
+  let mut counts = LineCounts {
+    files: 1,
+    bytes: content.len() as u64,
+    ..LineCounts::default()
+  };
+  // Comments before this index end before the current line
+  let mut next = 0;
+  let mut offset = 0;
+  for line in content.split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    counts.lines += 1;
+    if line.trim().is_empty() {
+      counts.blank += 1;
+      continue;
+    }
+    let first = start + line.len() - line.trim_start().len();
+    let last = start + line.trim_end().len();
+    while next < comments.len() && comments[next].end <= first {
+      next += 1;
+    }
+    // Comments found separately may overlap; any one covering the line will do
+    let covered = comments[next..]
+      .iter()
+      .take_while(|range| range.start <= first)
+      .any(|range| range.end >= last);
+    if covered {
+      counts.comment += 1;
+    } else {
+      counts.code += 1;
+    }
+  }
+  counts
+}
+
+/// Per-language counts of `files`, computed in parallel
+pub fn compute(files: &[Arc<StoredFile>], missing: Vec<String>) -> StatsReport {
+  let per_file: Vec<(String, LineCounts)> = files
+    .par_iter()
+    .map(|file| {
+      let key = language::detect(&file.name, &file.content);
+      let resolved = key.as_deref().map(language::resolve);
+      let comments = resolved.as_deref().map_or_else(Vec::new, |ext| crate::comment_ranges(&file.content, ext));
+      (language_name(key.as_deref()), count(&file.content, &comments))
+    })
+    .collect();
+
+  let mut by_language: HashMap<String, LineCounts> = HashMap::new();
+  let mut total = LineCounts::default();
+  for (language, counts) in per_file {
+    total.add(&counts);
+    by_language.entry(language).or_default().add(&counts);
+  }
+  let mut languages: Vec<LanguageStats> = by_language
+    .into_iter()
+    .map(|(language, counts)| LanguageStats { language, counts })
+    .collect();
+  languages.sort_by(|a, b| b.counts.code.cmp(&a.counts.code).then_with(|| a.language.cmp(&b.language)));
+
+  StatsReport {
+    languages,
+    total,
+    missing,
+  }
+}
@@ -0,0 +1,550 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tiktoken_rs::CoreBPE;
+
+use crate::binary::BinaryInfo;
+use crate::redact::RedactionCounts;
+use crate::scan::FileInfo;
+
+/// A processed file kept in the backend so later commands can refer to it by id
+/// instead of sending its content over IPC again
+pub struct StoredFile {
+  pub id: String,
+  pub name: String,
+  pub path: String,
+  pub content: Arc<str>,
+  /// blake3 hex digest of `content`
+  pub hash: String,
+  /// `FileInfo::relative_path`
+  pub relative_path: Option<String>,
+  /// How the content was processed; `None` for files as read
+  pub provenance: Option<Provenance>,
+  tokens: Mutex<Option<usize>>,
+}
+
+/// How a processed file was produced, for export summaries
+#[derive(Clone, Default)]
+pub struct Provenance {
+  /// Processing mode applied, after per-file overrides
+  pub mode: String,
+  /// Items masked per category, with `redact_pii`
+  pub redactions: Option<RedactionCounts>,
+  /// Values were masked as secrets
+  pub secrets_masked: bool,
+  /// blake3 hex digest of the content processed, to tell whether the file
+  /// changed since
+  pub source_hash: String,
+}
+
+impl StoredFile {
+  fn new(id: String, name: String, path: String, content: Arc<str>, tokens: Option<usize>) -> Self {
+    let hash = blake3::hash(content.as_bytes()).to_hex().to_string();
+    Self {
+      id,
+      name,
+      path,
+      content,
+      hash,
+      relative_path: None,
+      provenance: None,
+      tokens: Mutex::new(tokens),
+    }
+  }
+
+  /// Path below the dropped root if known, else the path, else the file name
+  pub fn label(&self) -> &str {
+    if let Some(relative) = &self.relative_path {
+      relative
+    } else if self.path.is_empty() {
+      &self.name
+    } else {
+      &self.path
+    }
+  }
+
+  /// Name of the dropped root the file was found under
+  pub fn root_name(&self) -> Option<&str> {
+    let relative = self.relative_path.as_deref()?;
+    Some(relative.split_once('/').map_or(relative, |(root, _)| root))
+  }
+
+  /// Give `info`, read again from this file's path, the root it was loaded under
+  pub fn keep_root(&self, info: &mut FileInfo) {
+    if info.relative_path.is_none() {
+      info.root = self.root_name().map(str::to_string);
+      info.relative_path = self.relative_path.clone();
+    }
+  }
+
+  /// This file at `path`, under the same id and with the same content
+  fn moved_to(&self, path: String) -> Self {
+    let relative_path = self
+      .relative_path
+      .as_deref()
+      .and_then(|relative| moved_relative_path(relative, &self.path, &path));
+    Self {
+      id: self.id.clone(),
+      name: Path::new(&path).file_name().map_or_else(|| self.name.clone(), |name| name.to_string_lossy().into_owned()),
+      path,
+      content: Arc::clone(&self.content),
+      hash: self.hash.clone(),
+      relative_path,
+      provenance: self.provenance.clone(),
+      tokens: Mutex::new(*self.tokens.lock().unwrap_or_else(|e| e.into_inner())),
+    }
+  }
+
+  /// Token count of the content, computed on first use
+  pub fn tokens(&self, encoder: &CoreBPE) -> usize {
+    let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+    *tokens.get_or_insert_with(|| encoder.encode_ordinary(&self.content).len())
+  }
+
+  /// At most `max_bytes` of the content, cut back to a character boundary
+  pub fn preview(&self, max_bytes: usize) -> FilePreview {
+    let mut end = max_bytes.min(self.content.len());
+    while !self.content.is_char_boundary(end) {
+      end -= 1;
+    }
+    FilePreview {
+      content: self.content[..end].to_string(),
+      truncated: end < self.content.len(),
+      size: self.content.len(),
+    }
+  }
+}
+
+/// `relative_path` of a file moved from `old_path` to `new_path`: below the
+/// same root while it stays under it
+fn moved_relative_path(relative: &str, old_path: &str, new_path: &str) -> Option<String> {
+  let old_path = old_path.replace('\\', "/");
+  let new_path = new_path.replace('\\', "/");
+  let Some((root_name, below)) = relative.split_once('/') else {
+    // The file was dropped itself and is known by its name
+    return new_path.rsplit('/').next().map(str::to_string);
+  };
+  let root = old_path.strip_suffix(below)?.strip_suffix('/')?;
+  let below = new_path.strip_prefix(root)?.strip_prefix('/')?;
+  Some(format!("{root_name}/{below}"))
+}
+
+/// A loaded file as sent to the frontend: everything but its content, which
+/// stays in the `FileStore` under `id`
+#[derive(Clone, serde::Serialize)]
+pub struct LoadedFile {
+  pub id: String,
+  pub name: String,
+  pub path: String,
+  pub is_text: bool,
+  pub size: u64,
+  pub modified_ms: Option<u64>,
+  /// `FileInfo::language`
+  pub language: Option<String>,
+  /// `FileInfo::generated`
+  pub generated: bool,
+  /// `FileInfo::root`
+  pub root: Option<String>,
+  /// `FileInfo::relative_path`
+  pub relative_path: Option<String>,
+  /// `FileInfo::binary`
+  pub binary: Option<BinaryInfo>,
+  /// Id of an earlier loaded file with byte-identical content
+  pub duplicate_of: Option<String>,
+  pub line_count: usize,
+  /// blake3 hex digest of the content, as read
+  pub content_hash: String,
+}
+
+/// The start of a loaded file, for showing before its full content is needed
+#[derive(serde::Serialize)]
+pub struct FilePreview {
+  pub content: String,
+  /// Whether `content` stops before the end of the file
+  pub truncated: bool,
+  /// Size of the whole content in bytes
+  pub size: usize,
+}
+
+/// How a freshly read file relates to the loaded file at its path
+pub enum Reread {
+  /// No file from its path is loaded
+  New,
+  /// The file loaded from its path has other content
+  Changed(Arc<StoredFile>),
+  /// The file loaded from its path has the same content
+  Unchanged,
+}
+
+/// `Reread` of `info` against `loaded`, the files by path of
+/// `FileStore::sources_by_path`
+pub fn reread(loaded: &HashMap<String, Arc<StoredFile>>, info: &FileInfo) -> Reread {
+  match loaded.get(&info.path) {
+    None => Reread::New,
+    Some(earlier) if earlier.hash == blake3::hash(info.content.as_bytes()).to_hex().as_str() => Reread::Unchanged,
+    Some(earlier) => Reread::Changed(Arc::clone(earlier)),
+  }
+}
+
+/// Files read again into a `FileStore` by `FileStore::reload`
+pub struct Reloaded {
+  /// Files new to the store or changed since they were loaded, as loaded now
+  pub files: Vec<LoadedFile>,
+  /// Of `files`, those loaded again under the id of their earlier version
+  pub updated: usize,
+  /// Paths of files loaded before with the same content, left as they were
+  pub unchanged: Vec<String>,
+}
+
+/// Tauri-managed store of loaded files, as read from disk, and of the latest
+/// processed version of each
+#[derive(Default)]
+pub struct FileStore {
+  /// Processed files, keyed by the id the processing run was given
+  files: Mutex<HashMap<String, Arc<StoredFile>>>,
+  /// Files as read from disk, keyed by the id assigned when loading them
+  sources: Mutex<HashMap<String, Arc<StoredFile>>>,
+  /// Id of the first loaded file with each content hash
+  originals: Mutex<HashMap<String, String>>,
+  next_id: AtomicU64,
+}
+
+/// The files of `map` under `ids`, in the given order, plus the ids it lacks
+fn lookup(map: &HashMap<String, Arc<StoredFile>>, ids: &[String]) -> (Vec<Arc<StoredFile>>, Vec<String>) {
+  let mut found = Vec::with_capacity(ids.len());
+  let mut missing = Vec::new();
+  for id in ids {
+    match map.get(id) {
+      Some(file) => found.push(Arc::clone(file)),
+      None => missing.push(id.clone()),
+    }
+  }
+  (found, missing)
+}
+
+impl FileStore {
+  /// Keep the content of freshly read files and return their metadata, with
+  /// the id under which each content can be retrieved. A file whose content
+  /// matches one still loaded is marked as its duplicate.
+  pub fn load(&self, files: Vec<FileInfo>) -> Vec<LoadedFile> {
+    self.load_as(files.into_iter().map(|info| (None, info)).collect())
+  }
+
+  /// `load`, keeping a file paired with an id under that id, in place of the
+  /// content loaded there before; its processed version stays until the file
+  /// is processed again
+  pub fn load_as(&self, files: Vec<(Option<String>, FileInfo)>) -> Vec<LoadedFile> {
+    let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+    let mut originals = self.originals.lock().unwrap_or_else(|e| e.into_inner());
+    files
+      .into_iter()
+      .map(|(id, info)| {
+        let id = id.unwrap_or_else(|| format!("file-{}", self.next_id.fetch_add(1, Ordering::Relaxed)));
+        let mut file = StoredFile::new(id.clone(), info.name, info.path, Arc::from(info.content), None);
+        file.relative_path = info.relative_path.clone();
+        // Empty files are all alike without being copies of each other
+        let duplicate_of = if file.content.is_empty() {
+          None
+        } else {
+          match originals.get(&file.hash) {
+            Some(original) if *original != id && sources.contains_key(original) => Some(original.clone()),
+            _ => {
+              originals.insert(file.hash.clone(), id.clone());
+              None
+            }
+          }
+        };
+        let loaded = LoadedFile {
+          id: id.clone(),
+          name: file.name.clone(),
+          path: file.path.clone(),
+          is_text: info.is_text,
+          size: info.size,
+          modified_ms: info.modified_ms,
+          language: info.language,
+          generated: info.generated,
+          root: info.root,
+          relative_path: info.relative_path,
+          binary: info.binary,
+          duplicate_of,
+          line_count: file.content.lines().count(),
+          content_hash: file.hash.clone(),
+        };
+        sources.insert(id, Arc::new(file));
+        loaded
+      })
+      .collect()
+  }
+
+  /// `load` for files that may have been loaded before: a file read again from
+  /// the path of a loaded file replaces it under its id when the content
+  /// changed and is left out otherwise
+  pub fn reload(&self, files: Vec<FileInfo>) -> Reloaded {
+    let loaded = self.sources_by_path();
+    let mut load = Vec::with_capacity(files.len());
+    let mut updated = 0;
+    let mut unchanged = Vec::new();
+    for mut info in files {
+      match reread(&loaded, &info) {
+        Reread::New => load.push((None, info)),
+        Reread::Changed(earlier) => {
+          earlier.keep_root(&mut info);
+          updated += 1;
+          load.push((Some(earlier.id.clone()), info));
+        }
+        Reread::Unchanged => unchanged.push(info.path),
+      }
+    }
+    Reloaded {
+      files: self.load_as(load),
+      updated,
+      unchanged,
+    }
+  }
+
+  /// Move the loaded files at `from`, and below it when it is a directory, to
+  /// the same place below `to`, keeping their ids. Returns the moved files.
+  pub fn rename(&self, from: &str, to: &str) -> Vec<Arc<StoredFile>> {
+    let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+    let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+    let mut moved = Vec::new();
+    for file in sources.values_mut() {
+      let Ok(below) = Path::new(&file.path).strip_prefix(from) else {
+        continue;
+      };
+      let path = if below.as_os_str().is_empty() {
+        to.to_string()
+      } else {
+        Path::new(to).join(below).to_string_lossy().into_owned()
+      };
+      if let Some(processed) = files.get_mut(&file.id) {
+        *processed = Arc::new(processed.moved_to(path.clone()));
+      }
+      *file = Arc::new(file.moved_to(path));
+      moved.push(Arc::clone(file));
+    }
+    moved
+  }
+
+  /// Forget the loaded files at `path`, and below it when it was a directory.
+  /// Returns their ids.
+  pub fn remove_path(&self, path: &str) -> Vec<String> {
+    let ids: Vec<String> = {
+      let sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+      sources
+        .values()
+        .filter(|file| Path::new(&file.path).starts_with(path))
+        .map(|file| file.id.clone())
+        .collect()
+    };
+    self.remove(&ids);
+    ids
+  }
+
+  /// The files currently loaded, by path
+  pub fn sources_by_path(&self) -> HashMap<String, Arc<StoredFile>> {
+    let sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+    sources.values().map(|file| (file.path.clone(), Arc::clone(file))).collect()
+  }
+
+  /// Ids of the files currently loaded, in load order
+  pub fn source_ids(&self) -> Vec<String> {
+    let sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+    let mut files: Vec<&Arc<StoredFile>> = sources.values().collect();
+    files.sort_by_key(|file| file.id.trim_start_matches("file-").parse::<u64>().unwrap_or(u64::MAX));
+    files.iter().map(|file| file.id.clone()).collect()
+  }
+
+  /// A loaded file as read from disk
+  pub fn source(&self, id: &str) -> Option<Arc<StoredFile>> {
+    self.sources.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
+  }
+
+  pub fn insert(
+    &self,
+    id: String,
+    name: String,
+    path: String,
+    content: &str,
+    tokens: Option<usize>,
+    provenance: Option<Provenance>,
+  ) {
+    let mut file = StoredFile::new(id.clone(), name, path, Arc::from(content), tokens);
+    file.provenance = provenance;
+    // Processed files keep the name they were loaded under
+    file.relative_path = self.source(&id).and_then(|source| source.relative_path.clone());
+    self.files.lock().unwrap_or_else(|e| e.into_inner()).insert(id, Arc::new(file));
+  }
+
+  /// Whether the processed version of the loaded file `id` was produced with
+  /// `mode` from the content now loaded
+  pub fn is_current(&self, id: &str, mode: &str) -> bool {
+    let (Some(source), Some(processed)) = (self.source(id), self.processed(id)) else {
+      return false;
+    };
+    processed
+      .provenance
+      .as_ref()
+      .is_some_and(|provenance| provenance.mode == mode && provenance.source_hash == source.hash)
+  }
+
+  /// The latest processed version of a file
+  pub fn processed(&self, id: &str) -> Option<Arc<StoredFile>> {
+    self.files.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
+  }
+
+  /// The stored files for `ids`, in the given order, plus the ids that are unknown
+  pub fn get_many(&self, ids: &[String]) -> (Vec<Arc<StoredFile>>, Vec<String>) {
+    lookup(&self.files.lock().unwrap_or_else(|e| e.into_inner()), ids)
+  }
+
+  /// `get_many` for the files as read from disk
+  pub fn get_sources(&self, ids: &[String]) -> (Vec<Arc<StoredFile>>, Vec<String>) {
+    lookup(&self.sources.lock().unwrap_or_else(|e| e.into_inner()), ids)
+  }
+
+  /// Forget files that are no longer loaded, both as read and as processed
+  pub fn remove(&self, ids: &[String]) {
+    let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+    let mut sources = self.sources.lock().unwrap_or_else(|e| e.into_inner());
+    for id in ids {
+      files.remove(id);
+      sources.remove(id);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn info(path: &str, content: &str) -> FileInfo {
+    FileInfo {
+      name: path.rsplit('/').next().unwrap_or(path).to_string(),
+      path: path.to_string(),
+      content: content.to_string(),
+      is_text: true,
+      size: content.len() as u64,
+      modified_ms: None,
+      language: None,
+      generated: false,
+      root: None,
+      relative_path: None,
+      binary: None,
+    }
+  }
+
+  #[test]
+  fn loaded_files_are_kept_under_their_ids() {
+    let store = FileStore::default();
+    let loaded = store.load(vec![info("/p/a.rs", "fn a() {}\n"), info("/p/b.rs", "fn b() {}\n")]);
+    assert_eq!(store.source_ids(), [loaded[0].id.clone(), loaded[1].id.clone()]);
+    assert_eq!(store.source(&loaded[1].id).map(|file| file.content.to_string()).as_deref(), Some("fn b() {}\n"));
+    assert_eq!(loaded[0].line_count, 1);
+  }
+
+  #[test]
+  fn identical_contents_are_marked_as_duplicates() {
+    let store = FileStore::default();
+    let loaded = store.load(vec![info("/p/a.rs", "same"), info("/p/b.rs", "same"), info("/p/c.rs", "")]);
+    assert_eq!(loaded[0].duplicate_of, None);
+    assert_eq!(loaded[1].duplicate_of.as_deref(), Some(loaded[0].id.as_str()));
+    assert_eq!(loaded[2].duplicate_of, None);
+  }
+
+  #[test]
+  fn reloading_under_an_id_replaces_the_content() {
+    let store = FileStore::default();
+    let id = store.load(vec![info("/p/a.rs", "old")]).remove(0).id;
+    store.load_as(vec![(Some(id.clone()), info("/p/a.rs", "new"))]);
+    assert_eq!(store.source(&id).map(|file| file.content.to_string()).as_deref(), Some("new"));
+    assert_eq!(store.source_ids(), [id]);
+  }
+
+  #[test]
+  fn processed_results_are_current_until_the_source_changes() {
+    let store = FileStore::default();
+    let id = store.load(vec![info("/p/a.rs", "let a = 1;")]).remove(0).id;
+    let source_hash = store.source(&id).map(|file| file.hash.clone()).unwrap_or_default();
+    let provenance = Provenance {
+      mode: "minify".to_string(),
+      source_hash,
+      ..Provenance::default()
+    };
+    store.insert(id.clone(), "a.rs".to_string(), "/p/a.rs".to_string(), "let a=1;", None, Some(provenance));
+    assert!(store.is_current(&id, "minify"));
+    assert!(!store.is_current(&id, "raw"));
+
+    store.load_as(vec![(Some(id.clone()), info("/p/a.rs", "let a = 2;"))]);
+    assert!(!store.is_current(&id, "minify"));
+  }
+
+  #[test]
+  fn reloading_a_path_keeps_its_id_and_skips_unchanged_files() {
+    let store = FileStore::default();
+    let id = store.load(vec![info("/p/a.rs", "old"), info("/p/b.rs", "b")]).remove(0).id;
+    let reloaded = store.reload(vec![info("/p/a.rs", "new"), info("/p/b.rs", "b"), info("/p/c.rs", "c")]);
+    let paths: Vec<&str> = reloaded.files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(paths, ["/p/a.rs", "/p/c.rs"]);
+    assert_eq!(reloaded.files[0].id, id);
+    assert_eq!(store.source_ids().len(), 3);
+    assert_eq!(store.source(&id).map(|file| file.content.to_string()).as_deref(), Some("new"));
+  }
+
+  #[test]
+  fn rereading_compares_against_the_file_loaded_from_the_same_path() {
+    let store = FileStore::default();
+    store.load(vec![info("/p/a.rs", "a")]);
+    let loaded = store.sources_by_path();
+    assert!(matches!(reread(&loaded, &info("/p/a.rs", "a")), Reread::Unchanged));
+    assert!(matches!(reread(&loaded, &info("/p/a.rs", "b")), Reread::Changed(_)));
+    assert!(matches!(reread(&loaded, &info("/p/b.rs", "a")), Reread::New));
+
+    let reloaded = store.reload(vec![info("/p/a.rs", "a"), info("/p/a.rs", "b"), info("/p/b.rs", "b")]);
+    assert_eq!((reloaded.files.len(), reloaded.updated), (2, 1));
+    assert_eq!(reloaded.unchanged, ["/p/a.rs"]);
+  }
+
+  #[test]
+  fn renamed_directories_move_their_files_under_the_same_ids() {
+    let store = FileStore::default();
+    let mut file = info("/p/src/a.rs", "a");
+    file.set_root(Path::new("/p"), "p");
+    let id = store.load(vec![file, info("/q/b.rs", "b")]).remove(0).id;
+
+    let moved = store.rename("/p/src", "/p/lib");
+    assert_eq!(moved.len(), 1);
+    let source = store.source(&id).expect("moved file is still loaded");
+    assert_eq!(source.path, Path::new("/p/lib").join("a.rs").to_string_lossy());
+    assert_eq!(source.relative_path.as_deref(), Some("p/lib/a.rs"));
+    assert_eq!(source.root_name(), Some("p"));
+  }
+
+  #[test]
+  fn removing_a_directory_forgets_the_files_below_it() {
+    let store = FileStore::default();
+    let loaded = store.load(vec![info("/p/src/a.rs", "a"), info("/p/src.rs", "b")]);
+    assert_eq!(store.remove_path("/p/src"), [loaded[0].id.clone()]);
+    assert_eq!(store.source_ids(), [loaded[1].id.clone()]);
+  }
+
+  #[test]
+  fn forgotten_files_are_gone_as_read_and_as_processed() {
+    let store = FileStore::default();
+    let ids: Vec<String> = store
+      .load(vec![info("/p/a.rs", "a"), info("/p/b.rs", "b")])
+      .into_iter()
+      .map(|file| file.id)
+      .collect();
+    store.insert(ids[0].clone(), "a.rs".to_string(), "/p/a.rs".to_string(), "a", None, None);
+
+    store.remove(&ids[..1]);
+    assert!(store.source(&ids[0]).is_none());
+    assert!(store.processed(&ids[0]).is_none());
+    let (found, missing) = store.get_sources(&ids);
+    assert_eq!(found.len(), 1);
+    assert_eq!(missing, [ids[0].clone()]);
+  }
+}
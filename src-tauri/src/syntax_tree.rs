@@ -0,0 +1,94 @@
+use tree_sitter::{Language, Node, Parser, Tree};
+
+/// Tree-sitter grammar for `ext`, lowercase and without the dot
+fn language(ext: &str) -> Option<Language> {
+  let language = match ext {
+    "rs" => tree_sitter_rust::LANGUAGE,
+    "py" | "pyw" | "pyi" => tree_sitter_python::LANGUAGE,
+    "js" | "mjs" | "cjs" | "jsx" => tree_sitter_javascript::LANGUAGE,
+    "ts" | "mts" | "cts" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+    "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+    "go" => tree_sitter_go::LANGUAGE,
+    "java" => tree_sitter_java::LANGUAGE,
+    "c" | "h" => tree_sitter_c::LANGUAGE,
+    "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => tree_sitter_cpp::LANGUAGE,
+    "cs" => tree_sitter_c_sharp::LANGUAGE,
+    "css" => tree_sitter_css::LANGUAGE,
+    "html" | "htm" => tree_sitter_html::LANGUAGE,
+    "php" => tree_sitter_php::LANGUAGE_PHP,
+    "rb" => tree_sitter_ruby::LANGUAGE,
+    "sh" | "bash" => tree_sitter_bash::LANGUAGE,
+    _ => return None,
+  };
+  Some(language.into())
+}
+
+/// Whether there is a grammar for `ext`
+pub fn supports(ext: &str) -> bool {
+  language(ext).is_some()
+}
+
+/// Parse `code` as the language of `ext`. `None` without a grammar, or when
+/// the code does not parse cleanly and a best-effort tree would mislead.
+pub fn parse(code: &str, ext: &str) -> Option<Tree> {
+  let mut parser = Parser::new();
+  parser.set_language(&language(ext)?).ok()?;
+  parser.parse(code, None).filter(|tree| !tree.root_node().has_error())
+}
+
+/// A Python statement made of a string alone
+fn is_docstring(node: Node) -> bool {
+  node.kind() == "expression_statement"
+    && node.named_child_count() == 1
+    && node
+      .named_child(0)
+      .is_some_and(|expr| matches!(expr.kind(), "string" | "concatenated_string"))
+}
+
+/// Byte ranges of the comments in `tree`, and of docstrings in Python
+fn comment_ranges(tree: &Tree, python: bool) -> Vec<std::ops::Range<usize>> {
+  let mut ranges = Vec::new();
+  let mut cursor = tree.walk();
+  loop {
+    let node = cursor.node();
+    let comment = node.kind().contains("comment") || (python && is_docstring(node));
+    if comment {
+      ranges.push(node.byte_range());
+    }
+    if comment || !cursor.goto_first_child() {
+      while !cursor.goto_next_sibling() {
+        if !cursor.goto_parent() {
+          return ranges;
+        }
+      }
+    }
+  }
+}
+
+/// Byte ranges of the comments (and, for Python, docstrings) in `code`, in
+/// order. `None` when `ext` has no grammar or `code` does not parse.
+pub fn comments(code: &str, ext: &str) -> Option<Vec<std::ops::Range<usize>>> {
+  let tree = parse(code, ext)?;
+  Some(comment_ranges(&tree, matches!(ext, "py" | "pyw" | "pyi")))
+}
+
+/// Remove the comments (and, for Python, docstrings) that `removes` accepts,
+/// located on the parse tree so strings can never be mistaken for them.
+/// `None` when `ext` has no grammar or `code` does not parse.
+pub fn strip_comments(code: &str, ext: &str, removes: impl Fn(&str) -> bool) -> Option<String> {
+  let tree = parse(code, ext)?;
+  let python = matches!(ext, "py" | "pyw" | "pyi");
+  let mut out = String::with_capacity(code.len());
+  let mut copied = 0;
+  for range in comment_ranges(&tree, python) {
+    // Line comments may own their newline; the line break stays
+    let text = code[range.clone()].trim_end_matches(['\r', '\n']);
+    if range.start < copied || !removes(text) {
+      continue;
+    }
+    out.push_str(&code[copied..range.start]);
+    copied = range.start + text.len();
+  }
+  out.push_str(&code[copied..]);
+  Some(out)
+}
@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+
+/// Most distinct values counted per column before giving up on the count
+const MAX_DISTINCT: usize = 1000;
+
+/// How CSV and TSV files are cut down
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(default)]
+pub struct TableSummary {
+  /// Rows kept after the header
+  pub head_rows: usize,
+  /// Rows kept at the end
+  pub tail_rows: usize,
+  /// Start with the row count and each column's type, empty count and range
+  pub column_stats: bool,
+}
+
+impl Default for TableSummary {
+  fn default() -> Self {
+    Self {
+      head_rows: 10,
+      tail_rows: 5,
+      column_stats: false,
+    }
+  }
+}
+
+fn delimiter(ext: &str) -> Option<char> {
+  match ext {
+    "csv" => Some(','),
+    "tsv" => Some('\t'),
+    _ => None,
+  }
+}
+
+/// The records of `content`, each with its line break. A quoted field may
+/// span lines, so records are not simply lines.
+fn records(content: &str) -> Vec<&str> {
+  let mut records = Vec::new();
+  let mut start = 0;
+  let mut quoted = false;
+  for (i, byte) in content.bytes().enumerate() {
+    match byte {
+      // A doubled quote inside a quoted field toggles twice
+      b'"' => quoted = !quoted,
+      b'\n' if !quoted => {
+        records.push(&content[start..=i]);
+        start = i + 1;
+      }
+      _ => {}
+    }
+  }
+  if start < content.len() {
+    records.push(&content[start..]);
+  }
+  records
+}
+
+/// The fields of one record, unquoted
+fn fields(record: &str, delimiter: char) -> Vec<String> {
+  let record = record.trim_end_matches(['\n', '\r']);
+  let mut fields = Vec::new();
+  let mut field = String::new();
+  let mut quoted = false;
+  let mut chars = record.chars().peekable();
+  while let Some(ch) = chars.next() {
+    match ch {
+      '"' if quoted && chars.peek() == Some(&'"') => {
+        field.push('"');
+        chars.next();
+      }
+      '"' => quoted = !quoted,
+      ch if ch == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+      ch => field.push(ch),
+    }
+  }
+  fields.push(field);
+  fields
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+  Integer,
+  Number,
+  Boolean,
+  Date,
+  Text,
+}
+
+impl Kind {
+  fn of(value: &str) -> Self {
+    let bytes = value.as_bytes();
+    if value.parse::<i64>().is_ok() {
+      Kind::Integer
+    } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+      Kind::Number
+    } else if ["true", "false"].iter().any(|b| value.eq_ignore_ascii_case(b)) {
+      Kind::Boolean
+    } else if bytes.len() >= 10
+      && bytes[..10].iter().enumerate().all(|(i, b)| match i {
+        4 | 7 => *b == b'-',
+        _ => b.is_ascii_digit(),
+      })
+    {
+      Kind::Date
+    } else {
+      Kind::Text
+    }
+  }
+
+  /// The kind a column holding values of both kinds has
+  fn widen(self, other: Kind) -> Kind {
+    match (self, other) {
+      (a, b) if a == b => a,
+      (Kind::Integer, Kind::Number) | (Kind::Number, Kind::Integer) => Kind::Number,
+      _ => Kind::Text,
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Kind::Integer => "integer",
+      Kind::Number => "number",
+      Kind::Boolean => "boolean",
+      Kind::Date => "date",
+      Kind::Text => "text",
+    }
+  }
+}
+
+#[derive(Default)]
+struct Column {
+  kind: Option<Kind>,
+  empty: usize,
+  min: Option<f64>,
+  max: Option<f64>,
+  sum: f64,
+  numbers: usize,
+  /// Smallest and largest value as text, for dates
+  first: Option<String>,
+  last: Option<String>,
+  distinct: HashSet<String>,
+}
+
+impl Column {
+  fn add(&mut self, value: &str) {
+    let value = value.trim();
+    if value.is_empty() {
+      self.empty += 1;
+      return;
+    }
+    let kind = Kind::of(value);
+    self.kind = Some(self.kind.map_or(kind, |current| current.widen(kind)));
+    if let (Kind::Integer | Kind::Number, Ok(number)) = (kind, value.parse::<f64>()) {
+      self.min = Some(self.min.map_or(number, |min| min.min(number)));
+      self.max = Some(self.max.map_or(number, |max| max.max(number)));
+      self.sum += number;
+      self.numbers += 1;
+    }
+    if self.first.as_deref().map_or(true, |first| value < first) {
+      self.first = Some(value.to_string());
+    }
+    if self.last.as_deref().map_or(true, |last| value > last) {
+      self.last = Some(value.to_string());
+    }
+    if self.distinct.len() < MAX_DISTINCT {
+      self.distinct.insert(value.to_string());
+    }
+  }
+
+  fn describe(&self, name: &str) -> String {
+    let kind = self.kind.unwrap_or(Kind::Text);
+    let mut line = format!("# {name}: {}", kind.name());
+    match (kind, self.min, self.max) {
+      (Kind::Integer | Kind::Number, Some(min), Some(max)) => {
+        line.push_str(&format!(", {min}..{max}, mean {:.2}", self.sum / self.numbers as f64));
+      }
+      (Kind::Date, _, _) => {
+        if let (Some(first), Some(last)) = (&self.first, &self.last) {
+          line.push_str(&format!(", {first}..{last}"));
+        }
+      }
+      _ => {
+        let distinct = self.distinct.len();
+        let plus = if distinct >= MAX_DISTINCT { "+" } else { "" };
+        line.push_str(&format!(", {distinct}{plus} distinct"));
+      }
+    }
+    if self.empty > 0 {
+      line.push_str(&format!(", {} empty", self.empty));
+    }
+    line.push('\n');
+    line
+  }
+}
+
+/// The row count and a line per column of `header`, over every row
+fn column_stats(header: &str, rows: &[&str], delimiter: char) -> String {
+  let names = fields(header, delimiter);
+  let mut columns: Vec<Column> = names.iter().map(|_| Column::default()).collect();
+  for row in rows {
+    for (column, value) in columns.iter_mut().zip(fields(row, delimiter)) {
+      column.add(&value);
+    }
+  }
+  let mut stats = format!("# {} rows, {} columns\n", rows.len(), names.len());
+  for (name, column) in names.iter().zip(&columns) {
+    stats.push_str(&column.describe(name));
+  }
+  stats
+}
+
+/// A CSV or TSV file (by extension `ext`) cut down to its header, the first
+/// and last rows and a marker for those left out, after the column
+/// summary when asked for. `None` for other files, and for tables short
+/// enough to keep whole without a summary.
+pub fn summarize(ext: &str, content: &str, options: &TableSummary) -> Option<String> {
+  let delimiter = delimiter(ext)?;
+  let records = records(content);
+  let (header, rows) = records.split_first()?;
+  let omitted = rows.len().saturating_sub(options.head_rows + options.tail_rows);
+  if omitted == 0 && !options.column_stats {
+    return None;
+  }
+
+  let mut summary = if options.column_stats {
+    column_stats(header, rows, delimiter)
+  } else {
+    String::new()
+  };
+  summary.push_str(header);
+  if omitted == 0 {
+    rows.iter().for_each(|row| summary.push_str(row));
+  } else {
+    rows[..options.head_rows].iter().for_each(|row| summary.push_str(row));
+    if !summary.ends_with('\n') {
+      summary.push('\n');
+    }
+    summary.push_str(&format!("... {omitted} rows omitted ...\n"));
+    rows[rows.len() - options.tail_rows..].iter().for_each(|row| summary.push_str(row));
+  }
+  Some(summary)
+}
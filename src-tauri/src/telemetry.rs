@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long one file took to process
+#[derive(Clone, serde::Serialize)]
+pub struct FileTiming {
+  pub id: String,
+  pub path: String,
+  pub bytes: u64,
+  pub duration_ms: f64,
+}
+
+/// Timings of the last `process_files_with_progress` run
+struct Run {
+  files: Vec<FileTiming>,
+  wall: Duration,
+  cancelled: bool,
+}
+
+/// Result of `get_processing_report`
+#[derive(serde::Serialize)]
+pub struct ProcessingReport {
+  /// Most time first
+  pub slowest: Vec<FileTiming>,
+  pub files_count: usize,
+  pub total_bytes: u64,
+  /// From the start of the run until its last file was collected
+  pub wall_ms: f64,
+  /// Time the worker threads spent processing, summed over files; well above
+  /// `wall_ms` when the threads were kept busy
+  pub processing_ms: f64,
+  /// Of wall time
+  pub bytes_per_second: f64,
+  pub cancelled: bool,
+}
+
+/// Tauri-managed timings of the last processing run. Contents are already in
+/// memory when processing starts, so these cover processing alone; reading
+/// from disk happens when files are loaded.
+#[derive(Default)]
+pub struct ProcessingTelemetry {
+  last: Mutex<Option<Run>>,
+}
+
+impl ProcessingTelemetry {
+  pub fn record(&self, files: Vec<FileTiming>, wall: Duration, cancelled: bool) {
+    *self.last.lock().unwrap_or_else(|e| e.into_inner()) = Some(Run { files, wall, cancelled });
+  }
+
+  /// The last run with its `limit` slowest files; `None` before any run
+  pub fn report(&self, limit: usize) -> Option<ProcessingReport> {
+    let last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+    let run = last.as_ref()?;
+    let total_bytes = run.files.iter().map(|file| file.bytes).sum();
+    let wall = run.wall.as_secs_f64();
+    let mut slowest = run.files.clone();
+    slowest.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+    slowest.truncate(limit);
+    Some(ProcessingReport {
+      slowest,
+      files_count: run.files.len(),
+      total_bytes,
+      wall_ms: wall * 1000.0,
+      processing_ms: run.files.iter().map(|file| file.duration_ms).sum(),
+      bytes_per_second: if wall > 0.0 { total_bytes as f64 / wall } else { 0.0 },
+      cancelled: run.cancelled,
+    })
+  }
+}
+
+/// How far back the throughput of a run is averaged, so the estimate follows
+/// changes in file mix without jumping with every file
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(5);
+
+/// Rolling average of the bytes processed per second during a run
+pub struct Throughput {
+  started: Instant,
+  /// `(when, bytes processed by then)`, oldest first
+  samples: VecDeque<(Instant, u64)>,
+}
+
+impl Throughput {
+  pub fn new(started: Instant) -> Self {
+    Self {
+      started,
+      samples: VecDeque::from([(started, 0)]),
+    }
+  }
+
+  /// Record the bytes processed so far; returns the elapsed time, the
+  /// average rate over the window and the estimated time to process
+  /// `total_bytes`, which is unknown until bytes have been processed
+  pub fn update(&mut self, processed_bytes: u64, total_bytes: u64) -> (Duration, f64, Option<Duration>) {
+    let now = Instant::now();
+    self.samples.push_back((now, processed_bytes));
+    // Keep one sample at or before the window start to measure from
+    while self.samples.len() > 2 && self.samples[1].0 + THROUGHPUT_WINDOW <= now {
+      self.samples.pop_front();
+    }
+    let (since, bytes_then) = self.samples[0];
+    let seconds = now.duration_since(since).as_secs_f64();
+    let rate = if seconds > 0.0 { processed_bytes.saturating_sub(bytes_then) as f64 / seconds } else { 0.0 };
+    let remaining = total_bytes.saturating_sub(processed_bytes);
+    let eta = match remaining {
+      0 => Some(Duration::ZERO),
+      _ if rate > 0.0 => Some(Duration::from_secs_f64(remaining as f64 / rate)),
+      _ => None,
+    };
+    (now.duration_since(self.started), rate, eta)
+  }
+}
@@ -0,0 +1,58 @@
+/// Layout used when a template leaves its body empty
+pub const DEFAULT_BODY: &str = "{{header}}\n\n{{tree}}\n\n{{files}}\n\n{{footer}}\n";
+
+/// A saved layout for exports. `{{tree}}`, `{{files}}`, `{{file_count}}`,
+/// `{{total_tokens}}`, `{{header}}` and `{{footer}}` in the body are filled in
+/// by the export builder; anything else is kept as written.
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PromptTemplate {
+  /// Text with placeholders; empty uses `DEFAULT_BODY`
+  pub body: String,
+  /// Instructions put in place of `{{header}}` and `{{footer}}`
+  pub header: String,
+  pub footer: String,
+}
+
+/// Values of the placeholders for one export
+pub struct TemplateValues<'a> {
+  /// Directory tree section, empty when the export has none
+  pub tree: &'a str,
+  pub files: &'a str,
+  pub file_count: usize,
+  pub total_tokens: usize,
+}
+
+impl PromptTemplate {
+  /// The body with every known placeholder replaced. Substituted text is not
+  /// scanned again, so file contents that contain `{{...}}` stay intact.
+  pub fn render(&self, values: &TemplateValues) -> String {
+    let body = if self.body.trim().is_empty() { DEFAULT_BODY } else { &self.body };
+    let mut out = String::with_capacity(body.len() + values.tree.len() + values.files.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+      out.push_str(&rest[..start]);
+      let after = &rest[start + 2..];
+      let Some(end) = after.find("}}") else {
+        rest = &rest[start..];
+        break;
+      };
+      match after[..end].trim() {
+        "tree" => out.push_str(values.tree),
+        "files" => out.push_str(values.files),
+        "file_count" => out.push_str(&values.file_count.to_string()),
+        "total_tokens" => out.push_str(&values.total_tokens.to_string()),
+        "header" => out.push_str(&self.header),
+        "footer" => out.push_str(&self.footer),
+        _ => out.push_str(&rest[start..start + end + 4]),
+      }
+      rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+
+    // An empty header or footer should not leave blank lines at either end
+    let mut text = out.trim_matches(['\n', '\r']).to_string();
+    text.push('\n');
+    text
+  }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::settings::AppSettings;
+use crate::{generated, lockfile};
+use crate::store::StoredFile;
+use crate::tokens::{self, TokenModel, TOKENIZER};
+
+#[derive(serde::Serialize)]
+pub struct FileTokens {
+  pub id: String,
+  pub path: String,
+  pub tokens: usize,
+}
+
+/// Tokens of every file below a directory, at any depth
+#[derive(Default, serde::Serialize)]
+pub struct DirectoryTokens {
+  /// `""` for the root, which holds every file
+  pub path: String,
+  pub files: usize,
+  pub tokens: usize,
+}
+
+/// Result of `token_report`
+#[derive(serde::Serialize)]
+pub struct TokenReport {
+  /// In the requested order
+  pub files: Vec<FileTokens>,
+  /// Sorted by path, so each directory directly follows its parent
+  pub directories: Vec<DirectoryTokens>,
+  pub total: usize,
+  /// Requested ids with no loaded file
+  pub missing: Vec<String>,
+}
+
+/// Why a large file is likely safe to exclude
+#[derive(serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outlier {
+  Lockfile,
+  /// Build output, generator output or minified code
+  Generated,
+}
+
+#[derive(serde::Serialize)]
+pub struct Contributor {
+  pub id: String,
+  pub path: String,
+  pub tokens: usize,
+  /// Share of the total tokens of the requested files
+  pub percent: f64,
+  pub outliers: Vec<Outlier>,
+}
+
+/// Result of `largest_contributors`
+#[derive(serde::Serialize)]
+pub struct Contributors {
+  /// Most tokens first
+  pub files: Vec<Contributor>,
+  /// Of every requested file, not just the listed ones
+  pub total: usize,
+  pub missing: Vec<String>,
+}
+
+/// The directories above `path`, innermost first, ending with the root `""`
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+  path
+    .rmatch_indices('/')
+    .map(move |(i, _)| &path[..i])
+    .filter(|directory| !directory.is_empty())
+    .chain(std::iter::once(""))
+}
+
+/// Token count of `file` as `model` sees it; the default encoding uses the
+/// count cached on the file
+fn count(file: &StoredFile, model: &TokenModel, settings: &AppSettings) -> Result<usize, String> {
+  match model {
+    TokenModel::O200k => Ok(file.tokens(TOKENIZER.as_ref().map_err(|e| e.clone())?)),
+    model => tokens::count(model, &file.content, settings, false),
+  }
+}
+
+/// Token counts of `files` and of the directories they are in, counted in
+/// parallel
+pub fn build(files: &[Arc<StoredFile>], missing: Vec<String>, model: &TokenModel, settings: &AppSettings) -> Result<TokenReport, String> {
+  let files: Vec<FileTokens> = files
+    .par_iter()
+    .map(|file| {
+      Ok(FileTokens {
+        id: file.id.clone(),
+        path: file.label().replace('\\', "/"),
+        tokens: count(file, model, settings)?,
+      })
+    })
+    .collect::<Result<_, String>>()?;
+
+  let mut directories: BTreeMap<&str, DirectoryTokens> = BTreeMap::new();
+  for file in &files {
+    for directory in ancestors(&file.path) {
+      let entry = directories.entry(directory).or_insert_with(|| DirectoryTokens {
+        path: directory.to_string(),
+        ..DirectoryTokens::default()
+      });
+      entry.files += 1;
+      entry.tokens += file.tokens;
+    }
+  }
+  let directories = directories.into_values().collect();
+
+  Ok(TokenReport {
+    total: files.iter().map(|file| file.tokens).sum(),
+    files,
+    directories,
+    missing,
+  })
+}
+
+/// What makes `file`, as read before processing, an outlier
+fn outliers(file: &StoredFile, source: Option<&StoredFile>) -> Vec<Outlier> {
+  let content = source.map_or(&file.content, |source| &source.content);
+  let mut outliers = Vec::new();
+  if lockfile::is_lockfile(&file.name) {
+    outliers.push(Outlier::Lockfile);
+  } else if generated::is_generated(&file.name, content) {
+    outliers.push(Outlier::Generated);
+  }
+  outliers
+}
+
+/// The `limit` files of `files` with the most tokens, counted in parallel;
+/// `sources` holds the files as read, matched by id, for outlier checks
+pub fn largest(
+  files: &[Arc<StoredFile>],
+  sources: &[Arc<StoredFile>],
+  missing: Vec<String>,
+  limit: usize,
+  model: &TokenModel,
+  settings: &AppSettings,
+) -> Result<Contributors, String> {
+  let mut counted: Vec<(&Arc<StoredFile>, usize)> = files
+    .par_iter()
+    .map(|file| Ok((file, count(file, model, settings)?)))
+    .collect::<Result<_, String>>()?;
+  let total: usize = counted.iter().map(|(_, tokens)| tokens).sum();
+  counted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.label().cmp(b.0.label())));
+
+  let files = counted
+    .into_iter()
+    .take(limit)
+    .map(|(file, tokens)| {
+      let source = sources.iter().find(|source| source.id == file.id);
+      Contributor {
+        id: file.id.clone(),
+        path: file.label().replace('\\', "/"),
+        tokens,
+        percent: if total == 0 { 0.0 } else { tokens as f64 / total as f64 * 100.0 },
+        outliers: outliers(file, source.map(Arc::as_ref)),
+      }
+    })
+    .collect();
+
+  Ok(Contributors { files, total, missing })
+}
@@ -0,0 +1,414 @@
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tiktoken_rs::{cl100k_base, o200k_base, CoreBPE};
+use tokenizers::Tokenizer;
+
+use crate::settings::AppSettings;
+
+pub static TOKENIZER: Lazy<Result<Arc<CoreBPE>, String>> = Lazy::new(|| {
+  o200k_base()
+    .or_else(|_| cl100k_base())
+    .map(Arc::new)
+    .map_err(|e| format!("failed to load tokenizer: {e}"))
+});
+
+static CL100K: Lazy<Result<Arc<CoreBPE>, String>> = Lazy::new(|| {
+  cl100k_base()
+    .map(Arc::new)
+    .map_err(|e| format!("failed to load cl100k tokenizer: {e}"))
+});
+
+/// Offline approximation ratios, measured on a mixed sample of source code.
+/// They are only estimates; configure an API key for exact counts.
+const CLAUDE_PER_CL100K: f64 = 1.16;
+const GEMINI_PER_O200K: f64 = 1.05;
+
+/// Offline ratios for open-weight model families, relative to cl100k, used
+/// when no `tokenizer.json` is registered for the model. Longer prefixes are
+/// listed before shorter ones they start with.
+const FAMILY_RATIOS: &[(&str, f64)] = &[
+  // Llama 3 uses a 128k tiktoken-derived vocabulary close to cl100k
+  ("llama-3", 1.02),
+  ("llama3", 1.02),
+  // Llama 2 and Code Llama use a 32k SentencePiece vocabulary
+  ("llama-2", 1.25),
+  ("llama2", 1.25),
+  ("codellama", 1.25),
+  ("llama", 1.02),
+  ("mistral", 1.20),
+  ("mixtral", 1.20),
+  ("codestral", 1.20),
+  ("qwen", 0.98),
+  ("deepseek", 1.05),
+];
+
+const DEFAULT_CLAUDE_MODEL: &str = "claude-sonnet-4-5";
+const DEFAULT_GEMINI_MODEL: &str = "gemini-2.5-flash";
+const REMOTE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Which tokenizer a `model` argument resolves to
+pub enum TokenModel {
+  O200k,
+  Cl100k,
+  Claude(String),
+  Gemini(String),
+  /// A Hugging Face `tokenizer.json` registered for the model in settings
+  HuggingFace(PathBuf),
+  /// cl100k scaled by a documented ratio for the model family
+  Approximate(f64),
+}
+
+impl TokenModel {
+  /// Resolve a model or encoding name. A `tokenizer.json` registered in
+  /// `settings` for the model (or the longest registered prefix of it) takes
+  /// precedence; unknown names use the default encoding.
+  pub fn parse(model: Option<&str>, settings: &AppSettings) -> Self {
+    let Some(model) = model.map(|m| m.trim().to_lowercase()).filter(|m| !m.is_empty()) else {
+      return TokenModel::O200k;
+    };
+
+    let registered = settings
+      .tokenizer_files
+      .iter()
+      .filter(|(name, _)| model.starts_with(name.as_str()))
+      .max_by_key(|(name, _)| name.len());
+    if let Some((_, path)) = registered {
+      return TokenModel::HuggingFace(PathBuf::from(path));
+    }
+
+    if model.starts_with("claude") {
+      let name = if model == "claude" { DEFAULT_CLAUDE_MODEL.to_string() } else { model };
+      return TokenModel::Claude(name);
+    }
+    if model.starts_with("gemini") {
+      let name = if model == "gemini" { DEFAULT_GEMINI_MODEL.to_string() } else { model };
+      return TokenModel::Gemini(name);
+    }
+    if let Some((_, ratio)) = FAMILY_RATIOS.iter().find(|(prefix, _)| model.starts_with(prefix)) {
+      return TokenModel::Approximate(*ratio);
+    }
+    if model.starts_with("cl100k")
+      || model.starts_with("gpt-3.5")
+      || model.starts_with("text-embedding")
+      || (model.starts_with("gpt-4") && !model.starts_with("gpt-4o") && !model.starts_with("gpt-4.1"))
+    {
+      return TokenModel::Cl100k;
+    }
+    TokenModel::O200k
+  }
+
+  /// Whether `count` gives an estimate rather than the model's own count.
+  /// Claude and Gemini counts are exact with an API key, unless the request
+  /// fails.
+  pub fn is_estimate(&self, settings: &AppSettings) -> bool {
+    match self {
+      TokenModel::Claude(_) => settings.anthropic_api_key.is_none(),
+      TokenModel::Gemini(_) => settings.gemini_api_key.is_none(),
+      TokenModel::Approximate(_) => true,
+      _ => false,
+    }
+  }
+}
+
+fn encoder(cache: &Lazy<Result<Arc<CoreBPE>, String>>) -> Result<Arc<CoreBPE>, String> {
+  cache.as_ref().map(Arc::clone).map_err(|e| e.clone())
+}
+
+/// Encode with chat-template markers such as `<|im_start|>` counted as single
+/// special tokens when `special_tokens` is set, or as plain text otherwise
+fn encode_len(encoder: &CoreBPE, text: &str, special_tokens: bool) -> usize {
+  if special_tokens {
+    encoder.encode_with_special_tokens(text).len()
+  } else {
+    encoder.encode_ordinary(text).len()
+  }
+}
+
+static SPECIAL_TOKEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"<\|[A-Za-z0-9_]+\|>").expect("valid regex"));
+
+/// Distinct special-token-like sequences (`<|...|>`) found in `text`, in order of appearance
+pub fn detect_special_tokens(text: &str) -> Vec<String> {
+  let mut found: Vec<String> = Vec::new();
+  for m in SPECIAL_TOKEN.find_iter(text) {
+    if !found.iter().any(|f| f == m.as_str()) {
+      found.push(m.as_str().to_string());
+    }
+  }
+  found
+}
+
+/// Loaded Hugging Face tokenizers, keyed by file
+static HF_TOKENIZERS: Lazy<Mutex<HashMap<PathBuf, Arc<Tokenizer>>>> = Lazy::new(Default::default);
+
+/// Load (or reuse) the `tokenizer.json` at `path`
+pub fn hf_tokenizer(path: &Path) -> Result<Arc<Tokenizer>, String> {
+  if let Some(tokenizer) = HF_TOKENIZERS.lock().unwrap_or_else(|e| e.into_inner()).get(path) {
+    return Ok(Arc::clone(tokenizer));
+  }
+  let tokenizer = Tokenizer::from_file(path)
+    .map(Arc::new)
+    .map_err(|e| format!("failed to load tokenizer {}: {e}", path.display()))?;
+  HF_TOKENIZERS
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .insert(path.to_path_buf(), Arc::clone(&tokenizer));
+  Ok(tokenizer)
+}
+
+fn scaled(count: usize, ratio: f64) -> usize {
+  (count as f64 * ratio).round() as usize
+}
+
+/// Count tokens for `text` as `model` would see it. Claude and Gemini use the
+/// official counting endpoints when an API key is configured and fall back to
+/// the offline approximation otherwise (or when the request fails).
+pub fn count(model: &TokenModel, text: &str, settings: &AppSettings, special_tokens: bool) -> Result<usize, String> {
+  match model {
+    TokenModel::O200k => Ok(encode_len(encoder(&TOKENIZER)?.as_ref(), text, special_tokens)),
+    TokenModel::Cl100k => Ok(encode_len(encoder(&CL100K)?.as_ref(), text, special_tokens)),
+    TokenModel::Claude(name) => {
+      if let Some(key) = settings.anthropic_api_key.as_deref() {
+        match count_anthropic(key, name, text) {
+          Ok(count) => return Ok(count),
+          Err(e) => log::warn!("Anthropic token counting failed, using estimate: {}", e),
+        }
+      }
+      Ok(scaled(encode_len(encoder(&CL100K)?.as_ref(), text, special_tokens), CLAUDE_PER_CL100K))
+    }
+    TokenModel::Gemini(name) => {
+      if let Some(key) = settings.gemini_api_key.as_deref() {
+        match count_gemini(key, name, text) {
+          Ok(count) => return Ok(count),
+          Err(e) => log::warn!("Gemini token counting failed, using estimate: {}", e),
+        }
+      }
+      Ok(scaled(encode_len(encoder(&TOKENIZER)?.as_ref(), text, special_tokens), GEMINI_PER_O200K))
+    }
+    TokenModel::HuggingFace(path) => {
+      let tokenizer = hf_tokenizer(path)?;
+      let encoding = tokenizer
+        .encode(text, false)
+        .map_err(|e| format!("failed to tokenize with {}: {e}", path.display()))?;
+      Ok(encoding.len())
+    }
+    TokenModel::Approximate(ratio) => Ok(scaled(encode_len(encoder(&CL100K)?.as_ref(), text, special_tokens), *ratio)),
+  }
+}
+
+/// One token of an `encode_preview`
+#[derive(serde::Serialize)]
+pub struct TokenPiece {
+  pub id: u32,
+  /// Byte range of the token in the text
+  pub start: usize,
+  pub end: usize,
+  /// The token's text; a token holding part of a character shows it as `\u{fffd}`
+  pub text: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct EncodePreview {
+  pub tokens: Vec<TokenPiece>,
+  /// The text has more tokens than were returned
+  pub truncated: bool,
+  /// The model has no local tokenizer, so the tokens are those of the
+  /// encoding its count is estimated from
+  pub approximate: bool,
+}
+
+/// Bytes of text encoded per requested token; a prefix this long almost
+/// always holds more tokens than asked for
+const PREVIEW_BYTES_PER_TOKEN: usize = 32;
+
+/// The first `limit` tokens `model` splits `text` into, with their offsets.
+/// Only a prefix of long texts is encoded.
+pub fn encode_preview(model: &TokenModel, text: &str, limit: usize) -> Result<EncodePreview, String> {
+  let mut end = text.len().min(limit.saturating_mul(PREVIEW_BYTES_PER_TOKEN));
+  while !text.is_char_boundary(end) {
+    end -= 1;
+  }
+  let prefix = &text[..end];
+  let cut = end < text.len();
+
+  let (mut tokens, approximate): (Vec<TokenPiece>, bool) = match model {
+    TokenModel::HuggingFace(path) => {
+      let encoding = hf_tokenizer(path)?
+        .encode(prefix, false)
+        .map_err(|e| format!("failed to tokenize with {}: {e}", path.display()))?;
+      let tokens = encoding
+        .get_ids()
+        .iter()
+        .zip(encoding.get_offsets())
+        .map(|(&id, &(start, end))| TokenPiece {
+          id,
+          start,
+          end,
+          text: prefix.get(start..end).unwrap_or_default().to_string(),
+        })
+        .collect();
+      (tokens, false)
+    }
+    model => {
+      let (encoder, approximate) = match model {
+        TokenModel::O200k => (encoder(&TOKENIZER)?, false),
+        TokenModel::Cl100k => (encoder(&CL100K)?, false),
+        TokenModel::Gemini(_) => (encoder(&TOKENIZER)?, true),
+        _ => (encoder(&CL100K)?, true),
+      };
+      let mut start = 0;
+      let tokens = encoder
+        .encode_ordinary(prefix)
+        .into_iter()
+        .map(|id| {
+          let bytes = encoder._decode_native(&[id]);
+          let piece = TokenPiece {
+            id: id as u32,
+            start,
+            end: start + bytes.len(),
+            text: String::from_utf8_lossy(&bytes).into_owned(),
+          };
+          start = piece.end;
+          piece
+        })
+        .collect();
+      (tokens, approximate)
+    }
+  };
+
+  // The last token of a cut prefix may continue past the cut
+  if cut {
+    tokens.pop();
+  }
+  let truncated = cut || tokens.len() > limit;
+  tokens.truncate(limit);
+  Ok(EncodePreview {
+    tokens,
+    truncated,
+    approximate,
+  })
+}
+
+/// The count of one model in `compare`
+#[derive(serde::Serialize)]
+pub struct ModelCount {
+  pub model: String,
+  /// Unset when the model's tokenizer could not be used
+  pub tokens: Option<usize>,
+  pub estimated: bool,
+  pub error: Option<String>,
+}
+
+/// Count `text` for each of `models` in parallel; a model that fails only
+/// gets an error of its own
+pub fn compare(models: &[String], text: &str, settings: &AppSettings, special_tokens: bool) -> Vec<ModelCount> {
+  models
+    .par_iter()
+    .map(|name| {
+      let model = TokenModel::parse(Some(name), settings);
+      let counted = count(&model, text, settings, special_tokens);
+      ModelCount {
+        model: name.clone(),
+        estimated: model.is_estimate(settings),
+        tokens: counted.as_ref().ok().copied(),
+        error: counted.err(),
+      }
+    })
+    .collect()
+}
+
+/// Target size of the pieces `count_in_pieces` counts one at a time
+pub const COUNT_PIECE_SIZE: usize = 256 * 1024;
+
+/// `text` cut into pieces of about `target` bytes, each cut made after a line
+/// break that a non-whitespace character follows. Pre-tokenization splits
+/// there anyway, so for the BPE encodings the counts of the pieces add up to
+/// the count of the whole text.
+fn pieces(text: &str, target: usize) -> Vec<&str> {
+  let mut pieces = Vec::new();
+  let mut rest = text;
+  while rest.len() > target {
+    let mut from = target;
+    while !rest.is_char_boundary(from) {
+      from += 1;
+    }
+    let cut = rest[from..]
+      .match_indices('\n')
+      .map(|(i, _)| from + i + 1)
+      .find(|&cut| rest[cut..].starts_with(|c: char| !c.is_whitespace()));
+    let Some(cut) = cut else {
+      break;
+    };
+    pieces.push(&rest[..cut]);
+    rest = &rest[cut..];
+  }
+  pieces.push(rest);
+  pieces
+}
+
+/// `count` one piece of `text` at a time, calling `on_piece` with the bytes
+/// and tokens counted so far after each; `None` when `on_piece` returned
+/// `false` to stop
+pub fn count_in_pieces(
+  model: &TokenModel,
+  text: &str,
+  settings: &AppSettings,
+  special_tokens: bool,
+  mut on_piece: impl FnMut(usize, usize) -> bool,
+) -> Result<Option<usize>, String> {
+  let mut bytes = 0;
+  let mut tokens = 0;
+  for piece in pieces(text, COUNT_PIECE_SIZE) {
+    tokens += count(model, piece, settings, special_tokens)?;
+    bytes += piece.len();
+    if !on_piece(bytes, tokens) {
+      return Ok(None);
+    }
+  }
+  Ok(Some(tokens))
+}
+
+fn count_anthropic(api_key: &str, model: &str, text: &str) -> Result<usize, String> {
+  let response: serde_json::Value = ureq::post("https://api.anthropic.com/v1/messages/count_tokens")
+    .timeout(REMOTE_TIMEOUT)
+    .set("x-api-key", api_key)
+    .set("anthropic-version", "2023-06-01")
+    .send_json(serde_json::json!({
+      "model": model,
+      "messages": [{ "role": "user", "content": text }],
+    }))
+    .map_err(|e| e.to_string())?
+    .into_json()
+    .map_err(|e| e.to_string())?;
+
+  response["input_tokens"]
+    .as_u64()
+    .map(|n| n as usize)
+    .ok_or_else(|| "response is missing input_tokens".to_string())
+}
+
+fn count_gemini(api_key: &str, model: &str, text: &str) -> Result<usize, String> {
+  // The name goes into the URL path, so nothing but a model id may pass
+  if model.is_empty() || !model.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')) {
+    return Err(format!("invalid Gemini model name: {model}"));
+  }
+  let url = format!("https://generativelanguage.googleapis.com/v1beta/models/{model}:countTokens");
+  let response: serde_json::Value = ureq::post(&url)
+    .timeout(REMOTE_TIMEOUT)
+    .set("x-goog-api-key", api_key)
+    .send_json(serde_json::json!({
+      "contents": [{ "parts": [{ "text": text }] }],
+    }))
+    .map_err(|e| e.to_string())?
+    .into_json()
+    .map_err(|e| e.to_string())?;
+
+  response["totalTokens"]
+    .as_u64()
+    .map(|n| n as usize)
+    .ok_or_else(|| "response is missing totalTokens".to_string())
+}
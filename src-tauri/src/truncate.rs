@@ -0,0 +1,127 @@
+use std::borrow::Cow;
+
+use crate::outline;
+use crate::tokens::TOKENIZER;
+
+/// How a file over its token budget is cut down
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(tag = "strategy", rename_all = "kebab-case")]
+pub enum Truncation {
+  /// Keep the start of the file
+  Head { max_tokens: usize },
+  /// Keep the start and the end, half the budget each
+  HeadTail { max_tokens: usize },
+  /// Keep the first `lines` lines of every function body, then cut the
+  /// result down as `HeadTail` if it is still too long. Languages without
+  /// an outline grammar go straight to `HeadTail`.
+  FunctionHeads { max_tokens: usize, lines: usize },
+}
+
+/// The line standing in for `count` removed lines
+fn marker(indent: &str, count: usize) -> String {
+  format!("{indent}... [{count} lines truncated]\n")
+}
+
+/// `lines` of `content` (with their line breaks), each with its token count
+fn lines_with_tokens(content: &str) -> Vec<(&str, usize)> {
+  let encoder = TOKENIZER.as_ref().ok();
+  content
+    .split_inclusive('\n')
+    .map(|line| (line, encoder.map_or(line.len() / 4, |encoder| encoder.encode_ordinary(line).len())))
+    .collect()
+}
+
+/// The first lines of `content` within `max_tokens`, and the last within
+/// `tail_tokens`, joined by a marker line
+fn head_tail(content: &str, max_tokens: usize, tail_tokens: usize) -> String {
+  let lines = lines_with_tokens(content);
+  let total: usize = lines.iter().map(|(_, tokens)| tokens).sum();
+  if total <= max_tokens {
+    return content.to_string();
+  }
+  let head_budget = max_tokens.saturating_sub(tail_tokens);
+  let mut used = 0;
+  let head = lines
+    .iter()
+    .take_while(|(_, tokens)| {
+      used += tokens;
+      used <= head_budget
+    })
+    .count();
+  let mut used = 0;
+  let tail = lines[head..]
+    .iter()
+    .rev()
+    .take_while(|(_, tokens)| {
+      used += tokens;
+      used <= tail_tokens
+    })
+    .count();
+
+  let mut out: String = lines[..head].iter().map(|(line, _)| *line).collect();
+  if !out.is_empty() && !out.ends_with('\n') {
+    out.push('\n');
+  }
+  out.push_str(&marker("", lines.len() - head - tail));
+  out.extend(lines[lines.len() - tail..].iter().map(|(line, _)| *line));
+  out
+}
+
+/// `content` with each function body in it cut to its first `keep` lines
+fn function_heads(content: &str, extension: &str, keep: usize) -> Option<String> {
+  let bodies = outline::function_bodies(content, extension)?;
+  let lines: Vec<&str> = content.split_inclusive('\n').collect();
+  // Row ranges to drop; a nested body inside a dropped range is gone already
+  let mut cuts: Vec<(usize, usize)> = Vec::new();
+  for body in bodies {
+    // Braces are kept on their own rows; statements between them count
+    let (first, last) = if body.braced {
+      (body.start + 1, body.end.saturating_sub(1))
+    } else {
+      (body.start, body.end)
+    };
+    let from = first + keep;
+    if from > last || cuts.last().is_some_and(|&(_, end)| from <= end) {
+      continue;
+    }
+    cuts.push((from, last));
+  }
+  if cuts.is_empty() {
+    return None;
+  }
+
+  let mut out = String::with_capacity(content.len());
+  let mut row = 0;
+  for (from, to) in cuts {
+    out.extend(lines[row..from].iter().copied());
+    let first = lines.get(from).copied().unwrap_or_default();
+    let indent = &first[..first.len() - first.trim_start().len()];
+    out.push_str(&marker(indent, to - from + 1));
+    row = to + 1;
+  }
+  out.extend(lines[row.min(lines.len())..].iter().copied());
+  Some(out)
+}
+
+/// `content` cut down as `truncation` says when it is over its budget
+pub fn apply<'a>(content: &'a str, extension: &str, truncation: &Truncation) -> Cow<'a, str> {
+  let max_tokens = match *truncation {
+    Truncation::Head { max_tokens } | Truncation::HeadTail { max_tokens } | Truncation::FunctionHeads { max_tokens, .. } => max_tokens,
+  };
+  let within = TOKENIZER
+    .as_ref()
+    .map_or(content.len() / 4, |encoder| encoder.encode_ordinary(content).len())
+    <= max_tokens;
+  if within {
+    return Cow::Borrowed(content);
+  }
+  Cow::Owned(match *truncation {
+    Truncation::Head { max_tokens } => head_tail(content, max_tokens, 0),
+    Truncation::HeadTail { max_tokens } => head_tail(content, max_tokens, max_tokens / 2),
+    Truncation::FunctionHeads { max_tokens, lines } => {
+      let cut = function_heads(content, extension, lines);
+      let cut = cut.as_deref().unwrap_or(content);
+      head_tail(cut, max_tokens, max_tokens / 2)
+    }
+  })
+}
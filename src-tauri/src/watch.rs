@@ -0,0 +1,238 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+use crate::scan::{self, FileInfo};
+use crate::store::{FileStore, LoadedFile};
+
+/// How long the first half of a split rename (`From` without `To`) waits for
+/// its partner before it is reported as a removal
+const RENAME_PAIR_WINDOW: Duration = Duration::from_millis(500);
+
+/// Emitted as `file-removed`
+#[derive(Clone, serde::Serialize)]
+pub struct FileRemoved {
+  pub path: String,
+  /// Ids of the loaded files at or below `path`, now forgotten
+  pub ids: Vec<String>,
+}
+
+/// Emitted as `file-renamed` when a watched file or directory is renamed or
+/// moved. For directories, every loaded file below `from` now lives below `to`.
+#[derive(Clone, serde::Serialize)]
+pub struct FileRenamed {
+  pub from: String,
+  pub to: String,
+  pub is_dir: bool,
+  /// The moved files as read from their new location, under the ids they were
+  /// loaded with; for a file that was not loaded, the file as loaded now
+  pub files: Vec<LoadedFile>,
+}
+
+/// Paths touched since the last `take_touched`
+type Touched = Arc<Mutex<BTreeSet<PathBuf>>>;
+
+/// Tauri-managed file watcher; replaced on every `watch_paths` call
+#[derive(Default)]
+pub struct WatchState {
+  watcher: Mutex<Option<RecommendedWatcher>>,
+  touched: Touched,
+}
+
+impl WatchState {
+  /// Watch `paths` recursively, emitting `file-added`, `file-changed`,
+  /// `file-removed` and `file-renamed` events. Any previous watch is stopped.
+  pub fn watch(&self, app_handle: tauri::AppHandle, paths: &[String]) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(|e| format!("failed to start file watcher: {e}"))?;
+    for path in paths {
+      watcher
+        .watch(Path::new(path), RecursiveMode::Recursive)
+        .map_err(|e| format!("failed to watch {path}: {e}"))?;
+    }
+
+    // The thread ends when the watcher (and with it the sender) is dropped
+    let touched = Arc::clone(&self.touched);
+    let roots = paths.iter().map(PathBuf::from).collect();
+    std::thread::spawn(move || {
+      Dispatcher {
+        app_handle,
+        roots,
+        pending_from: None,
+        touched,
+      }
+      .run(rx)
+    });
+
+    *self.watcher.lock().unwrap_or_else(|e| e.into_inner()) = Some(watcher);
+    Ok(())
+  }
+
+  pub fn stop(&self) {
+    self.watcher.lock().unwrap_or_else(|e| e.into_inner()).take();
+  }
+
+  /// Files added, changed, removed or renamed since the last call, which
+  /// clears them
+  pub fn take_touched(&self) -> Vec<PathBuf> {
+    std::mem::take(&mut *self.touched.lock().unwrap_or_else(|e| e.into_inner()))
+      .into_iter()
+      .collect()
+  }
+}
+
+/// Turns raw notify events into frontend events, pairing the two halves of
+/// renames that platforms report separately
+struct Dispatcher {
+  app_handle: tauri::AppHandle,
+  /// The watched paths
+  roots: Vec<PathBuf>,
+  pending_from: Option<(PathBuf, Instant)>,
+  touched: Touched,
+}
+
+impl Dispatcher {
+  /// Whether `path` is a dotfile or lies in a dot-directory below the watched
+  /// path it belongs to; the watched paths themselves may be anywhere
+  fn is_hidden(&self, path: &Path) -> bool {
+    let below = self
+      .roots
+      .iter()
+      .find_map(|root| path.strip_prefix(root).ok())
+      .unwrap_or(path);
+    below
+      .components()
+      .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+  }
+
+  fn run(mut self, rx: mpsc::Receiver<notify::Result<Event>>) {
+    loop {
+      match rx.recv_timeout(RENAME_PAIR_WINDOW) {
+        Ok(Ok(event)) => self.handle(event),
+        Ok(Err(e)) => log::warn!("File watcher error: {}", e),
+        Err(mpsc::RecvTimeoutError::Timeout) => self.flush_stale(),
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+          self.flush();
+          return;
+        }
+      }
+    }
+  }
+
+  fn handle(&mut self, event: Event) {
+    self.flush_stale();
+    let paths: Vec<PathBuf> = event.paths.into_iter().filter(|p| !self.is_hidden(p)).collect();
+
+    match event.kind {
+      EventKind::Modify(ModifyKind::Name(mode)) => match (mode, paths.as_slice()) {
+        (RenameMode::Both, [from, to]) => self.renamed(from, to),
+        (RenameMode::From, [from]) => self.rename_from(from),
+        (RenameMode::To, [to]) => self.rename_to(to),
+        // Some platforms only say that a name changed; the old name no longer exists
+        (_, [path]) if path.exists() => self.rename_to(path),
+        (_, [path]) => self.rename_from(path),
+        _ => paths.iter().for_each(|p| self.changed(p)),
+      },
+      EventKind::Create(_) => paths.iter().for_each(|p| self.added(p)),
+      EventKind::Modify(_) => paths.iter().for_each(|p| self.changed(p)),
+      EventKind::Remove(_) => paths.iter().for_each(|p| self.removed(p)),
+      _ => {}
+    }
+  }
+
+  fn rename_from(&mut self, from: &Path) {
+    self.flush();
+    self.pending_from = Some((from.to_path_buf(), Instant::now()));
+  }
+
+  fn rename_to(&mut self, to: &Path) {
+    match self.pending_from.take() {
+      Some((from, _)) => self.renamed(&from, to),
+      None => self.changed(to),
+    }
+  }
+
+  /// Report a rename half that never got its partner as a removal
+  fn flush_stale(&mut self) {
+    if self.pending_from.as_ref().is_some_and(|(_, at)| at.elapsed() >= RENAME_PAIR_WINDOW) {
+      self.flush();
+    }
+  }
+
+  fn flush(&mut self) {
+    if let Some((from, _)) = self.pending_from.take() {
+      self.removed(&from);
+    }
+  }
+
+  fn touch(&self, path: &Path) {
+    self.touched.lock().unwrap_or_else(|e| e.into_inner()).insert(path.to_path_buf());
+  }
+
+  fn renamed(&self, from: &Path, to: &Path) {
+    self.touch(from);
+    self.touch(to);
+    let store = self.app_handle.state::<FileStore>();
+    let from = from.to_string_lossy().into_owned();
+    let to_str = to.to_string_lossy().into_owned();
+    let moved = store.rename(&from, &to_str);
+    let files = if moved.is_empty() {
+      let read = scan::read_single_file(to).filter(|_| to.is_file());
+      store.reload(read.into_iter().collect()).files
+    } else {
+      let read: Vec<(Option<String>, FileInfo)> = moved
+        .iter()
+        .filter_map(|file| {
+          let mut info = scan::read_single_file(Path::new(&file.path))?;
+          file.keep_root(&mut info);
+          Some((Some(file.id.clone()), info))
+        })
+        .collect();
+      store.load_as(read)
+    };
+    let payload = FileRenamed {
+      from,
+      to: to_str,
+      is_dir: to.is_dir(),
+      files,
+    };
+    let _ = self.app_handle.emit("file-renamed", &payload);
+  }
+
+  /// Load the file at `path` into the store, in place of the version loaded
+  /// before, and emit it as `event` unless its content is unchanged
+  fn reload(&self, path: &Path, event: &str) {
+    if !path.is_file() {
+      return;
+    }
+    self.touch(path);
+    let Some(file) = scan::read_single_file(path) else {
+      return;
+    };
+    if let Some(loaded) = self.app_handle.state::<FileStore>().reload(vec![file]).files.pop() {
+      let _ = self.app_handle.emit(event, &loaded);
+    }
+  }
+
+  fn added(&self, path: &Path) {
+    self.reload(path, "file-added");
+  }
+
+  fn changed(&self, path: &Path) {
+    self.reload(path, "file-changed");
+  }
+
+  fn removed(&self, path: &Path) {
+    self.touch(path);
+    let path = path.to_string_lossy().into_owned();
+    let payload = FileRemoved {
+      ids: self.app_handle.state::<FileStore>().remove_path(&path),
+      path,
+    };
+    let _ = self.app_handle.emit("file-removed", &payload);
+  }
+}
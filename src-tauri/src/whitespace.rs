@@ -0,0 +1,90 @@
+use std::borrow::Cow;
+
+/// Which way leading indentation is converted
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(tag = "to", rename_all = "snake_case")]
+pub enum Retab {
+  /// Each tab becomes spaces up to the next multiple of `width`
+  Spaces { width: usize },
+  /// Each `width` columns of indentation become a tab
+  Tabs { width: usize },
+}
+
+/// Whitespace clean-up applied to a file before it is processed, so the
+/// tokens are not spent on `\r`, BOMs and uneven indentation
+#[derive(Clone, Copy, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct WhitespaceOptions {
+  /// Turn `\r\n` line endings into `\n`
+  pub normalize_line_endings: bool,
+  /// Drop a leading byte order mark
+  pub strip_bom: bool,
+  /// Convert indentation between tabs and spaces
+  pub retab: Option<Retab>,
+}
+
+impl WhitespaceOptions {
+  fn is_noop(&self) -> bool {
+    !self.normalize_line_endings && !self.strip_bom && self.retab.is_none()
+  }
+}
+
+/// Languages whose syntax needs tab indentation
+fn needs_tabs(extension: &str) -> bool {
+  matches!(extension, "makefile" | "mk")
+}
+
+fn leading_whitespace(line: &str) -> &str {
+  &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Visual width of `indent`, with tab stops every `width` columns
+fn columns(indent: &str, width: usize) -> usize {
+  indent.chars().fold(0, |column, c| match c {
+    '\t' => (column / width + 1) * width,
+    _ => column + 1,
+  })
+}
+
+fn retab(content: &str, retab: Retab) -> String {
+  content
+    .split_inclusive('\n')
+    .map(|line| {
+      let indent = leading_whitespace(line);
+      if indent.is_empty() || line.trim().is_empty() {
+        return Cow::Borrowed(line);
+      }
+      let replacement = match retab {
+        Retab::Spaces { width } => " ".repeat(columns(indent, width.max(1))),
+        Retab::Tabs { width } => {
+          let width = width.max(1);
+          let columns = columns(indent, width);
+          format!("{}{}", "\t".repeat(columns / width), " ".repeat(columns % width))
+        }
+      };
+      Cow::Owned(format!("{replacement}{}", &line[indent.len()..]))
+    })
+    .collect()
+}
+
+/// `content` cleaned up as `options` asks; `extension` keeps Makefiles
+/// indented with tabs
+pub fn normalize<'a>(content: &'a str, extension: &str, options: &WhitespaceOptions) -> Cow<'a, str> {
+  if options.is_noop() {
+    return Cow::Borrowed(content);
+  }
+  let content = if options.strip_bom {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+  } else {
+    content
+  };
+  let mut text = Cow::Borrowed(content);
+  if options.normalize_line_endings && text.contains("\r\n") {
+    text = Cow::Owned(text.replace("\r\n", "\n"));
+  }
+  match options.retab {
+    Some(to @ Retab::Spaces { .. }) if text.contains('\t') && !needs_tabs(extension) => Cow::Owned(retab(&text, to)),
+    Some(to @ Retab::Tabs { .. }) if text.contains(' ') => Cow::Owned(retab(&text, to)),
+    _ => text,
+  }
+}
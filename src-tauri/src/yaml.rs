@@ -0,0 +1,128 @@
+//! YAML comment removal. A `#` only starts a comment after whitespace or at
+//! the start of a line, outside quoted scalars, and never inside a `|` or `>`
+//! block scalar, so URLs, anchors in plain scalars and embedded scripts
+//! survive.
+
+/// Whether `content`, a line without its comment, ends in a block scalar
+/// header: `|` or `>`, possibly with chomping and indentation indicators,
+/// after a key, a sequence dash, a tag or an anchor
+fn opens_block_scalar(content: &str) -> bool {
+  let header = content
+    .trim_end()
+    .trim_end_matches(|c: char| c == '-' || c == '+' || c.is_ascii_digit());
+  let Some(before) = header.strip_suffix(['|', '>']) else {
+    return false;
+  };
+  let before = before.trim_end();
+  let last_token = before.rsplit([' ', '\t']).next().unwrap_or_default();
+  before.is_empty() || before.ends_with([':', '-', '?']) || last_token.starts_with(['!', '&'])
+}
+
+/// Byte ranges of the comments in `code`
+pub fn comment_ranges(code: &str) -> Vec<std::ops::Range<usize>> {
+  scan(code).0
+}
+
+/// Byte ranges of the contents of the `|` and `>` block scalars in `code`,
+/// from the line after their header
+pub fn block_scalar_ranges(code: &str) -> Vec<std::ops::Range<usize>> {
+  scan(code).1
+}
+
+/// The comments and the block scalar contents of `code`
+fn scan(code: &str) -> (Vec<std::ops::Range<usize>>, Vec<std::ops::Range<usize>>) {
+  let mut ranges = Vec::new();
+  let mut blocks = Vec::new();
+  // Quote of a scalar still open at the end of the previous line
+  let mut quote: Option<u8> = None;
+  // Indentation of the line that opened the block scalar being skipped, and
+  // where its content starts
+  let mut block: Option<(usize, usize)> = None;
+  let mut offset = 0;
+
+  for line in code.split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    let text = line.trim_end_matches(['\r', '\n']);
+    let indent = text.len() - text.trim_start().len();
+    if let Some((parent, block_start)) = block {
+      if text.trim().is_empty() || indent > parent {
+        continue;
+      }
+      blocks.push(block_start..start);
+      block = None;
+    }
+
+    let bytes = text.as_bytes();
+    let mut comment = None;
+    // Last character outside whitespace, to tell a quoted scalar from an
+    // apostrophe inside a plain one
+    let mut previous: Option<u8> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+      let b = bytes[i];
+      match quote {
+        Some(b'"') => match b {
+          b'\\' => i += 1,
+          b'"' => quote = None,
+          _ => {}
+        },
+        Some(_) => {
+          if b == b'\'' {
+            // `''` is an escaped quote
+            if bytes.get(i + 1) == Some(&b'\'') {
+              i += 1;
+            } else {
+              quote = None;
+            }
+          }
+        }
+        None => {
+          let after_space = i == 0 || matches!(bytes[i - 1], b' ' | b'\t');
+          match b {
+            b'#' if after_space => {
+              comment = Some(i);
+              break;
+            }
+            b'"' | b'\''
+              if (after_space || matches!(bytes[i - 1], b'[' | b'{' | b','))
+                && matches!(previous, None | Some(b':' | b'-' | b',' | b'[' | b'{' | b'?')) =>
+            {
+              quote = Some(b)
+            }
+            _ => {}
+          }
+        }
+      }
+      if !matches!(b, b' ' | b'\t') {
+        previous = Some(b);
+      }
+      i += 1;
+    }
+
+    if let Some(at) = comment {
+      ranges.push(start + at..start + text.len());
+    }
+    if quote.is_none() && opens_block_scalar(&text[..comment.unwrap_or(text.len())]) {
+      block = Some((indent, offset));
+    }
+  }
+  if let Some((_, block_start)) = block {
+    blocks.push(block_start..code.len());
+  }
+  (ranges, blocks)
+}
+
+/// `code` without the comments `removes` accepts, given their text
+pub fn strip_comments(code: &str, removes: impl Fn(&str) -> bool) -> String {
+  let mut out = String::with_capacity(code.len());
+  let mut copied = 0;
+  for range in comment_ranges(code) {
+    if removes(&code[range.clone()]) {
+      out.push_str(&code[copied..range.start]);
+      copied = range.end;
+    }
+  }
+  out.push_str(&code[copied..]);
+  out
+}
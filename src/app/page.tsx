@@ -52,7 +52,7 @@ import { useSettings } from '@/hooks/useSettings';
 
 // Services & Utils
 import { processFileObject, unzipAndProcess } from '@/lib/file-processing';
-import { getFilesFromEvent } from '@/lib/dropzone-utils';
+import { getFilesFromEvent, forgetBackendFiles } from '@/lib/dropzone-utils';
 import { buildFileTree, generateAsciiTree } from '@/lib/file-tree';
 import { scanForSecrets, SecurityIssue } from '@/lib/security';
 import {
@@ -408,7 +408,7 @@ function Contextractor() {
                         is_text: true, // Rust expects snake_case
                     }));
 
-                    const processedFiles: { id: string, content: string }[] = await invoke('process_files_with_progress', {
+                    const { files: processedFiles }: { files: { id: string, content: string }[], cancelled: boolean } = await invoke('process_files_with_progress', {
                         files: fileInputs,
                         mode: codeProcessingMode
                     });
@@ -627,6 +627,21 @@ function Contextractor() {
         };
     }, []);
 
+    // Forget backend copies of files no session lists any more, whether they
+    // were removed, cleared, replaced or their session closed
+    const backendIdsRef = useRef<Set<string>>(new Set());
+    useEffect(() => {
+        const listed = new Set<string>();
+        for (const session of sessions) {
+            for (const file of session.files) {
+                if (file.backendId) listed.add(file.backendId);
+            }
+        }
+        const gone = [...backendIdsRef.current].filter(id => !listed.has(id));
+        backendIdsRef.current = listed;
+        void forgetBackendFiles(gone);
+    }, [sessions]);
+
     // Wrapped close session that also clears the processing cache
     const handleCloseSession = useCallback((sessionId: string) => {
         clearSessionCache(sessionId);
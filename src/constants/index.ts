@@ -21,16 +21,19 @@ export const TEXT_FILE_EXTENSIONS = new Set([
     'go', 'mod', 'sum', 'rs', 'zig', 'nim', 'cr',
     
     // Functional languages
-    'hs', 'lhs', 'ml', 'mli', 'fs', 'fsx', 'fsi', 'ex', 'exs', 'eex', 'heex', 'erl', 'hrl',
+    'hs', 'lhs', 'ml', 'mli', 'fs', 'fsx', 'fsi', 'ex', 'exs', 'eex', 'heex', 'erl', 'hrl', 'nims', 'nimble',
     
     // Scientific
-    'r', 'rmd', 'jl', 'm', 'mat', 'f', 'f90', 'f95', 'for',
+    'r', 'rmd', 'jl', 'm', 'mat', 'f', 'f77', 'f90', 'f95', 'f03', 'f08', 'for',
+
+    // Legacy
+    'cob', 'cbl', 'vb', 'vba', 'bas', 'cls',
     
     // Web scripting
     'php', 'phtml', 'rb', 'erb', 'rake', 'gemspec', 'pl', 'pm', 'pod', 't', 'lua',
     
     // Shell
-    'sh', 'bash', 'zsh', 'fish', 'ksh', 'csh', 'tcsh', 'bat', 'cmd', 'ps1', 'psm1', 'psd1',
+    'sh', 'bash', 'zsh', 'fish', 'ksh', 'csh', 'tcsh', 'bat', 'cmd', 'ps1', 'psm1', 'psd1', 'vim',
     
     // Mobile
     'swift', 'dart', 'arb',
@@ -63,13 +66,16 @@ export const TEXT_FILE_EXTENSIONS = new Set([
     'md', 'mdx', 'markdown', 'rst', 'adoc', 'asciidoc', 'txt', 'log', 'license',
     
     // LaTeX
-    'tex', 'latex', 'bib',
+    'tex', 'latex', 'sty', 'ltx', 'dtx', 'bib',
     
     // Shaders
     'glsl', 'vert', 'frag', 'hlsl', 'shader',
     
     // Assembly
     'asm', 's', 'nasm',
+
+    // Hardware description
+    'v', 'vh', 'sv', 'svh', 'vhd', 'vhdl',
     
     // Build
     'makefile', 'mk', 'cmake', 'meson', 'ninja',
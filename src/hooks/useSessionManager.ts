@@ -48,6 +48,7 @@ export const fileDataToSessionFile = (file: FileData): SessionFile => ({
     characterCount: file.characterCount,
     tokenCount: file.tokenCount,
     path: file.path,
+    backendId: file.backendId,
 });
 
 // Global cache for converted FileData per session
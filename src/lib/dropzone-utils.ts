@@ -24,12 +24,29 @@ const getTauriInvoke = async (): Promise<TauriInvoke | null> => {
     }
 };
 
-// File info from Tauri backend
+// File metadata from Tauri backend; the content stays in the backend under `id`
 interface TauriFileInfo {
+    id: string;
     name: string;
     path: string;
-    content: string;
     is_text: boolean;
+    size: number;
+    modified_ms: number | null;
+    language: string | null;
+    generated: boolean;
+    root: string | null;
+    relative_path: string | null;
+    // Type and image size of a non-text file, described in its content
+    binary: { mime: string | null; width: number | null; height: number | null; description: string } | null;
+    duplicate_of: string | null;
+    line_count: number;
+    content_hash: string;
+}
+
+// A file the backend left out of a load, and why
+interface TauriSkippedFile {
+    path: string;
+    reason: 'too_large' | 'unreadable' | 'binary' | 'hidden' | 'generated' | 'already_loaded';
 }
 
 /**
@@ -108,8 +125,8 @@ const extractPathsFromPlainText = (text: string): string[] => {
 /**
  * Convert Tauri file info to File object
  */
-const tauriFileToFile = (info: TauriFileInfo): File => {
-    const blob = new Blob([info.content], { type: 'text/plain' });
+const tauriFileToFile = (info: TauriFileInfo, content: string): File => {
+    const blob = new Blob([content], { type: 'text/plain' });
     const file = new File([blob], info.name, { type: 'text/plain' });
 
     // Add path property
@@ -122,6 +139,57 @@ const tauriFileToFile = (info: TauriFileInfo): File => {
     return file;
 };
 
+/**
+ * A loaded file whose content stays in the backend `FileStore` under
+ * `backendId` until something reads it, so a drop only moves metadata
+ * over IPC. The content is fetched once, on the first read.
+ */
+export class BackendFile extends File {
+    readonly backendId: string;
+    private readonly byteSize: number;
+    private content: Promise<string> | null = null;
+
+    constructor(info: TauriFileInfo) {
+        super([], info.name, { type: 'text/plain', lastModified: info.modified_ms ?? Date.now() });
+        this.backendId = info.id;
+        this.byteSize = info.size;
+    }
+
+    get size(): number {
+        return this.byteSize;
+    }
+
+    text(): Promise<string> {
+        if (!this.content) {
+            const id = this.backendId;
+            this.content = getTauriInvoke().then(invoke => {
+                if (!invoke) throw new Error('backend unavailable');
+                return invoke<string>('get_file_content', { id });
+            });
+        }
+        return this.content;
+    }
+
+    async arrayBuffer(): Promise<ArrayBuffer> {
+        return new TextEncoder().encode(await this.text()).buffer as ArrayBuffer;
+    }
+}
+
+/**
+ * Drop files from the backend `FileStore` once the frontend no longer lists
+ * them, so their content is not kept twice
+ */
+export const forgetBackendFiles = async (ids: string[]): Promise<void> => {
+    if (ids.length === 0) return;
+    const invoke = await getTauriInvoke();
+    if (!invoke) return;
+    try {
+        await invoke('forget_files', { fileIds: ids });
+    } catch (error) {
+        console.warn('[Dropzone] Failed to forget files:', error);
+    }
+};
+
 /**
  * Read files from paths using Tauri backend
  */
@@ -131,7 +199,10 @@ const readFilesViaTauri = async (paths: string[]): Promise<File[]> => {
 
     try {
         console.log('[Dropzone] Reading files via Tauri:', paths);
-        const fileInfos = await invoke<TauriFileInfo[]>('read_files_from_paths', { paths });
+        const { files: fileInfos, skipped } = await invoke<{ files: TauriFileInfo[], skipped: TauriSkippedFile[] }>('read_files_from_paths', { paths });
+        if (skipped.length > 0) {
+            console.warn('[Dropzone] Skipped files:', skipped);
+        }
 
         // Calculate common root for relative paths
         let commonRoot = '';
@@ -171,8 +242,12 @@ const readFilesViaTauri = async (paths: string[]): Promise<File[]> => {
         console.log('[Dropzone] Common root:', commonRoot);
         console.log('[Dropzone] Base to strip:', baseDirToStrip);
 
-        const files = fileInfos
-            .filter(info => info.is_text && info.content)
+        // Only metadata crosses IPC on load; each content is fetched when read
+        const textInfos = fileInfos.filter(info => info.is_text && info.size > 0);
+        const leftOut = fileInfos.filter(info => !textInfos.includes(info)).map(info => info.id);
+        void forgetBackendFiles(leftOut);
+
+        const files = textInfos
             .map(info => {
                 // Calculate relative path
                 const normalizedPath = info.path.replace(/\\/g, '/');
@@ -190,8 +265,7 @@ const readFilesViaTauri = async (paths: string[]): Promise<File[]> => {
                 if (relativePath.startsWith('/')) relativePath = relativePath.substring(1);
 
                 // Create file manually (do NOT use tauriFileToFile to avoid double definition of path)
-                const blob = new Blob([info.content], { type: 'text/plain' });
-                const file = new File([blob], info.name, { type: 'text/plain' });
+                const file = new BackendFile(info);
 
                 // Add path property safely
                 Object.defineProperty(file, 'path', {
@@ -2,6 +2,7 @@ import JSZip from 'jszip';
 import { calculateTokens } from '@/lib/tokenWorker';
 import { FileData } from "@/types";
 import { TEXT_FILE_EXTENSIONS } from "@/constants";
+import { BackendFile } from "@/lib/dropzone-utils";
 
 interface FileWithPaths extends Blob {
     path?: string;
@@ -60,7 +61,8 @@ export const processFileObject = async (fileObject: File | Blob, explicitPath: s
         linesOfCode: lines,
         characterCount: chars,
         tokenCount: tokens,
-        path: fullPath
+        path: fullPath,
+        backendId: fileObject instanceof BackendFile ? fileObject.backendId : undefined
     };
 };
 
@@ -10,6 +10,8 @@ export interface FileData {
     characterCount: number;
     tokenCount: number;
     path: string;
+    // Id of the content in the backend FileStore, for files loaded through it
+    backendId?: string;
 }
 
 export interface TreeNode {
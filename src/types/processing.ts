@@ -5,4 +5,8 @@ export interface ProcessingProgress {
     processed_bytes: number;
     total_bytes: number;
     tokens_saved: number;
+    /** Sent by the backend; rate averaged over the last few seconds */
+    elapsed_ms?: number;
+    bytes_per_second?: number;
+    eta_ms?: number | null;
 }
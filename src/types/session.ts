@@ -33,6 +33,7 @@ export interface SessionFile {
     characterCount: number;
     tokenCount: number;
     path: string;
+    backendId?: string;
 }
 
 export interface RecentProject {